@@ -1,10 +1,16 @@
-use crate::config::Config;
+use crate::config::{
+    ComponentConfig, Config, FilterConfig, Merge, PROFILE_ENV, RootsConfig, RulesConfig,
+    ScanConfig, Sources, env_config,
+};
 use crate::error::TracyError;
 use crate::filter::FilterArgs;
-use crate::output::OutputFormat;
+use crate::git::{BlameArgs, GitBackend};
+use crate::output::{FormatOptions, GraphCollapse, OutputFormat, TemplateSource};
 use crate::scan::ScanArgs;
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -13,55 +19,402 @@ use std::path::{Path, PathBuf};
     about = "Scan codebases for requirement references in comments and output results"
 )]
 pub struct Args {
-    #[arg(long, help = "Root directory to scan (default: config dir or '.')")]
-    pub root: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-    #[arg(long, value_enum, help = "Output format")]
+    #[arg(
+        long,
+        global = true,
+        value_name = "[LABEL=]PATH",
+        help = "Root directory to scan, optionally labeled (repeatable; default: config dir or '.')"
+    )]
+    pub root: Vec<RootArg>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "COMMIT",
+        help = "Scan a git revision (read from the object database, working tree untouched)"
+    )]
+    pub rev: Option<String>,
+
+    #[arg(long, global = true, value_enum, help = "Output format")]
     pub format: Option<OutputFormat>,
 
     #[arg(
         long,
+        global = true,
         value_name = "PATH",
-        help = "Path to config file (default: search for tracy.toml)"
+        help = "Path to config file (default: search for tracy.toml or a package manifest)"
     )]
     pub config: Option<PathBuf>,
 
-    #[arg(long, help = "Disable config file loading")]
+    #[arg(long, global = true, help = "Disable config file loading")]
     pub no_config: bool,
 
-    #[arg(short, long, help = "Write output to file (in addition to stdout)")]
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Apply the [profile.NAME] section of the config (default: $TRACY_PROFILE)"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "Write output to file (in addition to stdout)"
+    )]
     pub output: Option<PathBuf>,
 
-    #[arg(short, long, help = "Suppress stdout output")]
+    #[arg(short, long, global = true, help = "Suppress stdout output")]
     pub quiet: bool,
 
-    #[arg(long, help = "Exit with error if no matches found")]
+    #[arg(long, global = true, help = "Exit with error if no matches found")]
     pub fail_on_empty: bool,
 
-    #[arg(long, help = "Include git repository metadata in output")]
+    #[arg(
+        long,
+        global = true,
+        help = "Include git repository metadata in output"
+    )]
     pub include_git_meta: bool,
 
-    #[arg(long, help = "Include git blame metadata for each match")]
+    #[arg(
+        long,
+        global = true,
+        help = "Include git blame metadata for each match"
+    )]
     pub include_blame: bool,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Include a forge permalink for each match, pinned to the scanned commit"
+    )]
+    pub include_permalinks: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        help = "Permalink preset (github, gitlab, gitea, bitbucket) or url template [default: inferred from the origin remote]"
+    )]
+    pub permalink_template: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "How to access git: shell out to `git` or use the in-process backend"
+    )]
+    pub git_backend: Option<GitBackend>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "How --slug, --include and --exclude combine with the config's lists [default: replace]"
+    )]
+    pub merge: Option<Merge>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "BYTES",
+        help = "Truncate markdown output to fit in this many bytes, 0 for no limit [default: 65536]"
+    )]
+    pub markdown_limit: Option<usize>,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        help = "Merge the code nodes of dot and mermaid graphs into their directory or component"
+    )]
+    pub graph_collapse: Option<GraphCollapse>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Show N levels of directories, file and scopes in dot and mermaid graphs"
+    )]
+    pub graph_depth: Option<usize>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        help = "Template file the template format renders, or builtin:<name> (plain, markdown-list, asciidoc)"
+    )]
+    pub template: Option<TemplateSource>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        help = "Link to each requirement, with {id} and {slug} replaced, e.g. https://tracker.example.com/browse/{id}"
+    )]
+    pub requirement_url: Option<String>,
+
+    #[command(flatten)]
+    pub negated: NegatedFlags,
+
     #[command(flatten)]
     pub filter: FilterArgs,
 
     #[command(flatten)]
     pub scan: ScanArgs,
+
+    #[command(flatten)]
+    pub blame: BlameArgs,
+}
+
+/// `--no-<flag>` for each boolean flag, turning off what the config or the
+/// environment turned on; the last of a flag and its negation wins.
+#[derive(ClapArgs, Debug, Clone, Default)]
+pub struct NegatedFlags {
+    #[arg(long, global = true, overrides_with = "quiet", help = "Undo --quiet")]
+    pub no_quiet: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "fail_on_empty",
+        help = "Undo --fail-on-empty"
+    )]
+    pub no_fail_on_empty: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_git_meta",
+        help = "Undo --include-git-meta"
+    )]
+    pub no_include_git_meta: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_blame",
+        help = "Undo --include-blame"
+    )]
+    pub no_include_blame: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_permalinks",
+        help = "Undo --include-permalinks"
+    )]
+    pub no_include_permalinks: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_vendored",
+        help = "Undo --include-vendored"
+    )]
+    pub no_include_vendored: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_generated",
+        help = "Undo --include-generated"
+    )]
+    pub no_include_generated: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_documentation",
+        help = "Undo --include-documentation"
+    )]
+    pub no_include_documentation: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_submodules",
+        help = "Undo --include-submodules"
+    )]
+    pub no_include_submodules: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_hidden",
+        help = "Undo --include-hidden"
+    )]
+    pub no_include_hidden: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "include_gitignored",
+        help = "Undo --include-gitignored"
+    )]
+    pub no_include_gitignored: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "follow_symlinks",
+        help = "Undo --follow-symlinks"
+    )]
+    pub no_follow_symlinks: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "one_file_system",
+        help = "Undo --one-file-system"
+    )]
+    pub no_one_file_system: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "blame_moves",
+        help = "Undo --blame-moves"
+    )]
+    pub no_blame_moves: bool,
+
+    #[arg(
+        long,
+        global = true,
+        overrides_with = "blame_copies",
+        help = "Undo --blame-copies"
+    )]
+    pub no_blame_copies: bool,
+}
+
+/// A boolean flag and its negation as a config value: unset unless one was given.
+fn flag(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// A `--root` value: `PATH` or `LABEL=PATH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootArg {
+    pub label: Option<String>,
+    pub path: PathBuf,
+}
+
+impl FromStr for RootArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (label, path) = match s.split_once('=') {
+            Some((label, path)) => (Some(label.to_string()), path),
+            None => (None, s),
+        };
+        if label.as_deref() == Some("") || path.is_empty() {
+            return Err(format!("expected PATH or LABEL=PATH, got `{s}`"));
+        }
+        Ok(RootArg {
+            label,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Evaluate the [rules] policy from tracy.toml; exit code reflects the worst severity
+    Check,
+    /// Report likely-mistyped markers (e.g. `REQ 12`, `req-12`, `REQ-`) as warnings
+    Lint {
+        #[arg(long, help = "Rewrite fixable markers in place")]
+        fix: bool,
+    },
+    /// Compare trace links between two revisions, or a revision and the working tree
+    Diff {
+        #[arg(
+            value_name = "RANGE",
+            help = "`base..head`, `base...head` (from merge base) or `base` (against the working tree)"
+        )]
+        range: String,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigCommand {
+    /// Print the effective configuration and where each value came from
+    Show,
+    /// Check the config and the component configs below the roots; fails on any error
+    Validate,
+    /// Print the JSON Schema of tracy.toml
+    Schema,
+}
+
+/// The profile applied to the config, and what selected it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedProfile {
+    pub name: String,
+    /// `--profile` or the environment variable
+    pub source: &'static str,
+}
+
+/// A directory to scan with the filters that apply to it.
+#[derive(Debug, Clone)]
+pub struct ScanRoot {
+    /// Tags entries and prefixes their paths; set for every root when there are several
+    pub label: Option<String>,
+    pub path: PathBuf,
+    pub filter: FilterArgs,
+    /// The config values component configs below the root are layered over
+    pub layer: ComponentConfig,
+}
+
+/// Values given in the environment and on the command line, which win over
+/// component configs too.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    /// The filter flags as a config layer: `--x` sets `Some(true)`, `--no-x` `Some(false)`
+    pub filter: FilterConfig,
+    pub slug: Vec<String>,
+    pub merge: Merge,
+    /// `[scan]` and `[filter]` keys set by `TRACY_*` variables
+    pub env: ComponentConfig,
 }
 
 #[derive(Debug)]
 pub struct ResolvedArgs {
-    pub root: PathBuf,
+    pub command: Option<Command>,
+    pub roots: Vec<ScanRoot>,
+    pub rev: Option<String>,
     pub format: OutputFormat,
+    pub format_options: FormatOptions,
     pub output: Option<PathBuf>,
     pub quiet: bool,
     pub fail_on_empty: bool,
     pub include_git_meta: bool,
     pub include_blame: bool,
-    pub filter: FilterArgs,
+    pub include_permalinks: bool,
+    pub permalink_template: Option<String>,
+    pub git_backend: GitBackend,
     pub scan: ScanArgs,
+    pub blame: BlameArgs,
+    pub rules: RulesConfig,
+    pub cli: CliOverrides,
+    /// Whether `tracy.toml` files below the roots apply; off with `--no-config`
+    pub component_configs: bool,
+    pub profile: Option<SelectedProfile>,
+    /// Where each value was set; unset keys have their default
+    pub sources: Sources,
 }
 
 pub fn resolve_args(
@@ -69,19 +422,83 @@ pub fn resolve_args(
     config: Option<Config>,
     config_dir: Option<&Path>,
 ) -> Result<ResolvedArgs, TracyError> {
-    let config = config.unwrap_or_default();
-
-    let base_dir = config_dir.unwrap_or_else(|| Path::new("."));
+    let component_configs = config.is_some();
+    let profile = match &cli.profile {
+        Some(name) => Some(SelectedProfile {
+            name: name.clone(),
+            source: "--profile",
+        }),
+        None => std::env::var(PROFILE_ENV)
+            .ok()
+            .filter(|name| !name.is_empty())
+            .map(|name| SelectedProfile {
+                name,
+                source: PROFILE_ENV,
+            }),
+    };
+    let config = match (config, &profile) {
+        (Some(config), Some(profile)) => config.with_profile(&profile.name)?,
+        (config, _) => config.unwrap_or_default(),
+    };
+    // The environment overrides the config file, and flags override both.
+    let env = env_config(|name| std::env::var(name).ok(), &std::env::current_dir()?)?;
+    let env_scan = env.scan.clone();
+    let env_filter = env.filter.clone();
+    let config = config.overlay(env);
 
-    let root = match (cli.root, config.root) {
-        (Some(root), _) => root,
-        (None, Some(root)) => resolve_path(base_dir, root),
-        (None, None) => config_dir
-            .map(|d| d.to_path_buf())
-            .unwrap_or_else(|| PathBuf::from(".")),
+    let merge = cli.merge.or(config.merge).unwrap_or_default();
+    let no = &cli.negated;
+    let overrides = CliOverrides {
+        filter: FilterConfig {
+            include_vendored: flag(cli.filter.include_vendored, no.no_include_vendored),
+            include_generated: flag(cli.filter.include_generated, no.no_include_generated),
+            include_documentation: flag(
+                cli.filter.include_documentation,
+                no.no_include_documentation,
+            ),
+            include_submodules: flag(cli.filter.include_submodules, no.no_include_submodules),
+            include_hidden: flag(cli.filter.include_hidden, no.no_include_hidden),
+            include_gitignored: flag(cli.filter.include_gitignored, no.no_include_gitignored),
+            follow_symlinks: flag(cli.filter.follow_symlinks, no.no_follow_symlinks),
+            max_depth: cli.filter.max_depth,
+            one_file_system: flag(cli.filter.one_file_system, no.no_one_file_system),
+            include: Some(cli.filter.include.clone()).filter(|globs| !globs.is_empty()),
+            exclude: Some(cli.filter.exclude.clone()).filter(|globs| !globs.is_empty()),
+        },
+        slug: cli.scan.slug.clone(),
+        merge,
+        env: ComponentConfig {
+            scan: env_scan,
+            filter: env_filter,
+        },
     };
 
+    let mut sources = config.sources.clone();
+    for key in cli_keys(&cli) {
+        // Appended lists come from both.
+        let source = match sources.get(key) {
+            Some(config) if merge == Merge::Append && LIST_KEYS.contains(&key) => {
+                format!("{config} + {CLI_SOURCE}")
+            }
+            _ => CLI_SOURCE.to_string(),
+        };
+        sources.insert(key.to_string(), source);
+    }
+
+    let base_dir = config_dir.unwrap_or_else(|| Path::new("."));
+
     let format = cli.format.or(config.format).unwrap_or(OutputFormat::Json);
+    let mut format_options = FormatOptions::default();
+    if let Some(limit) = cli.markdown_limit.or(config.markdown_limit) {
+        format_options.markdown_limit = limit;
+    }
+    format_options.graph_collapse = cli.graph_collapse.or(config.graph_collapse);
+    format_options.graph_depth = cli.graph_depth.or(config.graph_depth);
+    format_options.template = cli
+        .template
+        .clone()
+        .or(config.template.map(|template| template.resolve(base_dir)));
+    format_options.requirement_url = cli.requirement_url.clone().or(config.requirement_url);
 
     let output = match (cli.output, config.output) {
         (Some(output), _) => Some(output),
@@ -89,56 +506,252 @@ pub fn resolve_args(
         (None, None) => None,
     };
 
-    let quiet = cli.quiet || config.quiet.unwrap_or(false);
-    let fail_on_empty = cli.fail_on_empty || config.fail_on_empty.unwrap_or(false);
-    let include_git_meta = cli.include_git_meta || config.include_git_meta.unwrap_or(false);
-    let include_blame = cli.include_blame || config.include_blame.unwrap_or(false);
+    let quiet = flag(cli.quiet, no.no_quiet)
+        .or(config.quiet)
+        .unwrap_or(false);
+    let fail_on_empty = flag(cli.fail_on_empty, no.no_fail_on_empty)
+        .or(config.fail_on_empty)
+        .unwrap_or(false);
+    let include_git_meta = flag(cli.include_git_meta, no.no_include_git_meta)
+        .or(config.include_git_meta)
+        .unwrap_or(false);
+    let include_blame = flag(cli.include_blame, no.no_include_blame)
+        .or(config.include_blame)
+        .unwrap_or(false);
+    let include_permalinks = flag(cli.include_permalinks, no.no_include_permalinks)
+        .or(config.include_permalinks)
+        .unwrap_or(false);
+    let permalink_template = cli.permalink_template.or(config.permalink_template);
 
-    let include = if !cli.filter.include.is_empty() {
-        cli.filter.include
-    } else {
-        config.filter.include.unwrap_or_default()
-    };
-    let exclude = if !cli.filter.exclude.is_empty() {
-        cli.filter.exclude
-    } else {
-        config.filter.exclude.unwrap_or_default()
-    };
+    let git_backend = cli.git_backend.or(config.git_backend).unwrap_or_default();
 
-    let filter = FilterArgs {
-        include_vendored: cli.filter.include_vendored
-            || config.filter.include_vendored.unwrap_or(false),
-        include_generated: cli.filter.include_generated
-            || config.filter.include_generated.unwrap_or(false),
-        include_submodules: cli.filter.include_submodules
-            || config.filter.include_submodules.unwrap_or(false),
-        include,
-        exclude,
+    // Each root's `filter` table overrides `[filter]`, and the CLI overrides both.
+    let roots: Vec<(Option<String>, PathBuf, FilterConfig)> = match config.root {
+        _ if !cli.root.is_empty() => cli
+            .root
+            .iter()
+            .map(|root| (root.label.clone(), root.path.clone(), config.filter.clone()))
+            .collect(),
+        Some(RootsConfig::One(path)) => vec![(None, resolve_path(base_dir, path), config.filter)],
+        Some(RootsConfig::Many(roots)) => roots
+            .into_iter()
+            .map(|root| {
+                (
+                    root.label,
+                    resolve_path(base_dir, root.path),
+                    config.filter.clone().overlay(root.filter),
+                )
+            })
+            .collect(),
+        None => vec![(
+            None,
+            config_dir
+                .map(|d| d.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from(".")),
+            config.filter,
+        )],
     };
 
-    let slug = if !cli.scan.slug.is_empty() {
-        cli.scan.slug
-    } else {
-        config.scan.slug.unwrap_or_default()
+    let several = roots.len() > 1;
+    let mut labels = HashSet::new();
+    let roots = roots
+        .into_iter()
+        .map(|(label, path, filter)| {
+            let label = label.or_else(|| several.then(|| default_label(&path)));
+            if let Some(label) = &label
+                && !labels.insert(label.clone())
+            {
+                return Err(TracyError::DuplicateRootLabel(label.clone()));
+            }
+            Ok(ScanRoot {
+                label,
+                filter: resolve_filter(&overrides, filter.clone()),
+                path,
+                layer: ComponentConfig {
+                    scan: config.scan.clone(),
+                    filter,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let blame = BlameArgs {
+        blame_moves: flag(cli.blame.blame_moves, no.no_blame_moves)
+            .or(config.blame.moves)
+            .unwrap_or(false),
+        blame_copies: flag(cli.blame.blame_copies, no.no_blame_copies)
+            .or(config.blame.copies)
+            .unwrap_or(false),
+        blame_ignore_revs_file: cli.blame.blame_ignore_revs_file.or_else(|| {
+            config
+                .blame
+                .ignore_revs_file
+                .map(|p| resolve_path(base_dir, p))
+        }),
+        blame_mailmap_file: cli
+            .blame
+            .blame_mailmap_file
+            .or_else(|| config.blame.mailmap_file.map(|p| resolve_path(base_dir, p))),
     };
 
-    if slug.is_empty() {
-        return Err(TracyError::NoSlugs);
-    }
+    let slug = resolve_slug(&overrides, config.scan)?;
 
     Ok(ResolvedArgs {
-        root,
+        command: cli.command,
+        roots,
+        rev: cli.rev,
         format,
+        format_options,
         output,
         quiet,
         fail_on_empty,
         include_git_meta,
         include_blame,
-        filter,
+        include_permalinks,
+        permalink_template,
+        git_backend,
         scan: ScanArgs { slug },
+        blame,
+        rules: config.rules,
+        cli: overrides,
+        component_configs,
+        profile,
+        sources,
     })
 }
 
+/// The source of values set on the command line.
+pub const CLI_SOURCE: &str = "command line";
+
+/// The config keys the command line sets.
+fn cli_keys(cli: &Args) -> Vec<&'static str> {
+    let filter = &cli.filter;
+    let no = &cli.negated;
+    [
+        ("root", !cli.root.is_empty()),
+        ("format", cli.format.is_some()),
+        ("output", cli.output.is_some()),
+        ("quiet", cli.quiet || no.no_quiet),
+        ("fail_on_empty", cli.fail_on_empty || no.no_fail_on_empty),
+        (
+            "include_git_meta",
+            cli.include_git_meta || no.no_include_git_meta,
+        ),
+        ("include_blame", cli.include_blame || no.no_include_blame),
+        (
+            "include_permalinks",
+            cli.include_permalinks || no.no_include_permalinks,
+        ),
+        ("permalink_template", cli.permalink_template.is_some()),
+        ("git_backend", cli.git_backend.is_some()),
+        ("merge", cli.merge.is_some()),
+        ("markdown_limit", cli.markdown_limit.is_some()),
+        ("graph_collapse", cli.graph_collapse.is_some()),
+        ("graph_depth", cli.graph_depth.is_some()),
+        ("template", cli.template.is_some()),
+        ("requirement_url", cli.requirement_url.is_some()),
+        ("scan.slug", !cli.scan.slug.is_empty()),
+        (
+            "filter.include_vendored",
+            filter.include_vendored || no.no_include_vendored,
+        ),
+        (
+            "filter.include_generated",
+            filter.include_generated || no.no_include_generated,
+        ),
+        (
+            "filter.include_documentation",
+            filter.include_documentation || no.no_include_documentation,
+        ),
+        (
+            "filter.include_submodules",
+            filter.include_submodules || no.no_include_submodules,
+        ),
+        (
+            "filter.include_hidden",
+            filter.include_hidden || no.no_include_hidden,
+        ),
+        (
+            "filter.include_gitignored",
+            filter.include_gitignored || no.no_include_gitignored,
+        ),
+        (
+            "filter.follow_symlinks",
+            filter.follow_symlinks || no.no_follow_symlinks,
+        ),
+        ("filter.max_depth", filter.max_depth.is_some()),
+        (
+            "filter.one_file_system",
+            filter.one_file_system || no.no_one_file_system,
+        ),
+        ("filter.include", !filter.include.is_empty()),
+        ("filter.exclude", !filter.exclude.is_empty()),
+        ("blame.moves", cli.blame.blame_moves || no.no_blame_moves),
+        ("blame.copies", cli.blame.blame_copies || no.no_blame_copies),
+        (
+            "blame.ignore_revs_file",
+            cli.blame.blame_ignore_revs_file.is_some(),
+        ),
+        ("blame.mailmap_file", cli.blame.blame_mailmap_file.is_some()),
+    ]
+    .into_iter()
+    .filter(|(_, set)| *set)
+    .map(|(key, _)| key)
+    .collect()
+}
+
+/// The config keys whose values are lists, which `merge` applies to.
+const LIST_KEYS: [&str; 3] = ["scan.slug", "filter.include", "filter.exclude"];
+
+/// Filters from the command line over those of a config.
+pub(crate) fn resolve_filter(cli: &CliOverrides, config: FilterConfig) -> FilterArgs {
+    let over = &cli.filter;
+    let include = cli.merge.lists(
+        over.include.as_deref().unwrap_or_default(),
+        config.include.clone(),
+    );
+    let exclude = cli.merge.lists(
+        over.exclude.as_deref().unwrap_or_default(),
+        config.exclude.clone(),
+    );
+    let filter = config.overlay(over.clone());
+
+    FilterArgs {
+        include_vendored: filter.include_vendored.unwrap_or(false),
+        include_generated: filter.include_generated.unwrap_or(false),
+        include_documentation: filter.include_documentation.unwrap_or(false),
+        include_submodules: filter.include_submodules.unwrap_or(false),
+        include_hidden: filter.include_hidden.unwrap_or(false),
+        include_gitignored: filter.include_gitignored.unwrap_or(false),
+        follow_symlinks: filter.follow_symlinks.unwrap_or(false),
+        max_depth: filter.max_depth,
+        one_file_system: filter.one_file_system.unwrap_or(false),
+        include,
+        exclude,
+    }
+}
+
+/// Slugs from the command line merged with those of a config; there must be some.
+pub(crate) fn resolve_slug(
+    cli: &CliOverrides,
+    config: ScanConfig,
+) -> Result<Vec<String>, TracyError> {
+    let slug = cli.merge.lists(&cli.slug, config.slug);
+
+    if slug.is_empty() {
+        return Err(TracyError::NoSlugs);
+    }
+    Ok(slug)
+}
+
+/// The label of an unlabeled root when there are several: its directory name.
+fn default_label(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
 fn resolve_path(base_dir: &Path, path: PathBuf) -> PathBuf {
     if path.is_absolute() {
         path
@@ -0,0 +1,11 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CheckError {
+    #[error("invalid glob pattern in [rules.{rule}] {pattern}: {source}")]
+    InvalidGlob {
+        rule: &'static str,
+        pattern: String,
+        source: glob::PatternError,
+    },
+}
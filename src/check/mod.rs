@@ -0,0 +1,477 @@
+//! Policy checks evaluated over scan results.
+//!
+//! Each rule is configured under `[rules.<name>]` in `tracy.toml` and yields
+//! zero or more [`Violation`]s tagged with the rule's configured severity.
+
+mod error;
+
+pub use error::CheckError;
+
+use crate::config::RulesConfig;
+use crate::scan::{Entry, ScanResult};
+use serde::{Deserialize, Serialize, Serializer};
+use std::path::PathBuf;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl Severity {
+    /// SARIF `level` value for this severity.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    /// Process exit code for a run whose worst violation has this severity.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Severity::Note => 0,
+            Severity::Warning => 2,
+            Severity::Error => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    MinReferences,
+    ForbiddenPaths,
+    DocCommentsOnly,
+    RequireVerification,
+    MaxAge,
+    MalformedMarker,
+}
+
+impl Rule {
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::MinReferences => "policy.min_references",
+            Rule::ForbiddenPaths => "policy.forbidden_paths",
+            Rule::DocCommentsOnly => "policy.doc_comments_only",
+            Rule::RequireVerification => "policy.require_verification",
+            Rule::MaxAge => "policy.max_age",
+            Rule::MalformedMarker => "lint.malformed_marker",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::MinReferences => "Minimum references",
+            Rule::ForbiddenPaths => "Forbidden paths",
+            Rule::DocCommentsOnly => "Doc comments only",
+            Rule::RequireVerification => "Verification required",
+            Rule::MaxAge => "Maximum marker age",
+            Rule::MalformedMarker => "Malformed marker",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::MinReferences => "Requirements must be referenced a minimum number of times",
+            Rule::ForbiddenPaths => "Requirement markers must not appear in forbidden paths",
+            Rule::DocCommentsOnly => "Requirement markers must appear in doc comments",
+            Rule::RequireVerification => "Requirements must be referenced from verification paths",
+            Rule::MaxAge => "Requirement markers must have been touched recently",
+            Rule::MalformedMarker => "Comment text looks like a mistyped requirement marker",
+        }
+    }
+}
+
+impl Serialize for Rule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.id())
+    }
+}
+
+/// A single policy rule violation.
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub requirement_id: String,
+    /// Relative file path of the offending (or first) reference
+    pub file: PathBuf,
+    /// 1-indexed line number of the offending (or first) reference
+    pub line: usize,
+    pub message: String,
+}
+
+/// The policy checks `rules` configures, in the order they are evaluated.
+pub fn configured_rules(rules: &RulesConfig) -> Vec<Rule> {
+    [
+        (rules.min_references.is_some(), Rule::MinReferences),
+        (rules.forbidden_paths.is_some(), Rule::ForbiddenPaths),
+        (rules.doc_comments_only.is_some(), Rule::DocCommentsOnly),
+        (
+            rules.require_verification.is_some(),
+            Rule::RequireVerification,
+        ),
+        (rules.max_age.is_some(), Rule::MaxAge),
+    ]
+    .into_iter()
+    .filter_map(|(configured, rule)| configured.then_some(rule))
+    .collect()
+}
+
+/// Evaluate every configured rule against `results`.
+///
+/// `now` is a unix timestamp in seconds, used by the `max_age` rule together
+/// with each entry's blame author time. Entries without blame are skipped by
+/// that rule.
+pub fn check_rules(
+    results: &ScanResult,
+    rules: &RulesConfig,
+    now: i64,
+) -> Result<Vec<Violation>, CheckError> {
+    let mut violations = Vec::new();
+
+    if let Some(rule) = &rules.min_references {
+        for (requirement_id, entries) in selected(results, rule.slug.as_deref()) {
+            if entries.len() < rule.min {
+                let first = &entries[0];
+                violations.push(Violation {
+                    rule: Rule::MinReferences,
+                    severity: rule.severity,
+                    requirement_id: requirement_id.clone(),
+                    file: first.file.clone(),
+                    line: first.line,
+                    message: format!(
+                        "{requirement_id} has {} reference(s), expected at least {}",
+                        entries.len(),
+                        rule.min
+                    ),
+                });
+            }
+        }
+    }
+
+    if let Some(rule) = &rules.forbidden_paths {
+        let patterns = parse_globs("forbidden_paths", &rule.paths)?;
+        for (requirement_id, entries) in selected(results, rule.slug.as_deref()) {
+            for entry in entries {
+                if let Some(pattern) = patterns.iter().find(|p| matches_path(p, entry)) {
+                    violations.push(Violation {
+                        rule: Rule::ForbiddenPaths,
+                        severity: rule.severity,
+                        requirement_id: requirement_id.clone(),
+                        file: entry.file.clone(),
+                        line: entry.line,
+                        message: format!(
+                            "{requirement_id} is referenced in forbidden path {}",
+                            pattern.as_str()
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(rule) = &rules.doc_comments_only {
+        for (requirement_id, entries) in selected(results, rule.slug.as_deref()) {
+            for entry in entries {
+                if !is_doc_comment(&entry.comment_text) {
+                    violations.push(Violation {
+                        rule: Rule::DocCommentsOnly,
+                        severity: rule.severity,
+                        requirement_id: requirement_id.clone(),
+                        file: entry.file.clone(),
+                        line: entry.line,
+                        message: format!("{requirement_id} is referenced outside a doc comment"),
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(rule) = &rules.require_verification {
+        let patterns = parse_globs("require_verification", &rule.paths)?;
+        for (requirement_id, entries) in selected(results, rule.slug.as_deref()) {
+            let verified = entries
+                .iter()
+                .any(|entry| patterns.iter().any(|p| matches_path(p, entry)));
+            if !verified {
+                let first = &entries[0];
+                violations.push(Violation {
+                    rule: Rule::RequireVerification,
+                    severity: rule.severity,
+                    requirement_id: requirement_id.clone(),
+                    file: first.file.clone(),
+                    line: first.line,
+                    message: format!(
+                        "{requirement_id} has no reference in verification paths ({})",
+                        rule.paths.join(", ")
+                    ),
+                });
+            }
+        }
+    }
+
+    if let Some(rule) = &rules.max_age {
+        let max_age = i64::try_from(rule.days)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(SECONDS_PER_DAY);
+        for (requirement_id, entries) in selected(results, rule.slug.as_deref()) {
+            for entry in entries {
+                let Some(time) = entry.blame.as_ref().and_then(|b| b.author_time) else {
+                    continue;
+                };
+                let age = now.saturating_sub(time);
+                if age > max_age {
+                    violations.push(Violation {
+                        rule: Rule::MaxAge,
+                        severity: rule.severity,
+                        requirement_id: requirement_id.clone(),
+                        file: entry.file.clone(),
+                        line: entry.line,
+                        message: format!(
+                            "{requirement_id} marker is {} days old, maximum is {}",
+                            age / SECONDS_PER_DAY,
+                            rule.days
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Highest severity among `violations`, if any.
+pub fn max_severity(violations: &[Violation]) -> Option<Severity> {
+    violations.iter().map(|v| v.severity).max()
+}
+
+/// Slug portion of a requirement id (`REQ-12` -> `REQ`).
+pub fn slug_of(requirement_id: &str) -> &str {
+    requirement_id
+        .rsplit_once('-')
+        .map(|(slug, _)| slug)
+        .unwrap_or(requirement_id)
+}
+
+fn selected<'a>(
+    results: &'a ScanResult,
+    slugs: Option<&'a [String]>,
+) -> impl Iterator<Item = (&'a String, &'a Vec<Entry>)> {
+    results.iter().filter(move |(requirement_id, entries)| {
+        !entries.is_empty()
+            && slugs.is_none_or(|slugs| slugs.iter().any(|s| s == slug_of(requirement_id)))
+    })
+}
+
+fn parse_globs(rule: &'static str, globs: &[String]) -> Result<Vec<glob::Pattern>, CheckError> {
+    globs
+        .iter()
+        .map(|g| {
+            glob::Pattern::new(g).map_err(|e| CheckError::InvalidGlob {
+                rule,
+                pattern: g.clone(),
+                source: e,
+            })
+        })
+        .collect()
+}
+
+fn matches_path(pattern: &glob::Pattern, entry: &Entry) -> bool {
+    pattern.matches(&entry.file.to_string_lossy())
+}
+
+fn is_doc_comment(text: &str) -> bool {
+    let text = text.trim_start();
+    (text.starts_with("///") && !text.starts_with("////"))
+        || text.starts_with("//!")
+        || (text.starts_with("/**") && !text.starts_with("/**/"))
+        || text.starts_with("/*!")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{
+        DocCommentsOnlyRule, ForbiddenPathsRule, MaxAgeRule, MinReferencesRule,
+        RequireVerificationRule,
+    };
+    use crate::git::BlameInfo;
+    use std::collections::BTreeMap;
+
+    fn entry(file: &str, line: usize, comment_text: &str) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line,
+            comment_text: comment_text.to_string(),
+            above: None,
+            below: None,
+            inline: None,
+            scope: Vec::new(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    fn results() -> ScanResult {
+        let mut results: ScanResult = BTreeMap::new();
+        results.insert(
+            "REQ-1".to_string(),
+            vec![
+                entry("src/lib.rs", 1, "/// REQ-1: impl"),
+                entry("tests/lib.rs", 4, "// REQ-1: test"),
+            ],
+        );
+        results.insert(
+            "REQ-2".to_string(),
+            vec![entry("src/lib.rs", 9, "// REQ-2: impl")],
+        );
+        results.insert(
+            "LIN-1".to_string(),
+            vec![entry("src/lin.rs", 2, "/** LIN-1 */")],
+        );
+        results
+    }
+
+    #[test]
+    fn no_rules_no_violations() {
+        let violations = check_rules(&results(), &RulesConfig::default(), 0).unwrap();
+        assert!(violations.is_empty());
+        assert_eq!(max_severity(&violations), None);
+    }
+
+    #[test]
+    fn min_references_flags_undercounted_requirements() {
+        let rules = RulesConfig {
+            min_references: Some(MinReferencesRule {
+                severity: Severity::Warning,
+                slug: Some(vec!["REQ".to_string()]),
+                min: 2,
+            }),
+            ..Default::default()
+        };
+        let violations = check_rules(&results(), &rules, 0).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, Rule::MinReferences);
+        assert_eq!(violations[0].requirement_id, "REQ-2");
+        assert_eq!(max_severity(&violations), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn forbidden_paths_flags_each_entry() {
+        let rules = RulesConfig {
+            forbidden_paths: Some(ForbiddenPathsRule {
+                severity: Severity::Error,
+                slug: None,
+                paths: vec!["tests/**".to_string()],
+            }),
+            ..Default::default()
+        };
+        let violations = check_rules(&results(), &rules, 0).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].file, PathBuf::from("tests/lib.rs"));
+        assert_eq!(violations[0].line, 4);
+    }
+
+    #[test]
+    fn forbidden_paths_rejects_invalid_glob() {
+        let rules = RulesConfig {
+            forbidden_paths: Some(ForbiddenPathsRule {
+                severity: Severity::Error,
+                slug: None,
+                paths: vec!["[".to_string()],
+            }),
+            ..Default::default()
+        };
+        assert!(check_rules(&results(), &rules, 0).is_err());
+    }
+
+    #[test]
+    fn doc_comments_only_flags_plain_comments() {
+        let rules = RulesConfig {
+            doc_comments_only: Some(DocCommentsOnlyRule {
+                severity: Severity::Note,
+                slug: None,
+            }),
+            ..Default::default()
+        };
+        let violations = check_rules(&results(), &rules, 0).unwrap();
+        let ids: Vec<_> = violations
+            .iter()
+            .map(|v| v.requirement_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["REQ-1", "REQ-2"]);
+    }
+
+    #[test]
+    fn require_verification_flags_untested_requirements() {
+        let rules = RulesConfig {
+            require_verification: Some(RequireVerificationRule {
+                severity: Severity::Error,
+                slug: Some(vec!["REQ".to_string()]),
+                paths: vec!["tests/**".to_string()],
+            }),
+            ..Default::default()
+        };
+        let violations = check_rules(&results(), &rules, 0).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].requirement_id, "REQ-2");
+    }
+
+    #[test]
+    fn max_age_uses_blame_author_time() {
+        let mut results = results();
+        results.get_mut("REQ-2").unwrap()[0].blame = Some(BlameInfo {
+            commit: "a".repeat(40),
+            author: None,
+            author_mail: None,
+            author_time: Some(0),
+            summary: None,
+        });
+        let rules = RulesConfig {
+            max_age: Some(MaxAgeRule {
+                severity: Severity::Warning,
+                slug: None,
+                days: 30,
+            }),
+            ..Default::default()
+        };
+
+        let violations = check_rules(&results, &rules, 10 * SECONDS_PER_DAY).unwrap();
+        assert!(violations.is_empty());
+
+        let violations = check_rules(&results, &rules, 31 * SECONDS_PER_DAY).unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].requirement_id, "REQ-2");
+    }
+
+    #[test]
+    fn detects_doc_comment_styles() {
+        assert!(is_doc_comment("/// doc"));
+        assert!(is_doc_comment("//! inner"));
+        assert!(is_doc_comment("/** block */"));
+        assert!(is_doc_comment("/*! inner block */"));
+        assert!(!is_doc_comment("// plain"));
+        assert!(!is_doc_comment("//// banner"));
+        assert!(!is_doc_comment("/**/"));
+        assert!(!is_doc_comment("# python"));
+    }
+
+    #[test]
+    fn severity_exit_codes_are_ordered() {
+        assert!(Severity::Error > Severity::Warning);
+        assert!(Severity::Warning > Severity::Note);
+        assert_eq!(Severity::Note.exit_code(), 0);
+        assert!(Severity::Error.exit_code() > Severity::Warning.exit_code());
+    }
+}
@@ -0,0 +1,248 @@
+//! Component configs: `tracy.toml` files (or package manifests with a tracy
+//! section) below a scan root.
+//!
+//! Each one applies its `[scan]` and `[filter]` to the files beneath it,
+//! layered over the config of the enclosing component (or of the scan root),
+//! and `TRACY_*` variables and flags on the command line still take
+//! precedence. Its other keys are run-wide and ignored. When scanning a
+//! revision, components are read from the revision's tree.
+
+use crate::args::{CliOverrides, ScanRoot, resolve_filter, resolve_slug};
+use crate::config::{
+    CONFIG_FILES, ComponentConfig, Config, ConfigError, FilterConfig, config_in, load_config,
+};
+use crate::error::TracyError;
+use crate::filter::{FilterArgs, FilterError, collect_files_in, walker};
+use crate::scan::{ScanArgs, ScanResult};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct Component {
+    /// Directory relative to the scan root; empty for the root itself
+    pub dir: PathBuf,
+    /// The component's config file relative to the scan root; `None` for the root itself
+    pub config: Option<PathBuf>,
+    pub filter: FilterArgs,
+    pub scan: ScanArgs,
+}
+
+/// The scan root itself, then every component below it, parents before
+/// children. With `enabled` unset only the scan root is returned.
+pub fn discover_components(
+    root: &ScanRoot,
+    scan: &ScanArgs,
+    cli: &CliOverrides,
+    enabled: bool,
+) -> Result<Vec<Component>, TracyError> {
+    if !enabled {
+        return layer_components(root, scan, cli, Vec::new(), load_config);
+    }
+
+    let mut dirs = BTreeSet::new();
+    for entry in walker(&root.path, &root.filter).build() {
+        let entry = entry.map_err(FilterError::from)?;
+        // A config directly in the root is a top-level config, not a component.
+        if entry.depth() > 1
+            && CONFIG_FILES.iter().any(|name| entry.file_name() == *name)
+            && entry.file_type().is_some_and(|ft| ft.is_file())
+            && let Some(dir) = entry.path().parent()
+        {
+            dirs.insert(dir.to_path_buf());
+        }
+    }
+    // One config per directory, the preferred one of those it has.
+    let configs = dirs
+        .iter()
+        .filter_map(|dir| config_in(dir))
+        .filter_map(|path| Some(path.strip_prefix(&root.path).ok()?.to_path_buf()))
+        .collect();
+    layer_components(root, scan, cli, configs, |path| {
+        load_config(&root.path.join(path))
+    })
+}
+
+/// The scan root itself, then a component for each of `configs`, paths
+/// relative to the scan root, each config loaded by `load` and layered over
+/// that of the component enclosing it.
+pub fn layer_components(
+    root: &ScanRoot,
+    scan: &ScanArgs,
+    cli: &CliOverrides,
+    mut configs: Vec<PathBuf>,
+    mut load: impl FnMut(&Path) -> Result<Config, ConfigError>,
+) -> Result<Vec<Component>, TracyError> {
+    let mut components = vec![Component {
+        dir: PathBuf::new(),
+        config: None,
+        filter: root.filter.clone(),
+        scan: scan.clone(),
+    }];
+    configs.sort_by_key(|path| path.components().count());
+
+    let mut layers = vec![(PathBuf::new(), root.layer.clone())];
+    for config_path in configs {
+        let dir = config_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let config = load(&config_path)?;
+        let (_, parent) = layers
+            .iter()
+            .filter(|(parent, _)| dir.starts_with(parent))
+            .max_by_key(|(parent, _)| parent.components().count())
+            .expect("the scan root encloses every component");
+        let layer = parent.clone().overlay(ComponentConfig {
+            scan: config.scan,
+            filter: rebase_globs(&dir, config.filter),
+        });
+
+        let resolved = layer.clone().overlay(cli.env.clone());
+        components.push(Component {
+            dir: dir.clone(),
+            config: Some(config_path),
+            filter: resolve_filter(cli, resolved.filter),
+            scan: ScanArgs {
+                slug: resolve_slug(cli, resolved.scan)?,
+            },
+        });
+        layers.push((dir, layer));
+    }
+
+    Ok(components)
+}
+
+/// `filter` with its globs, written relative to `dir`, made relative to the
+/// scan root like those it inherits and those of the command line.
+fn rebase_globs(dir: &Path, mut filter: FilterConfig) -> FilterConfig {
+    let prefix = glob::Pattern::escape(&dir.to_string_lossy());
+    let rebase = |globs: Vec<String>| {
+        globs
+            .into_iter()
+            .map(|glob| format!("{prefix}/{glob}"))
+            .collect()
+    };
+    filter.include = filter.include.map(rebase);
+    filter.exclude = filter.exclude.map(rebase);
+    filter
+}
+
+/// The innermost of `components` containing `path`, relative to the scan root.
+pub fn component_of<'a>(components: &'a [Component], path: &Path) -> &'a Component {
+    components
+        .iter()
+        .filter(|c| path.starts_with(&c.dir))
+        .max_by_key(|c| c.dir.components().count())
+        .unwrap_or(&components[0])
+}
+
+/// The working tree files of `component` under `root` that pass its filters,
+/// leaving out those of the components nested in it.
+pub fn collect_component_files(
+    root: &Path,
+    components: &[Component],
+    component: &Component,
+) -> Result<Vec<PathBuf>, FilterError> {
+    let files = collect_files_in(
+        &component.dir,
+        &root.join(&component.dir),
+        &component.filter,
+    )?;
+    Ok(files
+        .into_iter()
+        .filter(|path| {
+            path.strip_prefix(root)
+                .is_ok_and(|relative| component_of(components, relative).dir == component.dir)
+        })
+        .collect())
+}
+
+/// Record on each entry the component config that produced it.
+pub fn tag_component(component: &Component, results: &mut ScanResult) {
+    for entry in results.values_mut().flatten() {
+        entry.component = component.config.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScanConfig;
+    use std::fs;
+
+    fn root(path: &Path) -> ScanRoot {
+        ScanRoot {
+            label: None,
+            path: path.to_path_buf(),
+            filter: FilterArgs::default(),
+            layer: ComponentConfig {
+                scan: ScanConfig {
+                    slug: Some(vec!["REQ".to_string()]),
+                },
+                filter: FilterConfig {
+                    exclude: Some(vec!["gen/**".to_string()]),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    fn scan(slug: &str) -> ScanArgs {
+        ScanArgs {
+            slug: vec![slug.to_string()],
+        }
+    }
+
+    #[test]
+    fn layers_component_configs_over_their_parents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("comms/radio")).unwrap();
+        fs::write(dir.path().join("tracy.toml"), "[scan]\nslug = [\"TOP\"]\n").unwrap();
+        fs::write(
+            dir.path().join("comms/tracy.toml"),
+            "[scan]\nslug = [\"COM\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("comms/radio/tracy.toml"),
+            "[filter]\nexclude = [\"fixtures/**\"]\n",
+        )
+        .unwrap();
+        let root = root(dir.path());
+
+        let components =
+            discover_components(&root, &scan("REQ"), &CliOverrides::default(), true).unwrap();
+        let summary: Vec<_> = components
+            .iter()
+            .map(|c| (c.dir.clone(), c.scan.slug.clone(), c.filter.exclude.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PathBuf::new(), vec!["REQ".to_string()], vec![]),
+                (
+                    PathBuf::from("comms"),
+                    vec!["COM".to_string()],
+                    vec!["gen/**".to_string()]
+                ),
+                (
+                    PathBuf::from("comms/radio"),
+                    vec!["COM".to_string()],
+                    vec!["comms/radio/fixtures/**".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(
+            component_of(&components, Path::new("comms/radio/tx.rs")).config,
+            Some(PathBuf::from("comms/radio/tracy.toml"))
+        );
+        assert_eq!(component_of(&components, Path::new("main.rs")).config, None);
+
+        let cli = CliOverrides {
+            slug: vec!["CLI".to_string()],
+            ..Default::default()
+        };
+        let components = discover_components(&root, &scan("CLI"), &cli, true).unwrap();
+        assert!(components.iter().all(|c| c.scan.slug == ["CLI"]));
+
+        let components = discover_components(&root, &scan("REQ"), &cli, false).unwrap();
+        assert_eq!(components.len(), 1);
+    }
+}
@@ -0,0 +1,209 @@
+//! `TRACY_*` environment variables: a config layer between the config file
+//! and the command line. Each variable is named after its flag.
+
+use super::{Config, ConfigError, Merge, RootsConfig, Sources};
+use crate::git::GitBackend;
+use crate::output::{GraphCollapse, OutputFormat, TemplateSource};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// The config set by the environment. `var` looks a variable up; empty
+/// values count as unset. Relative paths are resolved against `cwd`.
+pub fn env_config(var: impl Fn(&str) -> Option<String>, cwd: &Path) -> Result<Config, ConfigError> {
+    let mut env = Env {
+        var: &var,
+        cwd,
+        sources: Sources::new(),
+    };
+    let mut config = Config {
+        root: env.path("TRACY_ROOT", "root").map(RootsConfig::One),
+        format: env.enumeration::<OutputFormat>("TRACY_FORMAT", "format")?,
+        output: env.path("TRACY_OUTPUT", "output"),
+        quiet: env.bool("TRACY_QUIET", "quiet")?,
+        fail_on_empty: env.bool("TRACY_FAIL_ON_EMPTY", "fail_on_empty")?,
+        include_git_meta: env.bool("TRACY_INCLUDE_GIT_META", "include_git_meta")?,
+        include_blame: env.bool("TRACY_INCLUDE_BLAME", "include_blame")?,
+        include_permalinks: env.bool("TRACY_INCLUDE_PERMALINKS", "include_permalinks")?,
+        permalink_template: env.string("TRACY_PERMALINK_TEMPLATE", "permalink_template"),
+        git_backend: env.enumeration::<GitBackend>("TRACY_GIT_BACKEND", "git_backend")?,
+        merge: env.enumeration::<Merge>("TRACY_MERGE", "merge")?,
+        markdown_limit: env.number("TRACY_MARKDOWN_LIMIT", "markdown_limit")?,
+        graph_collapse: env
+            .enumeration::<GraphCollapse>("TRACY_GRAPH_COLLAPSE", "graph_collapse")?,
+        graph_depth: env.number("TRACY_GRAPH_DEPTH", "graph_depth")?,
+        template: env
+            .string("TRACY_TEMPLATE", "template")
+            .map(|template| TemplateSource::from(template).resolve(cwd)),
+        requirement_url: env.string("TRACY_REQUIREMENT_URL", "requirement_url"),
+        ..Config::default()
+    };
+    config.scan.slug = env.list("TRACY_SLUG", "scan.slug");
+
+    let filter = &mut config.filter;
+    filter.include_vendored = env.bool("TRACY_INCLUDE_VENDORED", "filter.include_vendored")?;
+    filter.include_generated = env.bool("TRACY_INCLUDE_GENERATED", "filter.include_generated")?;
+    filter.include_documentation = env.bool(
+        "TRACY_INCLUDE_DOCUMENTATION",
+        "filter.include_documentation",
+    )?;
+    filter.include_submodules =
+        env.bool("TRACY_INCLUDE_SUBMODULES", "filter.include_submodules")?;
+    filter.include_hidden = env.bool("TRACY_INCLUDE_HIDDEN", "filter.include_hidden")?;
+    filter.include_gitignored =
+        env.bool("TRACY_INCLUDE_GITIGNORED", "filter.include_gitignored")?;
+    filter.follow_symlinks = env.bool("TRACY_FOLLOW_SYMLINKS", "filter.follow_symlinks")?;
+    filter.max_depth = env.number("TRACY_MAX_DEPTH", "filter.max_depth")?;
+    filter.one_file_system = env.bool("TRACY_ONE_FILE_SYSTEM", "filter.one_file_system")?;
+    filter.include = env.list("TRACY_INCLUDE", "filter.include");
+    filter.exclude = env.list("TRACY_EXCLUDE", "filter.exclude");
+
+    let blame = &mut config.blame;
+    blame.moves = env.bool("TRACY_BLAME_MOVES", "blame.moves")?;
+    blame.copies = env.bool("TRACY_BLAME_COPIES", "blame.copies")?;
+    blame.ignore_revs_file = env.path("TRACY_BLAME_IGNORE_REVS_FILE", "blame.ignore_revs_file");
+    blame.mailmap_file = env.path("TRACY_BLAME_MAILMAP_FILE", "blame.mailmap_file");
+
+    config.sources = env.sources;
+    Ok(config)
+}
+
+struct Env<'a, F> {
+    var: &'a F,
+    cwd: &'a Path,
+    /// The variable each key was set by
+    sources: Sources,
+}
+
+impl<F: Fn(&str) -> Option<String>> Env<'_, F> {
+    /// The value of `name`, recorded as the source of config key `key`.
+    fn string(&mut self, name: &str, key: &str) -> Option<String> {
+        let value = (self.var)(name).filter(|value| !value.is_empty())?;
+        self.sources.insert(key.to_string(), name.to_string());
+        Some(value)
+    }
+
+    fn bool(&mut self, name: &str, key: &str) -> Result<Option<bool>, ConfigError> {
+        let Some(value) = self.string(name, key) else {
+            return Ok(None);
+        };
+        match value.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(Some(true)),
+            "0" | "false" | "no" | "off" => Ok(Some(false)),
+            _ => Err(invalid(name, value, "true or false")),
+        }
+    }
+
+    fn number(&mut self, name: &str, key: &str) -> Result<Option<usize>, ConfigError> {
+        let Some(value) = self.string(name, key) else {
+            return Ok(None);
+        };
+        match value.parse() {
+            Ok(n) => Ok(Some(n)),
+            Err(_) => Err(invalid(name, value, "a number")),
+        }
+    }
+
+    fn enumeration<T: ValueEnum>(
+        &mut self,
+        name: &str,
+        key: &str,
+    ) -> Result<Option<T>, ConfigError> {
+        let Some(value) = self.string(name, key) else {
+            return Ok(None);
+        };
+        match T::from_str(&value, true) {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => {
+                let names: Vec<_> = T::value_variants()
+                    .iter()
+                    .filter_map(|v| v.to_possible_value())
+                    .map(|v| v.get_name().to_string())
+                    .collect();
+                Err(invalid(
+                    name,
+                    value,
+                    &format!("one of {}", names.join(", ")),
+                ))
+            }
+        }
+    }
+
+    /// A comma-separated list.
+    fn list(&mut self, name: &str, key: &str) -> Option<Vec<String>> {
+        let value = self.string(name, key)?;
+        Some(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    fn path(&mut self, name: &str, key: &str) -> Option<PathBuf> {
+        self.string(name, key).map(|value| self.cwd.join(value))
+    }
+}
+
+fn invalid(name: &str, value: String, expected: &str) -> ConfigError {
+    ConfigError::InvalidEnv {
+        name: name.to_string(),
+        value,
+        expected: expected.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        env_config(
+            |name| vars.get(name).map(|v| v.to_string()),
+            Path::new("/work"),
+        )
+    }
+
+    #[test]
+    fn reads_variables_named_after_flags() {
+        let config = config(&[
+            ("TRACY_FORMAT", "jsonl"),
+            ("TRACY_QUIET", "yes"),
+            ("TRACY_INCLUDE_BLAME", "0"),
+            ("TRACY_OUTPUT", "out/refs.json"),
+            ("TRACY_SLUG", "REQ, LIN"),
+            ("TRACY_EXCLUDE", "vendor/**"),
+            ("TRACY_MAX_DEPTH", "3"),
+            ("TRACY_BLAME_MOVES", "true"),
+            ("TRACY_PERMALINK_TEMPLATE", ""),
+        ])
+        .unwrap();
+
+        assert_eq!(config.format, Some(OutputFormat::Jsonl));
+        assert_eq!(config.quiet, Some(true));
+        assert_eq!(config.include_blame, Some(false));
+        assert_eq!(config.output, Some(PathBuf::from("/work/out/refs.json")));
+        assert_eq!(config.scan.slug, Some(vec!["REQ".into(), "LIN".into()]));
+        assert_eq!(config.filter.exclude, Some(vec!["vendor/**".into()]));
+        assert_eq!(config.filter.max_depth, Some(3));
+        assert_eq!(config.blame.moves, Some(true));
+        assert_eq!(config.permalink_template, None);
+        assert_eq!(config.sources["scan.slug"], "TRACY_SLUG");
+        assert_eq!(config.sources["quiet"], "TRACY_QUIET");
+        assert!(!config.sources.contains_key("permalink_template"));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let err = config(&[("TRACY_QUIET", "maybe")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid value `maybe` for TRACY_QUIET: expected true or false"
+        );
+        let err = config(&[("TRACY_FORMAT", "yaml")]).unwrap_err();
+        assert!(err.to_string().contains("expected one of json"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Config read from a table of a package manifest (`[package.metadata.tracy]`
+//! in `Cargo.toml`, `[tool.tracy]` in `pyproject.toml`, `"tracy"` in
+//! `package.json`) as well as from a whole `tracy.toml`.
+
+use super::validate::validate;
+use super::{CONFIG_FILE, Config, ConfigError, Issues};
+use serde::Deserialize;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::convert::Infallible;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The files a directory's config is read from, in order of preference.
+pub const CONFIG_FILES: [&str; 4] = [CONFIG_FILE, "Cargo.toml", "pyproject.toml", "package.json"];
+
+/// Where in a file its config is.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Section {
+    /// The whole file, a `tracy.toml`
+    Whole,
+    /// A table of a TOML manifest, by its keys
+    Toml(&'static [&'static str]),
+    /// A key of a JSON manifest
+    Json(&'static str),
+}
+
+impl Section {
+    pub(super) fn of(path: &Path) -> Section {
+        match path.file_name().and_then(OsStr::to_str) {
+            Some("Cargo.toml") => Section::Toml(&["package", "metadata", "tracy"]),
+            Some("pyproject.toml") => Section::Toml(&["tool", "tracy"]),
+            Some("package.json") => Section::Json("tracy"),
+            _ => Section::Whole,
+        }
+    }
+
+    /// The section as written in the file, e.g. `[tool.tracy]`.
+    pub(super) fn name(self) -> Option<String> {
+        match self {
+            Section::Whole => None,
+            Section::Toml(keys) => Some(format!("[{}]", keys.join("."))),
+            Section::Json(key) => Some(format!("\"{key}\"")),
+        }
+    }
+
+    /// The config in `content`, and the raw table it was read from.
+    pub(super) fn read(
+        self,
+        path: &Path,
+        content: &str,
+    ) -> Result<(Config, toml::Table), ConfigError> {
+        let parse_error = |e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source: e,
+        };
+        let json_error = |e| ConfigError::ParseJson {
+            path: path.to_path_buf(),
+            source: e,
+        };
+        let missing = || ConfigError::MissingSection {
+            path: path.to_path_buf(),
+            section: self.name().unwrap_or_default(),
+        };
+        let invalid = |issues: Vec<_>| ConfigError::Invalid {
+            path: path.to_path_buf(),
+            issues: Issues(issues),
+        };
+
+        match self {
+            Section::Whole | Section::Toml(_) => {
+                let keys = match self {
+                    Section::Toml(keys) => keys,
+                    _ => &[][..],
+                };
+                let document: toml::Table = toml::from_str(content).map_err(parse_error)?;
+                let table = toml_section(&document, keys).ok_or_else(missing)?.clone();
+                let issues = validate(content, keys);
+                if !issues.is_empty() {
+                    return Err(invalid(issues));
+                }
+                let config = At(keys)
+                    .deserialize(toml::Deserializer::new(content))
+                    .map_err(parse_error)?
+                    .ok_or_else(missing)?;
+                Ok((config, table))
+            }
+            Section::Json(key) => {
+                let document: serde_json::Value =
+                    serde_json::from_str(content).map_err(json_error)?;
+                let value = document.get(key).ok_or_else(missing)?;
+                let table: toml::Table =
+                    serde_json::from_value(value.clone()).map_err(json_error)?;
+                // JSON has no spans to point at, so issues are reported
+                // without a location.
+                let text = toml::to_string(&table).unwrap_or_default();
+                let issues: Vec<_> = validate(&text, &[])
+                    .into_iter()
+                    .map(|issue| issue.without_location())
+                    .collect();
+                if !issues.is_empty() {
+                    return Err(invalid(issues));
+                }
+                let mut deserializer = serde_json::Deserializer::from_str(content);
+                let config = At(&[key])
+                    .deserialize(&mut deserializer)
+                    .map_err(json_error)?
+                    .ok_or_else(missing)?;
+                Ok((config, table))
+            }
+        }
+    }
+}
+
+/// The config file of `dir`, if it has one: its `tracy.toml`, or a manifest
+/// with a tracy section.
+pub fn config_in(dir: &Path) -> Option<PathBuf> {
+    let read = |path: &Path| {
+        Ok::<_, Infallible>(
+            path.is_file()
+                .then(|| fs::read_to_string(path).ok())
+                .flatten(),
+        )
+    };
+    match config_in_with(dir, read) {
+        Ok(config) => config,
+    }
+}
+
+/// The config file of `dir` as for [`config_in`], with files read by `read`,
+/// which gives `None` for a file that is missing.
+pub fn config_in_with<E>(
+    dir: &Path,
+    mut read: impl FnMut(&Path) -> Result<Option<String>, E>,
+) -> Result<Option<PathBuf>, E> {
+    for path in CONFIG_FILES.iter().map(|name| dir.join(name)) {
+        if read(&path)?.is_some_and(|content| has_section(&path, &content)) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+fn has_section(path: &Path, content: &str) -> bool {
+    match Section::of(path) {
+        Section::Whole => true,
+        Section::Toml(keys) => toml::from_str::<toml::Table>(content)
+            .is_ok_and(|document| toml_section(&document, keys).is_some()),
+        Section::Json(key) => serde_json::from_str::<serde_json::Value>(content)
+            .is_ok_and(|document| document.get(key).is_some()),
+    }
+}
+
+fn toml_section<'a>(document: &'a toml::Table, keys: &[&str]) -> Option<&'a toml::Table> {
+    keys.iter()
+        .try_fold(document, |table, key| table.get(*key)?.as_table())
+}
+
+/// Deserializes the config at a path of keys in a document, so errors keep
+/// their locations in the whole file.
+struct At<'a>(&'a [&'a str]);
+
+impl<'de> DeserializeSeed<'de> for At<'_> {
+    type Value = Option<Config>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        match self.0 {
+            [] => Config::deserialize(deserializer).map(Some),
+            [key, rest @ ..] => deserializer.deserialize_map(AtVisitor { key, rest }),
+        }
+    }
+}
+
+struct AtVisitor<'a> {
+    key: &'a str,
+    rest: &'a [&'a str],
+}
+
+impl<'de> Visitor<'de> for AtVisitor<'_> {
+    type Value = Option<Config>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a table with a `{}` key", self.key)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut config = None;
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.key {
+                config = map.next_value_seed(At(self.rest))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,831 @@
+mod env;
+mod manifest;
+mod schema;
+mod show;
+mod validate;
+
+pub use env::env_config;
+pub use manifest::{CONFIG_FILES, config_in, config_in_with};
+pub use schema::json_schema;
+pub use show::{effective_config, show_config};
+pub use validate::{Issue, Issues};
+
+use crate::check::Severity;
+use crate::git::GitBackend;
+use crate::output::{GraphCollapse, OutputFormat, TemplateSource};
+use clap::ValueEnum;
+use manifest::Section;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// File name of the config, both at the top and for components. Package
+/// manifests with a tracy section are read too, see [`CONFIG_FILES`].
+pub const CONFIG_FILE: &str = "tracy.toml";
+
+/// Environment variable selecting a profile when `--profile` is not given.
+pub const PROFILE_ENV: &str = "TRACY_PROFILE";
+
+/// Where each key was set, by dotted path (`format`, `filter.exclude`,
+/// `rules.max_age`): a config file, optionally with its profile section, or
+/// an environment variable.
+pub type Sources = BTreeMap<String, String>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// A config this one overrides, relative to this file
+    pub extends: Option<PathBuf>,
+    pub root: Option<RootsConfig>,
+    pub format: Option<OutputFormat>,
+    pub output: Option<PathBuf>,
+    pub quiet: Option<bool>,
+    pub fail_on_empty: Option<bool>,
+    pub include_git_meta: Option<bool>,
+    pub include_blame: Option<bool>,
+    pub include_permalinks: Option<bool>,
+    pub permalink_template: Option<String>,
+    pub git_backend: Option<GitBackend>,
+    pub merge: Option<Merge>,
+    pub markdown_limit: Option<usize>,
+    pub graph_collapse: Option<GraphCollapse>,
+    pub graph_depth: Option<usize>,
+    pub template: Option<TemplateSource>,
+    pub requirement_url: Option<String>,
+    #[serde(default)]
+    pub scan: ScanConfig,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    #[serde(default)]
+    pub blame: BlameConfig,
+    #[serde(default)]
+    pub rules: RulesConfig,
+    /// `[profile.<name>]` sections, each overlaying the rest of the config when selected
+    #[serde(default)]
+    pub profile: BTreeMap<String, Config>,
+    #[serde(skip)]
+    pub sources: Sources,
+}
+
+impl Config {
+    /// `self` with every key set in `over` replaced; tables are merged key by key,
+    /// and profiles of the same name are merged too.
+    pub fn overlay(self, over: Config) -> Config {
+        let mut profile = self.profile;
+        for (name, over) in over.profile {
+            let merged = match profile.remove(&name) {
+                Some(base) => base.overlay(over),
+                None => over,
+            };
+            profile.insert(name, merged);
+        }
+        let mut sources = self.sources;
+        sources.extend(over.sources);
+
+        Config {
+            extends: None,
+            root: over.root.or(self.root),
+            format: over.format.or(self.format),
+            output: over.output.or(self.output),
+            quiet: over.quiet.or(self.quiet),
+            fail_on_empty: over.fail_on_empty.or(self.fail_on_empty),
+            include_git_meta: over.include_git_meta.or(self.include_git_meta),
+            include_blame: over.include_blame.or(self.include_blame),
+            include_permalinks: over.include_permalinks.or(self.include_permalinks),
+            permalink_template: over.permalink_template.or(self.permalink_template),
+            git_backend: over.git_backend.or(self.git_backend),
+            merge: over.merge.or(self.merge),
+            markdown_limit: over.markdown_limit.or(self.markdown_limit),
+            graph_collapse: over.graph_collapse.or(self.graph_collapse),
+            graph_depth: over.graph_depth.or(self.graph_depth),
+            template: over.template.or(self.template),
+            requirement_url: over.requirement_url.or(self.requirement_url),
+            scan: self.scan.overlay(over.scan),
+            filter: self.filter.overlay(over.filter),
+            blame: self.blame.overlay(over.blame),
+            rules: self.rules.overlay(over.rules),
+            profile,
+            sources,
+        }
+    }
+
+    /// The config with profile `name` overlaid; it must be defined.
+    pub fn with_profile(mut self, name: &str) -> Result<Config, ConfigError> {
+        let Some(profile) = self.profile.remove(name) else {
+            return Err(ConfigError::UnknownProfile(name.to_string()));
+        };
+        Ok(self.overlay(profile))
+    }
+
+    /// Resolve the relative paths in `self` against `dir`, so they keep
+    /// pointing at the same files once merged into a config elsewhere.
+    fn rebase(mut self, dir: &Path) -> Config {
+        let rebase = |path: PathBuf| dir.join(path);
+        self.root = self.root.map(|root| match root {
+            RootsConfig::One(path) => RootsConfig::One(rebase(path)),
+            RootsConfig::Many(roots) => RootsConfig::Many(
+                roots
+                    .into_iter()
+                    .map(|root| RootConfig {
+                        path: rebase(root.path),
+                        ..root
+                    })
+                    .collect(),
+            ),
+        });
+        self.output = self.output.map(rebase);
+        self.template = self.template.map(|template| template.resolve(dir));
+        self.blame.ignore_revs_file = self.blame.ignore_revs_file.map(rebase);
+        self.blame.mailmap_file = self.blame.mailmap_file.map(rebase);
+        self.profile = self
+            .profile
+            .into_iter()
+            .map(|(name, profile)| (name, profile.rebase(dir)))
+            .collect();
+        self
+    }
+}
+
+/// How lists given on the command line (`--slug`, `--include`, `--exclude`)
+/// combine with those of the config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Merge {
+    /// The command line's list replaces the config's
+    #[default]
+    Replace,
+    /// The command line's list is appended to the config's
+    Append,
+}
+
+impl Merge {
+    /// The list from the command line combined with the config's.
+    pub fn lists(self, cli: &[String], config: Option<Vec<String>>) -> Vec<String> {
+        let mut list = match self {
+            _ if cli.is_empty() => return config.unwrap_or_default(),
+            Merge::Replace => Vec::new(),
+            Merge::Append => config.unwrap_or_default(),
+        };
+        for item in cli {
+            if !list.contains(item) {
+                list.push(item.clone());
+            }
+        }
+        list
+    }
+}
+
+/// The keys a component config applies to the files beneath it.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentConfig {
+    pub scan: ScanConfig,
+    pub filter: FilterConfig,
+}
+
+impl ComponentConfig {
+    /// `self` with every key set in `over` replaced.
+    pub fn overlay(self, over: ComponentConfig) -> ComponentConfig {
+        ComponentConfig {
+            scan: self.scan.overlay(over.scan),
+            filter: self.filter.overlay(over.filter),
+        }
+    }
+}
+
+/// `root = "path"`, or one `[[root]]` table per scan root.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RootsConfig {
+    One(PathBuf),
+    Many(Vec<RootConfig>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RootConfig {
+    pub path: PathBuf,
+    pub label: Option<String>,
+    /// Overrides of the top-level `[filter]` for this root
+    #[serde(default)]
+    pub filter: FilterConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanConfig {
+    pub slug: Option<Vec<String>>,
+}
+
+impl ScanConfig {
+    /// `self` with every key set in `over` replaced.
+    pub fn overlay(self, over: ScanConfig) -> ScanConfig {
+        ScanConfig {
+            slug: over.slug.or(self.slug),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    pub include_vendored: Option<bool>,
+    pub include_generated: Option<bool>,
+    pub include_documentation: Option<bool>,
+    pub include_submodules: Option<bool>,
+    pub include_hidden: Option<bool>,
+    pub include_gitignored: Option<bool>,
+    pub follow_symlinks: Option<bool>,
+    pub max_depth: Option<usize>,
+    pub one_file_system: Option<bool>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+}
+
+impl FilterConfig {
+    /// `self` with every key set in `over` replaced.
+    pub fn overlay(self, over: FilterConfig) -> FilterConfig {
+        FilterConfig {
+            include_vendored: over.include_vendored.or(self.include_vendored),
+            include_generated: over.include_generated.or(self.include_generated),
+            include_documentation: over.include_documentation.or(self.include_documentation),
+            include_submodules: over.include_submodules.or(self.include_submodules),
+            include_hidden: over.include_hidden.or(self.include_hidden),
+            include_gitignored: over.include_gitignored.or(self.include_gitignored),
+            follow_symlinks: over.follow_symlinks.or(self.follow_symlinks),
+            max_depth: over.max_depth.or(self.max_depth),
+            one_file_system: over.one_file_system.or(self.one_file_system),
+            include: over.include.or(self.include),
+            exclude: over.exclude.or(self.exclude),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlameConfig {
+    pub moves: Option<bool>,
+    pub copies: Option<bool>,
+    pub ignore_revs_file: Option<PathBuf>,
+    pub mailmap_file: Option<PathBuf>,
+}
+
+impl BlameConfig {
+    /// `self` with every key set in `over` replaced.
+    pub fn overlay(self, over: BlameConfig) -> BlameConfig {
+        BlameConfig {
+            moves: over.moves.or(self.moves),
+            copies: over.copies.or(self.copies),
+            ignore_revs_file: over.ignore_revs_file.or(self.ignore_revs_file),
+            mailmap_file: over.mailmap_file.or(self.mailmap_file),
+        }
+    }
+}
+
+/// Policy rules evaluated by `tracy check`. Every rule is off unless its table is present.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesConfig {
+    pub min_references: Option<MinReferencesRule>,
+    pub forbidden_paths: Option<ForbiddenPathsRule>,
+    pub doc_comments_only: Option<DocCommentsOnlyRule>,
+    pub require_verification: Option<RequireVerificationRule>,
+    pub max_age: Option<MaxAgeRule>,
+}
+
+impl RulesConfig {
+    /// `self` with every rule set in `over` replaced.
+    pub fn overlay(self, over: RulesConfig) -> RulesConfig {
+        RulesConfig {
+            min_references: over.min_references.or(self.min_references),
+            forbidden_paths: over.forbidden_paths.or(self.forbidden_paths),
+            doc_comments_only: over.doc_comments_only.or(self.doc_comments_only),
+            require_verification: over.require_verification.or(self.require_verification),
+            max_age: over.max_age.or(self.max_age),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MinReferencesRule {
+    #[serde(default)]
+    pub severity: Severity,
+    pub slug: Option<Vec<String>>,
+    pub min: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForbiddenPathsRule {
+    #[serde(default)]
+    pub severity: Severity,
+    pub slug: Option<Vec<String>>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DocCommentsOnlyRule {
+    #[serde(default)]
+    pub severity: Severity,
+    pub slug: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequireVerificationRule {
+    #[serde(default)]
+    pub severity: Severity,
+    pub slug: Option<Vec<String>>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaxAgeRule {
+    #[serde(default)]
+    pub severity: Severity,
+    pub slug: Option<Vec<String>>,
+    pub days: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("failed to parse config file {path}: {source}")]
+    ParseJson {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("config file {path} has no {section} section")]
+    MissingSection { path: PathBuf, section: String },
+
+    #[error("invalid config file {path}:{issues}")]
+    Invalid { path: PathBuf, issues: Issues },
+
+    #[error("config file {0} extends itself")]
+    ExtendsCycle(PathBuf),
+
+    #[error("invalid value `{value}` for {name}: expected {expected}")]
+    InvalidEnv {
+        name: String,
+        value: String,
+        expected: String,
+    },
+
+    #[error("profile `{0}` is not defined in the config")]
+    UnknownProfile(String),
+}
+
+/// Load a config file, layered over the config it `extends`, if any.
+pub fn load_config(path: &Path) -> Result<Config, ConfigError> {
+    load_config_with(path, &mut |path| fs::read_to_string(path))
+}
+
+/// Load a config file as [`load_config`] does, with it and the configs it
+/// extends read by `read`, e.g. from a revision.
+pub fn load_config_with(
+    path: &Path,
+    read: &mut dyn FnMut(&Path) -> io::Result<String>,
+) -> Result<Config, ConfigError> {
+    load_extending(path, read, &mut Vec::new())
+}
+
+fn load_extending(
+    path: &Path,
+    read: &mut dyn FnMut(&Path) -> io::Result<String>,
+    chain: &mut Vec<PathBuf>,
+) -> Result<Config, ConfigError> {
+    let read_error = |e| ConfigError::Read {
+        path: path.to_path_buf(),
+        source: e,
+    };
+    // A config read from elsewhere than the disk is told apart by its path.
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| normalize_path(path));
+    if chain.contains(&canonical) {
+        return Err(ConfigError::ExtendsCycle(path.to_path_buf()));
+    }
+    chain.push(canonical);
+
+    let content = read(path).map_err(read_error)?;
+    let section = Section::of(path);
+    let (mut config, table) = section.read(path, &content)?;
+    let source = match section.name() {
+        Some(name) => format!("{} {name}", path.display()),
+        None => path.display().to_string(),
+    };
+    record_sources(&mut config, &table, &source);
+
+    let Some(extends) = &config.extends else {
+        return Ok(config);
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let base_path = dir.join(extends);
+    let base = load_extending(&base_path, read, chain)?;
+    let base_dir = base_path.parent().unwrap_or(Path::new(""));
+    Ok(base.rebase(base_dir).overlay(config))
+}
+
+/// `path` with its `.` components dropped and its `..` components resolved
+/// against the components before them, without touching the disk.
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(normal.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+/// Record `source` for every key set in `table`, and for the keys of each
+/// profile section with the section appended.
+fn record_sources(config: &mut Config, table: &toml::Table, source: &str) {
+    config.sources = key_paths(table)
+        .map(|key| (key, source.to_string()))
+        .collect();
+    let Some(toml::Value::Table(profiles)) = table.get("profile") else {
+        return;
+    };
+    for (name, profile) in &mut config.profile {
+        if let Some(toml::Value::Table(table)) = profiles.get(name) {
+            record_sources(profile, table, &format!("{source} [profile.{name}]"));
+        }
+    }
+}
+
+/// Dotted paths of the keys set in `table`, down to the keys of its tables.
+fn key_paths(table: &toml::Table) -> impl Iterator<Item = String> + '_ {
+    table
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "extends" | "profile"))
+        .flat_map(|(key, value)| match value {
+            toml::Value::Table(inner) => inner.keys().map(|k| format!("{key}.{k}")).collect(),
+            _ => vec![key.clone()],
+        })
+}
+
+pub fn find_config(start: &Path) -> Option<PathBuf> {
+    let mut dir = if start.is_file() {
+        start.parent()?.to_path_buf()
+    } else {
+        start.to_path_buf()
+    };
+
+    loop {
+        if let Some(config) = config_in(&dir) {
+            return Some(config);
+        }
+        dir = dir.parent()?.to_path_buf();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn config_in_with_propagates_read_errors() {
+        let found = config_in_with(Path::new("comms"), |path| match path.file_name() {
+            Some(name) if name == "tracy.toml" => Ok(None),
+            _ => Err("unreadable"),
+        });
+        assert_eq!(found, Err("unreadable"));
+    }
+
+    #[test]
+    fn normalize_path_resolves_parent_components() {
+        assert_eq!(
+            normalize_path(Path::new("./comms/../shared/./base.toml")),
+            Path::new("shared/base.toml")
+        );
+        assert_eq!(
+            normalize_path(Path::new("../a/../../b")),
+            Path::new("../../b")
+        );
+    }
+
+    #[test]
+    fn find_config_walks_up() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("a/b/c");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(repo.join("tracy.toml"), "quiet = true\n").unwrap();
+
+        let found = find_config(&nested).unwrap();
+        assert_eq!(found, repo.join("tracy.toml"));
+    }
+
+    #[test]
+    fn parses_config_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tracy.toml");
+        fs::write(
+            &path,
+            r#"
+format = "jsonl"
+quiet = true
+permalink_template = "gitea"
+[scan]
+slug = ["REQ"]
+[filter]
+include = ["src/**"]
+[blame]
+copies = true
+ignore_revs_file = ".git-blame-ignore-revs"
+"#,
+        )
+        .unwrap();
+
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.format, Some(OutputFormat::Jsonl));
+        assert_eq!(config.quiet, Some(true));
+        assert_eq!(config.permalink_template.as_deref(), Some("gitea"));
+        assert_eq!(config.scan.slug.as_deref(), Some(&["REQ".to_string()][..]));
+        assert_eq!(
+            config.filter.include.as_deref(),
+            Some(&["src/**".to_string()][..])
+        );
+        assert_eq!(config.blame.copies, Some(true));
+        assert_eq!(config.blame.moves, None);
+        assert_eq!(
+            config.blame.ignore_revs_file,
+            Some(PathBuf::from(".git-blame-ignore-revs"))
+        );
+    }
+
+    #[test]
+    fn parses_rules_tables() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tracy.toml");
+        fs::write(
+            &path,
+            r#"
+[rules.min_references]
+min = 2
+
+[rules.forbidden_paths]
+severity = "warning"
+slug = ["REQ"]
+paths = ["tests/**"]
+"#,
+        )
+        .unwrap();
+
+        let config = load_config(&path).unwrap();
+        let min_refs = config.rules.min_references.unwrap();
+        assert_eq!(min_refs.min, 2);
+        assert_eq!(min_refs.severity, Severity::Error);
+
+        let forbidden = config.rules.forbidden_paths.unwrap();
+        assert_eq!(forbidden.severity, Severity::Warning);
+        assert_eq!(forbidden.paths, vec!["tests/**".to_string()]);
+        assert!(config.rules.max_age.is_none());
+    }
+
+    #[test]
+    fn parses_one_or_many_roots() {
+        let config: Config = toml::from_str(r#"root = "src""#).unwrap();
+        assert!(matches!(config.root, Some(RootsConfig::One(path)) if path == Path::new("src")));
+
+        let config: Config = toml::from_str(
+            r#"
+[[root]]
+path = "../firmware"
+label = "fw"
+filter = { exclude = ["build/**"] }
+
+[[root]]
+path = "../tools"
+"#,
+        )
+        .unwrap();
+        let Some(RootsConfig::Many(roots)) = config.root else {
+            panic!("expected a root list");
+        };
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].label.as_deref(), Some("fw"));
+        assert_eq!(
+            roots[0].filter.exclude.as_deref(),
+            Some(&["build/**".to_string()][..])
+        );
+        assert_eq!(roots[1].path, PathBuf::from("../tools"));
+        assert_eq!(roots[1].label, None);
+    }
+
+    #[test]
+    fn extends_layers_over_the_base_config() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("shared")).unwrap();
+        fs::create_dir_all(dir.path().join("app")).unwrap();
+        fs::write(
+            dir.path().join("shared/base.toml"),
+            r#"
+quiet = true
+output = "report.json"
+[scan]
+slug = ["REQ"]
+[filter]
+exclude = ["vendor/**"]
+include_vendored = true
+"#,
+        )
+        .unwrap();
+        let path = dir.path().join("app/tracy.toml");
+        fs::write(
+            &path,
+            r#"
+extends = "../shared/base.toml"
+[filter]
+exclude = ["build/**"]
+"#,
+        )
+        .unwrap();
+
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.quiet, Some(true));
+        assert_eq!(config.scan.slug.as_deref(), Some(&["REQ".to_string()][..]));
+        assert_eq!(
+            config.filter.exclude.as_deref(),
+            Some(&["build/**".to_string()][..])
+        );
+        assert_eq!(config.filter.include_vendored, Some(true));
+        // Paths in the base stay relative to the base.
+        assert_eq!(
+            config.output,
+            Some(dir.path().join("app/../shared/report.json"))
+        );
+    }
+
+    #[test]
+    fn extends_cycles_are_an_error() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.toml"), r#"extends = "b.toml""#).unwrap();
+        fs::write(dir.path().join("b.toml"), r#"extends = "a.toml""#).unwrap();
+
+        let err = load_config(&dir.path().join("a.toml")).unwrap_err();
+        assert!(matches!(err, ConfigError::ExtendsCycle(path) if path.ends_with("a.toml")));
+    }
+
+    #[test]
+    fn profiles_overlay_the_base_and_record_sources() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("base.toml"),
+            r#"
+[profile.ci]
+include_blame = true
+"#,
+        )
+        .unwrap();
+        let path = dir.path().join("tracy.toml");
+        fs::write(
+            &path,
+            r#"
+extends = "base.toml"
+format = "json"
+[scan]
+slug = ["REQ"]
+[profile.ci]
+format = "sarif"
+[profile.ci.filter]
+exclude = ["tests/**"]
+"#,
+        )
+        .unwrap();
+
+        let config = load_config(&path).unwrap().with_profile("ci").unwrap();
+        assert_eq!(config.format, Some(OutputFormat::Sarif));
+        assert_eq!(config.include_blame, Some(true));
+        assert_eq!(config.scan.slug.as_deref(), Some(&["REQ".to_string()][..]));
+        let source = |key: &str| config.sources.get(key).cloned();
+        let file = path.display().to_string();
+        assert_eq!(source("format"), Some(format!("{file} [profile.ci]")));
+        assert_eq!(
+            source("filter.exclude"),
+            Some(format!("{file} [profile.ci]"))
+        );
+        assert_eq!(source("scan.slug"), Some(file));
+        assert_eq!(
+            source("include_blame"),
+            Some(format!(
+                "{} [profile.ci]",
+                dir.path().join("base.toml").display()
+            ))
+        );
+        assert_eq!(source("quiet"), None);
+
+        let err = load_config(&path)
+            .unwrap()
+            .with_profile("release")
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::UnknownProfile(name) if name == "release"));
+    }
+
+    #[test]
+    fn unknown_keys_fail_to_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tracy.toml");
+        fs::write(&path, "[scan]\nslugs = [\"REQ\"]\n").unwrap();
+
+        let err = load_config(&path).unwrap_err();
+        let ConfigError::Invalid { issues, .. } = &err else {
+            panic!("expected invalid config, got {err}");
+        };
+        assert_eq!(issues.0.len(), 1);
+        assert_eq!(issues.0[0].location, Some((2, 1)));
+        assert!(err.to_string().contains("did you mean `slug`?"));
+    }
+
+    #[test]
+    fn reads_config_from_package_manifests() {
+        let dir = TempDir::new().unwrap();
+        let cargo = dir.path().join("Cargo.toml");
+        fs::write(
+            &cargo,
+            "[package]\nname = \"app\"\n\n[package.metadata.tracy]\nformat = \"jsonl\"\n",
+        )
+        .unwrap();
+        let pyproject = dir.path().join("pyproject.toml");
+        fs::write(&pyproject, "[tool.tracy.scan]\nslug = [\"REQ\"]\n").unwrap();
+        let package = dir.path().join("package.json");
+        fs::write(&package, r#"{"name": "app", "tracy": {"quiet": true}}"#).unwrap();
+
+        let config = load_config(&cargo).unwrap();
+        assert_eq!(config.format, Some(OutputFormat::Jsonl));
+        assert_eq!(
+            config.sources["format"],
+            format!("{} [package.metadata.tracy]", cargo.display())
+        );
+        let config = load_config(&pyproject).unwrap();
+        assert_eq!(config.scan.slug, Some(vec!["REQ".to_string()]));
+        let config = load_config(&package).unwrap();
+        assert_eq!(config.quiet, Some(true));
+        assert_eq!(
+            config.sources["quiet"],
+            format!("{} \"tracy\"", package.display())
+        );
+
+        // A manifest is found like a `tracy.toml`, which is preferred.
+        assert_eq!(find_config(dir.path()), Some(cargo));
+        fs::write(dir.path().join("tracy.toml"), "").unwrap();
+        assert_eq!(find_config(dir.path()), Some(dir.path().join("tracy.toml")));
+    }
+
+    #[test]
+    fn manifests_without_a_tracy_section_are_skipped() {
+        let dir = TempDir::new().unwrap();
+        let cargo = dir.path().join("Cargo.toml");
+        fs::write(&cargo, "[package]\nname = \"app\"\n").unwrap();
+        let package = dir.path().join("package.json");
+        fs::write(&package, r#"{"tracy": {"qiuet": true}}"#).unwrap();
+
+        assert_eq!(find_config(dir.path()), Some(package.clone()));
+        let err = load_config(&cargo).unwrap_err();
+        assert!(matches!(err, ConfigError::MissingSection { .. }));
+        let err = load_config(&package).unwrap_err();
+        let ConfigError::Invalid { issues, .. } = &err else {
+            panic!("expected invalid config, got {err}");
+        };
+        assert_eq!(issues.0[0].location, None);
+        assert!(err.to_string().contains("unknown key `qiuet`"));
+    }
+
+    #[test]
+    fn merge_replaces_or_appends_lists() {
+        let config = Some(vec!["a/**".to_string(), "b/**".to_string()]);
+        let cli = ["b/**".to_string(), "c/**".to_string()];
+
+        assert_eq!(Merge::Replace.lists(&cli, config.clone()), ["b/**", "c/**"]);
+        assert_eq!(
+            Merge::Append.lists(&cli, config.clone()),
+            ["a/**", "b/**", "c/**"]
+        );
+        assert_eq!(Merge::Append.lists(&[], config.clone()), ["a/**", "b/**"]);
+        assert_eq!(Merge::Replace.lists(&cli, None), ["b/**", "c/**"]);
+    }
+}
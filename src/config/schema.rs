@@ -0,0 +1,350 @@
+//! The keys of `tracy.toml`. Config files are validated against them, and
+//! the published JSON Schema is generated from them.
+
+use super::Merge;
+use crate::output::{GraphCollapse, OutputFormat};
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+
+pub(super) enum Kind {
+    Bool,
+    Int,
+    String,
+    /// One of the names returned
+    Enum(fn() -> Vec<String>),
+    Globs,
+    Slugs,
+    Table(&'static [Key]),
+    /// A path, or an array of `[[root]]` tables
+    Roots,
+    /// `[profile.<name>]` tables of config keys
+    Profiles,
+}
+
+pub(super) struct Key {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub doc: &'static str,
+}
+
+const fn key(name: &'static str, kind: Kind, doc: &'static str) -> Key {
+    Key { name, kind, doc }
+}
+
+pub(super) static CONFIG: &[Key] = &[
+    key(
+        "extends",
+        Kind::String,
+        "A config this one builds on, relative to this file",
+    ),
+    key("root", Kind::Roots, "Scan root, or one table per scan root"),
+    key("format", Kind::Enum(format_names), "Output format"),
+    key("output", Kind::String, "Write output to this file"),
+    key("quiet", Kind::Bool, "Suppress stdout output"),
+    key(
+        "fail_on_empty",
+        Kind::Bool,
+        "Exit with an error if no matches are found",
+    ),
+    key(
+        "include_git_meta",
+        Kind::Bool,
+        "Include git repository metadata",
+    ),
+    key(
+        "include_blame",
+        Kind::Bool,
+        "Include git blame metadata for each match",
+    ),
+    key(
+        "include_permalinks",
+        Kind::Bool,
+        "Include a forge permalink for each match",
+    ),
+    key(
+        "permalink_template",
+        Kind::String,
+        "Permalink preset (github, gitlab, gitea, bitbucket) or url template",
+    ),
+    key(
+        "git_backend",
+        Kind::Enum(backend_names),
+        "How to access git",
+    ),
+    key(
+        "merge",
+        Kind::Enum(merge_names),
+        "How command line lists combine with the config's: replace (default) or append",
+    ),
+    key(
+        "markdown_limit",
+        Kind::Int,
+        "Most bytes of markdown output, truncating the tables to fit (0 for no limit)",
+    ),
+    key(
+        "graph_collapse",
+        Kind::Enum(graph_collapse_names),
+        "Merge the code nodes of dot and mermaid graphs into directories or components",
+    ),
+    key(
+        "graph_depth",
+        Kind::Int,
+        "Levels of directories, file and scopes shown in dot and mermaid graphs",
+    ),
+    key(
+        "template",
+        Kind::String,
+        "Template the template format renders, relative to this file, or builtin:<name>",
+    ),
+    key(
+        "requirement_url",
+        Kind::String,
+        "Link to each requirement, with {id} and {slug} replaced",
+    ),
+    key("scan", Kind::Table(SCAN), "Scan settings"),
+    key("filter", Kind::Table(FILTER), "Which files are scanned"),
+    key("blame", Kind::Table(BLAME), "Blame settings"),
+    key(
+        "rules",
+        Kind::Table(RULES),
+        "Policy rules evaluated by `tracy check`",
+    ),
+    key(
+        "profile",
+        Kind::Profiles,
+        "Overrides applied with `--profile <name>`",
+    ),
+];
+
+static SCAN: &[Key] = &[key("slug", Kind::Slugs, "Slugs of the markers to find")];
+
+static FILTER: &[Key] = &[
+    key("include_vendored", Kind::Bool, "Include vendored files"),
+    key("include_generated", Kind::Bool, "Include generated files"),
+    key(
+        "include_documentation",
+        Kind::Bool,
+        "Include documentation files",
+    ),
+    key(
+        "include_submodules",
+        Kind::Bool,
+        "Scan submodules as repositories of their own",
+    ),
+    key(
+        "include_hidden",
+        Kind::Bool,
+        "Include hidden files and directories",
+    ),
+    key(
+        "include_gitignored",
+        Kind::Bool,
+        "Include files ignored by .gitignore",
+    ),
+    key("follow_symlinks", Kind::Bool, "Follow symbolic links"),
+    key(
+        "max_depth",
+        Kind::Int,
+        "Descend at most this many directories below the root",
+    ),
+    key(
+        "one_file_system",
+        Kind::Bool,
+        "Do not cross file system boundaries",
+    ),
+    key(
+        "include",
+        Kind::Globs,
+        "Only include paths matching these globs",
+    ),
+    key("exclude", Kind::Globs, "Exclude paths matching these globs"),
+];
+
+pub(super) static ROOT: &[Key] = &[
+    key(
+        "path",
+        Kind::String,
+        "Directory to scan, relative to the config",
+    ),
+    key(
+        "label",
+        Kind::String,
+        "Tags and prefixes the root's entries",
+    ),
+    key(
+        "filter",
+        Kind::Table(FILTER),
+        "Filters overriding [filter] for this root",
+    ),
+];
+
+static BLAME: &[Key] = &[
+    key(
+        "moves",
+        Kind::Bool,
+        "Blame through moved lines (git blame -M)",
+    ),
+    key(
+        "copies",
+        Kind::Bool,
+        "Blame through copied lines (git blame -C)",
+    ),
+    key(
+        "ignore_revs_file",
+        Kind::String,
+        "Commits to skip when blaming",
+    ),
+    key(
+        "mailmap_file",
+        Kind::String,
+        "Extra mailmap applied to blame authors",
+    ),
+];
+
+static RULES: &[Key] = &[
+    key(
+        "min_references",
+        Kind::Table(&[
+            SEVERITY,
+            RULE_SLUG,
+            key("min", Kind::Int, "Minimum references per requirement id"),
+        ]),
+        "Require a minimum number of references per requirement id",
+    ),
+    key(
+        "forbidden_paths",
+        Kind::Table(&[
+            SEVERITY,
+            RULE_SLUG,
+            key("paths", Kind::Globs, "Paths markers must not appear in"),
+        ]),
+        "Forbid markers in some paths",
+    ),
+    key(
+        "doc_comments_only",
+        Kind::Table(&[SEVERITY, RULE_SLUG]),
+        "Require markers to be in doc comments",
+    ),
+    key(
+        "require_verification",
+        Kind::Table(&[
+            SEVERITY,
+            RULE_SLUG,
+            key("paths", Kind::Globs, "Paths one reference must be in"),
+        ]),
+        "Require a reference to each requirement in some paths",
+    ),
+    key(
+        "max_age",
+        Kind::Table(&[
+            SEVERITY,
+            RULE_SLUG,
+            key("days", Kind::Int, "Maximum age in days"),
+        ]),
+        "Limit the age of marker lines per git blame",
+    ),
+];
+
+const SEVERITY: Key = key(
+    "severity",
+    Kind::Enum(severity_names),
+    "Severity of violations",
+);
+const RULE_SLUG: Key = key("slug", Kind::Slugs, "Only apply the rule to these slugs");
+
+fn format_names() -> Vec<String> {
+    OutputFormat::value_variants()
+        .iter()
+        .filter_map(|format| format.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn merge_names() -> Vec<String> {
+    Merge::value_variants()
+        .iter()
+        .filter_map(|merge| merge.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn graph_collapse_names() -> Vec<String> {
+    GraphCollapse::value_variants()
+        .iter()
+        .filter_map(|collapse| collapse.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn backend_names() -> Vec<String> {
+    // Both, whether or not this build has the `gix` feature.
+    vec!["cli".to_string(), "gix".to_string()]
+}
+
+fn severity_names() -> Vec<String> {
+    ["note", "warning", "error"].map(String::from).to_vec()
+}
+
+/// The JSON Schema of `tracy.toml`.
+pub fn json_schema() -> Value {
+    let profile = CONFIG
+        .iter()
+        .filter(|key| !matches!(key.kind, Kind::Profiles) && key.name != "extends");
+    let mut schema = object(CONFIG.iter());
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["title"] = json!("tracy.toml");
+    schema["$defs"] = json!({ "profile": object(profile) });
+    schema
+}
+
+fn object<'a>(keys: impl Iterator<Item = &'a Key>) -> Value {
+    let properties: Map<String, Value> = keys
+        .map(|key| {
+            let mut schema = kind_schema(&key.kind);
+            schema["description"] = json!(key.doc);
+            (key.name.to_string(), schema)
+        })
+        .collect();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+fn kind_schema(kind: &Kind) -> Value {
+    let strings = json!({ "type": "array", "items": { "type": "string" } });
+    match kind {
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::Int => json!({ "type": "integer", "minimum": 0 }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Enum(names) => json!({ "enum": names() }),
+        Kind::Globs | Kind::Slugs => strings,
+        Kind::Table(keys) => object(keys.iter()),
+        Kind::Roots => json!({
+            "oneOf": [
+                { "type": "string" },
+                { "type": "array", "items": object(ROOT.iter()) },
+            ]
+        }),
+        Kind::Profiles => json!({
+            "type": "object",
+            "additionalProperties": { "$ref": "#/$defs/profile" },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn published_schema_is_current() {
+        let published = include_str!("../../docs/tracy.schema.json");
+        let current = serde_json::to_string_pretty(&json_schema()).unwrap() + "\n";
+        assert!(
+            published == current,
+            "docs/tracy.schema.json is stale; regenerate it with `tracy config schema`"
+        );
+    }
+}
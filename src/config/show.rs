@@ -0,0 +1,208 @@
+//! `tracy config show`: the effective configuration as TOML, each value
+//! commented with where it came from.
+
+use super::Sources;
+use crate::args::{CLI_SOURCE, ResolvedArgs, ScanRoot};
+use crate::filter::FilterArgs;
+use clap::ValueEnum;
+use std::path::Path;
+use toml::Value;
+
+/// The source of values nothing set.
+const DEFAULT: &str = "default";
+
+struct Show<'a> {
+    sources: &'a Sources,
+    lines: Vec<String>,
+}
+
+impl Show<'_> {
+    fn source(&self, key: &str) -> &str {
+        self.sources.get(key).map_or(DEFAULT, String::as_str)
+    }
+
+    /// A `key = value` line for the dotted config path `key`.
+    fn value(&mut self, key: &str, value: impl Into<Value>) {
+        let source = self.source(key).to_string();
+        self.line(key, value.into(), &source);
+    }
+
+    fn line(&mut self, key: &str, value: Value, source: &str) {
+        let name = key.rsplit('.').next().unwrap_or(key);
+        self.lines.push(format!("{name} = {value}  # {source}"));
+    }
+
+    fn header(&mut self, header: &str) {
+        self.lines.push(String::new());
+        self.lines.push(header.to_string());
+    }
+
+    /// The filter values of a root; `root_source` is the config the root's
+    /// own `filter` table is in, if it has one.
+    fn filter(&mut self, filter: &FilterArgs, root_source: Option<&str>) {
+        let values: [(&str, Option<Value>); 11] = [
+            ("include_vendored", Some(filter.include_vendored.into())),
+            ("include_generated", Some(filter.include_generated.into())),
+            (
+                "include_documentation",
+                Some(filter.include_documentation.into()),
+            ),
+            ("include_submodules", Some(filter.include_submodules.into())),
+            ("include_hidden", Some(filter.include_hidden.into())),
+            ("include_gitignored", Some(filter.include_gitignored.into())),
+            ("follow_symlinks", Some(filter.follow_symlinks.into())),
+            ("max_depth", filter.max_depth.map(|n| Value::from(n as i64))),
+            ("one_file_system", Some(filter.one_file_system.into())),
+            ("include", Some(filter.include.clone().into())),
+            ("exclude", Some(filter.exclude.clone().into())),
+        ];
+        for (name, value) in values {
+            let Some(value) = value else { continue };
+            let key = format!("filter.{name}");
+            // Values set by neither `[filter]` nor the command line come
+            // from the root's table, unless they are the defaults.
+            let is_default = match &value {
+                Value::Boolean(set) => !set,
+                Value::Array(items) => items.is_empty(),
+                _ => false,
+            };
+            let source = match (self.sources.get(&key), root_source) {
+                (Some(source), _) => source.clone(),
+                (None, Some(root)) if !is_default => root.to_string(),
+                (None, _) => DEFAULT.to_string(),
+            };
+            self.line(&key, value, &source);
+        }
+    }
+}
+
+/// Render the effective configuration of a run.
+pub fn show_config(args: &ResolvedArgs) -> String {
+    let mut show = Show {
+        sources: &args.sources,
+        lines: Vec::new(),
+    };
+
+    if let Some(profile) = &args.profile {
+        show.lines.push(format!(
+            "# profile `{}` (from {})",
+            profile.name, profile.source
+        ));
+    }
+    show.value("format", enum_name(args.format));
+    if let Some(output) = &args.output {
+        show.value("output", path(output));
+    }
+    show.value("quiet", args.quiet);
+    show.value("fail_on_empty", args.fail_on_empty);
+    show.value("include_git_meta", args.include_git_meta);
+    show.value("include_blame", args.include_blame);
+    show.value("include_permalinks", args.include_permalinks);
+    if let Some(template) = &args.permalink_template {
+        show.value("permalink_template", template.as_str());
+    }
+    show.value("git_backend", enum_name(args.git_backend));
+    show.value("merge", enum_name(args.cli.merge));
+    show.value("markdown_limit", args.format_options.markdown_limit as i64);
+    if let Some(collapse) = args.format_options.graph_collapse {
+        show.value("graph_collapse", enum_name(collapse));
+    }
+    if let Some(depth) = args.format_options.graph_depth {
+        show.value("graph_depth", depth as i64);
+    }
+    if let Some(template) = &args.format_options.template {
+        show.value("template", template.to_string());
+    }
+    if let Some(url) = &args.format_options.requirement_url {
+        show.value("requirement_url", url.as_str());
+    }
+    let single = match args.roots.as_slice() {
+        [root] if root.label.is_none() => Some(root),
+        _ => None,
+    };
+    if let Some(root) = single {
+        show.value("root", path(&root.path));
+    }
+
+    show.header("[scan]");
+    show.value("scan.slug", args.scan.slug.clone());
+
+    if let Some(root) = single {
+        show.header("[filter]");
+        show.filter(&root.filter, None);
+    }
+
+    show.header("[blame]");
+    show.value("blame.moves", args.blame.blame_moves);
+    show.value("blame.copies", args.blame.blame_copies);
+    if let Some(file) = &args.blame.blame_ignore_revs_file {
+        show.value("blame.ignore_revs_file", path(file));
+    }
+    if let Some(file) = &args.blame.blame_mailmap_file {
+        show.value("blame.mailmap_file", path(file));
+    }
+
+    if single.is_none() {
+        for root in &args.roots {
+            show_root(&mut show, root);
+        }
+    }
+
+    let rules = &args.rules;
+    let rules = [
+        ("min_references", Value::try_from(&rules.min_references)),
+        ("forbidden_paths", Value::try_from(&rules.forbidden_paths)),
+        (
+            "doc_comments_only",
+            Value::try_from(&rules.doc_comments_only),
+        ),
+        (
+            "require_verification",
+            Value::try_from(&rules.require_verification),
+        ),
+        ("max_age", Value::try_from(&rules.max_age)),
+    ];
+    for (name, rule) in rules {
+        let Ok(Value::Table(rule)) = rule else {
+            continue;
+        };
+        let key = format!("rules.{name}");
+        let header = format!("[{key}]  # {}", show.source(&key));
+        show.header(&header);
+        for (key, value) in rule {
+            show.lines.push(format!("{key} = {value}"));
+        }
+    }
+
+    show.lines.join("\n")
+}
+
+/// The effective configuration of a run as a table: the values of
+/// [`show_config`], without their sources.
+pub fn effective_config(args: &ResolvedArgs) -> toml::Table {
+    toml::from_str(&show_config(args)).unwrap_or_default()
+}
+
+fn show_root(show: &mut Show, root: &ScanRoot) {
+    let source = show.source("root").to_string();
+    show.header("[[root]]");
+    show.line("path", path(&root.path), &source);
+    if let Some(label) = &root.label {
+        show.line("label", label.as_str().into(), &source);
+    }
+    show.header("[root.filter]");
+    // Roots given on the command line have no filter table of their own.
+    let root_source = (source != CLI_SOURCE).then_some(source.as_str());
+    show.filter(&root.filter, root_source);
+}
+
+fn enum_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+fn path(path: &Path) -> Value {
+    path.display().to_string().into()
+}
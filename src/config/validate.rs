@@ -0,0 +1,295 @@
+//! Checks deserializing does not make: unknown keys, with suggestions, and
+//! globs and slugs, each located by line and column.
+
+use super::schema::{CONFIG, Key, Kind, ROOT};
+use crate::scan::slug_error;
+use std::fmt;
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, TableLike, Value};
+
+/// A problem found in a config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Line and column, for formats with spans
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Issue {
+    pub(super) fn without_location(self) -> Issue {
+        Issue {
+            location: None,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{line}:{column}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The issues of a file, one per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issues(pub Vec<Issue>);
+
+impl fmt::Display for Issues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.0 {
+            write!(f, "\n  {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the config in a file's text, which must be valid TOML, at the table
+/// `section` (empty for the whole file); the issues are empty for a valid
+/// config.
+pub(super) fn validate(content: &str, section: &[&str]) -> Vec<Issue> {
+    let Ok(document) = ImDocument::parse(content) else {
+        return Vec::new();
+    };
+    let Some(table) = section
+        .iter()
+        .try_fold(document.as_table() as &dyn TableLike, |table, key| {
+            table.get(key)?.as_table_like()
+        })
+    else {
+        return Vec::new();
+    };
+    let mut check = Check {
+        content,
+        issues: Vec::new(),
+    };
+    check.table(table, CONFIG, "");
+    check.issues.sort_by_key(|issue| issue.location);
+    check.issues
+}
+
+struct Check<'a> {
+    content: &'a str,
+    issues: Vec<Issue>,
+}
+
+impl Check<'_> {
+    fn issue(&mut self, span: Option<Range<usize>>, message: String) {
+        let start = span.map_or(0, |span| span.start.min(self.content.len()));
+        let before = &self.content[..start];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .map_or(0, |line| line.chars().count())
+            + 1;
+        self.issues.push(Issue {
+            location: Some((line, column)),
+            message,
+        });
+    }
+
+    /// `path` is the dotted path of the table, empty at the top level.
+    fn table(&mut self, table: &dyn TableLike, keys: &[Key], path: &str) {
+        for (name, item) in table.iter() {
+            let span = table.get_key_value(name).and_then(|(key, _)| key.span());
+            match keys.iter().find(|key| key.name == name) {
+                Some(key) => self.item(item, &key.kind, &join(path, name)),
+                None => {
+                    let place = if path.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" in [{path}]")
+                    };
+                    let suggestion = suggest(name, keys)
+                        .map(|key| format!("; did you mean `{key}`?"))
+                        .unwrap_or_default();
+                    self.issue(span, format!("unknown key `{name}`{place}{suggestion}"));
+                }
+            }
+        }
+    }
+
+    fn item(&mut self, item: &Item, kind: &Kind, path: &str) {
+        match kind {
+            Kind::Table(keys) => {
+                if let Some(table) = item.as_table_like() {
+                    self.table(table, keys, path);
+                }
+            }
+            Kind::Roots => {
+                if let Some(roots) = item.as_array_of_tables() {
+                    for root in roots.iter() {
+                        self.table(root, ROOT, path);
+                    }
+                } else if let Some(roots) = item.as_array() {
+                    for root in roots.iter().filter_map(Value::as_inline_table) {
+                        self.table(root, ROOT, path);
+                    }
+                }
+            }
+            Kind::Profiles => {
+                let Some(profiles) = item.as_table_like() else {
+                    return;
+                };
+                for (name, profile) in profiles.iter() {
+                    let Some(profile) = profile.as_table_like() else {
+                        continue;
+                    };
+                    for (key, _) in profile.iter() {
+                        if matches!(key, "extends" | "profile") {
+                            let span = profile.get_key_value(key).and_then(|(key, _)| key.span());
+                            self.issue(span, format!("`{key}` cannot be set in a profile"));
+                        }
+                    }
+                    self.table(profile, CONFIG, &join(path, name));
+                }
+            }
+            Kind::Globs => {
+                for value in item.as_array().into_iter().flat_map(|items| items.iter()) {
+                    if let Some(glob) = value.as_str()
+                        && let Err(e) = glob::Pattern::new(glob)
+                    {
+                        self.issue(
+                            value.span(),
+                            format!("invalid glob `{glob}` in `{path}`: {e}"),
+                        );
+                    }
+                }
+            }
+            Kind::Slugs => {
+                for value in item.as_array().into_iter().flat_map(|items| items.iter()) {
+                    if let Some(slug) = value.as_str()
+                        && let Some(reason) = slug_error(slug)
+                    {
+                        self.issue(
+                            value.span(),
+                            format!("invalid slug `{slug}` in `{path}`: {reason}"),
+                        );
+                    }
+                }
+            }
+            // Type mismatches are reported when the file is deserialized.
+            Kind::Bool | Kind::Int | Kind::String | Kind::Enum(_) => {}
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The key closest to an unknown `name`, if any is close.
+fn suggest<'a>(name: &str, keys: &'a [Key]) -> Option<&'a str> {
+    keys.iter()
+        .map(|key| (strsim::jaro_winkler(name, key.name), key.name))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(content: &str) -> Vec<String> {
+        validate(content, &[])
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn reports_unknown_keys_with_suggestions() {
+        let content = r#"
+format = "json"
+[scan]
+slugs = ["REQ"]
+
+[filters]
+include = ["src/**"]
+
+[[root]]
+path = "a"
+lable = "x"
+
+[profile.ci]
+extends = "base.toml"
+[profile.ci.blame]
+movse = true
+zzz = 1
+"#;
+        assert_eq!(
+            messages(content),
+            vec![
+                "4:1: unknown key `slugs` in [scan]; did you mean `slug`?",
+                "6:2: unknown key `filters`; did you mean `filter`?",
+                "11:1: unknown key `lable` in [root]; did you mean `label`?",
+                "14:1: `extends` cannot be set in a profile",
+                "16:1: unknown key `movse` in [profile.ci.blame]; did you mean `moves`?",
+                "17:1: unknown key `zzz` in [profile.ci.blame]",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_globs_and_slugs() {
+        let content = r#"
+[scan]
+slug = ["REQ", "REQ-", "  "]
+[filter]
+exclude = ["ok/**", "src/[a"]
+[rules.forbidden_paths]
+paths = ["***"]
+"#;
+        assert_eq!(
+            messages(content),
+            vec![
+                "3:16: invalid slug `REQ-` in `scan.slug`: markers are `{SLUG}-{NUMBER}`, so slugs cannot end with `-`",
+                "3:24: invalid slug `  ` in `scan.slug`: slugs cannot contain whitespace",
+                "5:21: invalid glob `src/[a` in `filter.exclude`: Pattern syntax error near position 4: invalid range pattern",
+                "7:10: invalid glob `***` in `rules.forbidden_paths.paths`: Pattern syntax error near position 2: wildcards are either regular `*` or recursive `**`",
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_a_valid_config() {
+        let content = r#"
+extends = "base.toml"
+root = "src"
+[scan]
+slug = ["REQ"]
+[rules.max_age]
+days = 30
+[profile.ci]
+format = "sarif"
+"#;
+        assert_eq!(messages(content), Vec::<String>::new());
+    }
+
+    #[test]
+    fn locates_issues_in_a_manifest_section() {
+        let content = r#"
+[package]
+name = "app"
+
+[package.metadata.tracy]
+formt = "json"
+"#;
+        let issues: Vec<_> = validate(content, &["package", "metadata", "tracy"])
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            issues,
+            vec!["6:1: unknown key `formt`; did you mean `format`?"]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Trace link diff between two scans.
+//!
+//! Entries are matched per requirement id by file, scope and comment
+//! fingerprint, so a marker that only shifted lines is not reported, and one
+//! that moved to another file or scope is reported as moved rather than
+//! removed and re-added.
+
+use crate::scan::{Entry, ScanResult};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Moved,
+    ChangedText,
+}
+
+impl ChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Moved => "moved",
+            ChangeKind::ChangedText => "changed_text",
+        }
+    }
+}
+
+/// A single trace link change. `base` is absent for additions, `head` for removals.
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub requirement_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<Entry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<Entry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffReport {
+    /// The base revision as given
+    pub base: String,
+    pub base_sha: String,
+    /// The head revision as given; absent when diffing against the working tree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_sha: Option<String>,
+    pub changes: Vec<Change>,
+}
+
+/// A parsed `base..head`, `base...head` or `base` argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevRange {
+    pub base: String,
+    /// `None` compares against the working tree
+    pub head: Option<String>,
+    /// `base...head`: use the merge base of the two as the base
+    pub symmetric: bool,
+}
+
+impl RevRange {
+    pub fn parse(range: &str) -> Self {
+        let (base, head, symmetric) = if let Some((base, head)) = range.split_once("...") {
+            (base, head, true)
+        } else if let Some((base, head)) = range.split_once("..") {
+            (base, head, false)
+        } else {
+            (range, "", false)
+        };
+
+        let or_head = |rev: &str| {
+            if rev.is_empty() {
+                "HEAD".to_string()
+            } else {
+                rev.to_string()
+            }
+        };
+
+        RevRange {
+            base: or_head(base),
+            head: if range.contains("..") {
+                Some(or_head(head))
+            } else {
+                None
+            },
+            symmetric,
+        }
+    }
+}
+
+/// Compare two scans, returning changes ordered by requirement id then kind.
+pub fn diff_results(base: ScanResult, mut head: ScanResult) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for (requirement_id, base_entries) in base {
+        let head_entries = head.remove(&requirement_id).unwrap_or_default();
+        diff_requirement(&requirement_id, base_entries, head_entries, &mut changes);
+    }
+    for (requirement_id, head_entries) in head {
+        diff_requirement(&requirement_id, Vec::new(), head_entries, &mut changes);
+    }
+
+    changes.sort_by(|a, b| (&a.requirement_id, a.kind).cmp(&(&b.requirement_id, b.kind)));
+    changes
+}
+
+type EntryMatcher<'a> = dyn Fn(&Entry, &Entry) -> bool + 'a;
+
+fn diff_requirement(
+    requirement_id: &str,
+    base: Vec<Entry>,
+    head: Vec<Entry>,
+    changes: &mut Vec<Change>,
+) {
+    let mut base: Vec<Option<Entry>> = base.into_iter().map(Some).collect();
+    let mut head: Vec<Option<Entry>> = head.into_iter().map(Some).collect();
+
+    let same_place = |a: &Entry, b: &Entry| a.file == b.file && a.scope_path() == b.scope_path();
+
+    // Passes in order of confidence. Identical text in the same file and scope
+    // is unchanged whatever its line; identical text elsewhere has moved; a
+    // different text in the same file and scope was edited in place.
+    let passes: [(Option<ChangeKind>, &EntryMatcher<'_>); 4] = [
+        (None, &|a, b| {
+            same_place(a, b) && a.line == b.line && a.fingerprint() == b.fingerprint()
+        }),
+        (None, &|a, b| {
+            same_place(a, b) && a.fingerprint() == b.fingerprint()
+        }),
+        (Some(ChangeKind::Moved), &|a, b| {
+            a.fingerprint() == b.fingerprint()
+        }),
+        (Some(ChangeKind::ChangedText), &same_place),
+    ];
+
+    for (kind, matches) in passes {
+        for base_slot in base.iter_mut() {
+            let Some(base_entry) = base_slot.as_ref() else {
+                continue;
+            };
+            let Some(head_slot) = head
+                .iter_mut()
+                .find(|h| h.as_ref().is_some_and(|h| matches(base_entry, h)))
+            else {
+                continue;
+            };
+
+            let base_entry = base_slot.take();
+            let head_entry = head_slot.take();
+            if let Some(kind) = kind {
+                changes.push(Change {
+                    kind,
+                    requirement_id: requirement_id.to_string(),
+                    base: base_entry,
+                    head: head_entry,
+                });
+            }
+        }
+    }
+
+    for entry in base.into_iter().flatten() {
+        changes.push(Change {
+            kind: ChangeKind::Removed,
+            requirement_id: requirement_id.to_string(),
+            base: Some(entry),
+            head: None,
+        });
+    }
+    for entry in head.into_iter().flatten() {
+        changes.push(Change {
+            kind: ChangeKind::Added,
+            requirement_id: requirement_id.to_string(),
+            base: None,
+            head: Some(entry),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::ScopeItem;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    fn entry(file: &str, line: usize, text: &str, scope: Option<&str>) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line,
+            comment_text: text.to_string(),
+            above: None,
+            below: None,
+            inline: None,
+            scope: scope
+                .map(|name| {
+                    vec![ScopeItem {
+                        kind: "function_item".to_string(),
+                        name: Some(name.to_string()),
+                        line: 1,
+                    }]
+                })
+                .unwrap_or_default(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    fn result(entries: Vec<(&str, Entry)>) -> ScanResult {
+        let mut results: ScanResult = BTreeMap::new();
+        for (id, entry) in entries {
+            results.entry(id.to_string()).or_default().push(entry);
+        }
+        results
+    }
+
+    fn kinds(changes: &[Change]) -> Vec<(&str, ChangeKind)> {
+        changes
+            .iter()
+            .map(|c| (c.requirement_id.as_str(), c.kind))
+            .collect()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(
+            RevRange::parse("main..feature"),
+            RevRange {
+                base: "main".to_string(),
+                head: Some("feature".to_string()),
+                symmetric: false,
+            }
+        );
+        assert_eq!(
+            RevRange::parse("main...feature"),
+            RevRange {
+                base: "main".to_string(),
+                head: Some("feature".to_string()),
+                symmetric: true,
+            }
+        );
+        assert_eq!(RevRange::parse("v1.0").head, None);
+        assert_eq!(RevRange::parse("v1.0..").head.as_deref(), Some("HEAD"));
+    }
+
+    #[test]
+    fn line_shift_is_not_a_change() {
+        let base = result(vec![("REQ-1", entry("a.rs", 3, "// REQ-1", Some("f")))]);
+        let head = result(vec![("REQ-1", entry("a.rs", 30, "//  REQ-1", Some("f")))]);
+        assert!(diff_results(base, head).is_empty());
+    }
+
+    #[test]
+    fn reports_moved_entries() {
+        let base = result(vec![
+            ("REQ-1", entry("a.rs", 3, "// REQ-1", Some("f"))),
+            ("REQ-2", entry("a.rs", 9, "// REQ-2", Some("g"))),
+        ]);
+        let head = result(vec![
+            ("REQ-1", entry("a.rs", 10, "// REQ-1", Some("h"))),
+            ("REQ-2", entry("b.rs", 1, "// REQ-2", Some("g"))),
+        ]);
+
+        let changes = diff_results(base, head);
+        assert_eq!(
+            kinds(&changes),
+            vec![("REQ-1", ChangeKind::Moved), ("REQ-2", ChangeKind::Moved)]
+        );
+        assert_eq!(changes[0].base.as_ref().unwrap().line, 3);
+        assert_eq!(changes[0].head.as_ref().unwrap().line, 10);
+    }
+
+    #[test]
+    fn reports_changed_text_added_and_removed() {
+        let base = result(vec![
+            ("REQ-1", entry("a.rs", 3, "// REQ-1 old", Some("f"))),
+            ("REQ-2", entry("a.rs", 9, "// REQ-2", Some("g"))),
+        ]);
+        let head = result(vec![
+            ("REQ-1", entry("a.rs", 3, "// REQ-1 new", Some("f"))),
+            ("REQ-3", entry("a.rs", 12, "// REQ-3", None)),
+        ]);
+
+        let changes = diff_results(base, head);
+        assert_eq!(
+            kinds(&changes),
+            vec![
+                ("REQ-1", ChangeKind::ChangedText),
+                ("REQ-2", ChangeKind::Removed),
+                ("REQ-3", ChangeKind::Added),
+            ]
+        );
+    }
+}
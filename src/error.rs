@@ -1,8 +1,10 @@
 use thiserror::Error;
 
+use crate::check::CheckError;
 use crate::config::ConfigError;
 use crate::filter::FilterError;
 use crate::git::GitError;
+use crate::output::OutputError;
 use crate::scan::ScanError;
 
 #[derive(Debug, Error)]
@@ -19,15 +21,36 @@ pub enum TracyError {
     #[error(transparent)]
     Config(#[from] ConfigError),
 
+    #[error(transparent)]
+    Check(#[from] CheckError),
+
     #[error("failed to serialize output: {0}")]
     Serialize(#[from] serde_json::Error),
 
+    #[error(transparent)]
+    Output(#[from] OutputError),
+
     #[error("failed to write output file: {0}")]
     WriteOutput(#[from] std::io::Error),
 
+    #[error("not writing a binary format to a terminal (use --output or redirect stdout)")]
+    BinaryToTerminal,
+
     #[error("no matches found")]
     NoResults,
 
+    #[error("--rev cannot be used with `tracy {0}`")]
+    RevUnsupported(&'static str),
+
+    #[error("`tracy {0}` scans a single root")]
+    MultipleRootsUnsupported(&'static str),
+
+    #[error("duplicate root label `{0}`")]
+    DuplicateRootLabel(String),
+
+    #[error("no config file found (searched for tracy.toml upwards)")]
+    NoConfig,
+
     #[error("no slugs specified (use --slug or set [scan].slug in tracy.toml)")]
     NoSlugs,
 }
@@ -1,18 +1,42 @@
 use clap::Args;
 
-#[derive(Debug, Default, Args)]
+#[derive(Debug, Clone, Default, Args)]
 pub struct FilterArgs {
-    #[arg(long, help = "Include vendored files")]
+    #[arg(long, global = true, help = "Include vendored files")]
     pub include_vendored: bool,
 
-    #[arg(long, help = "Include generated files")]
+    #[arg(long, global = true, help = "Include generated files")]
     pub include_generated: bool,
 
-    #[arg(long, help = "Include submodules")]
+    #[arg(long, global = true, help = "Include documentation files")]
+    pub include_documentation: bool,
+
+    #[arg(long, global = true, help = "Include submodules")]
     pub include_submodules: bool,
 
+    #[arg(long, global = true, help = "Include hidden files and directories")]
+    pub include_hidden: bool,
+
+    #[arg(long, global = true, help = "Include files ignored by .gitignore")]
+    pub include_gitignored: bool,
+
+    #[arg(long, global = true, help = "Follow symbolic links")]
+    pub follow_symlinks: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "N",
+        help = "Descend at most N directories below the root"
+    )]
+    pub max_depth: Option<usize>,
+
+    #[arg(long, global = true, help = "Do not cross file system boundaries")]
+    pub one_file_system: bool,
+
     #[arg(
         long,
+        global = true,
         value_name = "GLOB",
         help = "Only include paths matching this glob (repeatable)"
     )]
@@ -20,6 +44,7 @@ pub struct FilterArgs {
 
     #[arg(
         long,
+        global = true,
         value_name = "GLOB",
         help = "Exclude paths matching this glob (repeatable)"
     )]
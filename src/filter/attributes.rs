@@ -0,0 +1,446 @@
+//! `.gitattributes` resolution, following git's rules.
+//!
+//! Every `.gitattributes` from the repository root down to a path's directory
+//! applies, deeper files and later lines taking precedence, and
+//! `$GIT_DIR/info/attributes` overrides them all. Patterns match like
+//! `.gitignore` patterns, except that negated patterns are invalid and a
+//! pattern naming a directory does not apply to the files inside it. Macros
+//! (`[attr]name ...`) are only honored at the top level.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    Set,
+    Unset,
+    Unspecified,
+    Value(String),
+}
+
+type Assignments = Vec<(String, State)>;
+
+/// One parsed attributes file. `lines[i]` holds the assignments of the pattern
+/// at index `i` of `globs`.
+struct AttrFile {
+    globs: GlobSet,
+    lines: Vec<Assignments>,
+}
+
+/// The linguist attributes that decide whether a path is skipped.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Linguist {
+    pub vendored: bool,
+    pub generated: bool,
+    pub documentation: bool,
+}
+
+/// Where attribute files are looked up for a scan root.
+#[derive(Debug, Clone)]
+pub struct AttributesLocation {
+    /// The enclosing repository's work tree, or the scan root outside a repository
+    pub top: PathBuf,
+    /// The scan root relative to `top`
+    pub prefix: PathBuf,
+    info: Option<PathBuf>,
+}
+
+impl AttributesLocation {
+    pub fn discover(scan_root: &Path) -> Self {
+        let root = fs::canonicalize(scan_root).unwrap_or_else(|_| scan_root.to_path_buf());
+        let Some(top) = root.ancestors().find(|dir| dir.join(".git").exists()) else {
+            return AttributesLocation {
+                top: scan_root.to_path_buf(),
+                prefix: PathBuf::new(),
+                info: None,
+            };
+        };
+
+        let git_dir = top.join(".git");
+        AttributesLocation {
+            top: top.to_path_buf(),
+            prefix: root
+                .strip_prefix(top)
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+            // A `.git` file points elsewhere (worktrees, submodules); only a
+            // plain git dir is looked into.
+            info: git_dir
+                .is_dir()
+                .then(|| git_dir.join("info").join("attributes")),
+        }
+    }
+}
+
+/// Resolved attribute files for a set of paths under a scan root.
+pub struct Attributes {
+    prefix: PathBuf,
+    /// `.gitattributes` by directory relative to the top
+    files: HashMap<PathBuf, AttrFile>,
+    info: Option<AttrFile>,
+    macros: HashMap<String, Assignments>,
+}
+
+impl Attributes {
+    /// No attributes at all.
+    pub fn empty() -> Self {
+        Attributes {
+            prefix: PathBuf::new(),
+            files: HashMap::new(),
+            info: None,
+            macros: builtin_macros(),
+        }
+    }
+
+    /// Load the `.gitattributes` of every directory that can affect `paths`
+    /// (relative to the scan root). `read` returns the content of the
+    /// `.gitattributes` in a directory relative to the top, if there is one.
+    pub fn load<E>(
+        location: &AttributesLocation,
+        paths: &[PathBuf],
+        mut read: impl FnMut(&Path) -> Result<Option<String>, E>,
+    ) -> Result<Self, E> {
+        let mut dirs = BTreeSet::new();
+        for path in paths {
+            let mut dir = location.prefix.join(path);
+            while dir.pop() && dirs.insert(dir.clone()) {}
+        }
+
+        let mut attributes = Attributes {
+            prefix: location.prefix.clone(),
+            ..Attributes::empty()
+        };
+        for dir in dirs {
+            if let Some(content) = read(&dir)? {
+                let top_level = dir.as_os_str().is_empty();
+                let file = attributes.parse(&content, top_level);
+                attributes.files.insert(dir, file);
+            }
+        }
+
+        if let Some(content) = location
+            .info
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+        {
+            attributes.info = Some(attributes.parse(&content, true));
+        }
+
+        Ok(attributes)
+    }
+
+    /// The linguist attributes of `path`, relative to the scan root.
+    pub fn linguist(&self, path: &Path) -> Linguist {
+        let path = self.prefix.join(path);
+        let mut states = HashMap::new();
+
+        let mut dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+        dirs.reverse();
+        for dir in dirs {
+            if let (Some(file), Ok(relative)) = (self.files.get(dir), path.strip_prefix(dir)) {
+                self.apply(file, relative, &mut states);
+            }
+        }
+        if let Some(info) = &self.info {
+            self.apply(info, &path, &mut states);
+        }
+
+        let truthy = |name: &str| match states.get(name) {
+            Some(State::Set) => true,
+            Some(State::Value(value)) => value == "true",
+            _ => false,
+        };
+        Linguist {
+            vendored: truthy("linguist-vendored"),
+            generated: truthy("linguist-generated"),
+            documentation: truthy("linguist-documentation"),
+        }
+    }
+
+    fn apply(&self, file: &AttrFile, path: &Path, states: &mut HashMap<String, State>) {
+        let mut matches = file.globs.matches(path);
+        matches.sort_unstable();
+        for index in matches {
+            for (name, state) in &file.lines[index] {
+                self.assign(name, state, states, 0);
+            }
+        }
+    }
+
+    fn assign(&self, name: &str, state: &State, states: &mut HashMap<String, State>, depth: usize) {
+        states.insert(name.to_string(), state.clone());
+        // Guard against macros that expand to themselves.
+        if *state != State::Set || depth > 16 {
+            return;
+        }
+        if let Some(expansion) = self.macros.get(name) {
+            for (name, state) in expansion {
+                self.assign(name, state, states, depth + 1);
+            }
+        }
+    }
+
+    /// Parse a file's patterns, recording any macro definitions it may hold.
+    fn parse(&mut self, content: &str, allow_macros: bool) -> AttrFile {
+        let mut globs = GlobSetBuilder::new();
+        let mut lines = Vec::new();
+
+        for line in content.lines() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((pattern, rest)) = split_pattern(line) else {
+                continue;
+            };
+            let assignments = parse_assignments(rest);
+
+            if let Some(name) = pattern.strip_prefix("[attr]") {
+                if allow_macros {
+                    self.macros.insert(name.to_string(), assignments);
+                }
+                continue;
+            }
+
+            let Some(glob) = pattern_glob(&pattern) else {
+                continue;
+            };
+            let Ok(glob) = GlobBuilder::new(&glob).literal_separator(true).build() else {
+                continue;
+            };
+            globs.add(glob);
+            lines.push(assignments);
+        }
+
+        AttrFile {
+            globs: globs.build().unwrap_or_else(|_| GlobSet::empty()),
+            lines,
+        }
+    }
+}
+
+/// Attributes of the scanned repository and of the submodules under it. A
+/// path takes its attributes from the innermost repository holding it.
+#[derive(Default)]
+pub struct RepoAttributes {
+    /// By repository path relative to the scan root
+    repos: Vec<(PathBuf, Attributes)>,
+}
+
+impl RepoAttributes {
+    /// Add the attributes of the repository at `path`, relative to the scan root.
+    pub fn insert(&mut self, path: PathBuf, attributes: Attributes) {
+        self.repos.push((path, attributes));
+    }
+
+    /// The linguist attributes of `path`, relative to the scan root.
+    pub fn linguist(&self, path: &Path) -> Linguist {
+        self.repos
+            .iter()
+            .filter(|(repo, _)| path.starts_with(repo))
+            .max_by_key(|(repo, _)| repo.components().count())
+            .and_then(|(repo, attributes)| Some(attributes.linguist(path.strip_prefix(repo).ok()?)))
+            .unwrap_or_default()
+    }
+}
+
+impl From<Attributes> for RepoAttributes {
+    fn from(attributes: Attributes) -> Self {
+        RepoAttributes {
+            repos: vec![(PathBuf::new(), attributes)],
+        }
+    }
+}
+
+fn builtin_macros() -> HashMap<String, Assignments> {
+    HashMap::from([(
+        "binary".to_string(),
+        parse_assignments("-diff -merge -text"),
+    )])
+}
+
+/// Split a line into its pattern, unquoting a C-style quoted one, and the rest.
+fn split_pattern(line: &str) -> Option<(String, &str)> {
+    let Some(quoted) = line.strip_prefix('"') else {
+        let end = line.find(char::is_whitespace).unwrap_or(line.len());
+        return Some((line[..end].to_string(), &line[end..]));
+    };
+
+    let mut pattern = String::new();
+    let mut chars = quoted.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((pattern, &quoted[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => pattern.push('\n'),
+                't' => pattern.push('\t'),
+                escaped => pattern.push(escaped),
+            },
+            c => pattern.push(c),
+        }
+    }
+    None
+}
+
+fn parse_assignments(rest: &str) -> Assignments {
+    rest.split_whitespace()
+        .map(|token| {
+            if let Some(name) = token.strip_prefix('-') {
+                (name.to_string(), State::Unset)
+            } else if let Some(name) = token.strip_prefix('!') {
+                (name.to_string(), State::Unspecified)
+            } else if let Some((name, value)) = token.split_once('=') {
+                (name.to_string(), State::Value(value.to_string()))
+            } else {
+                (token.to_string(), State::Set)
+            }
+        })
+        .collect()
+}
+
+/// Translate a gitignore-style pattern into a glob over paths relative to the
+/// directory of its attributes file.
+fn pattern_glob(pattern: &str) -> Option<String> {
+    // Negation is forbidden, and a directory pattern never matches a file.
+    if pattern.is_empty() || pattern.starts_with('!') || pattern.ends_with('/') {
+        return None;
+    }
+    Some(match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    fn attributes(files: &[(&str, &str)], paths: &[&str]) -> Attributes {
+        let location = AttributesLocation {
+            top: PathBuf::new(),
+            prefix: PathBuf::new(),
+            info: None,
+        };
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        Attributes::load(&location, &paths, |dir| {
+            Ok::<_, Infallible>(
+                files
+                    .iter()
+                    .find(|(d, _)| Path::new(d) == dir)
+                    .map(|(_, content)| content.to_string()),
+            )
+        })
+        .unwrap()
+    }
+
+    fn linguist(attributes: &Attributes, path: &str) -> (bool, bool, bool) {
+        let l = attributes.linguist(Path::new(path));
+        (l.vendored, l.generated, l.documentation)
+    }
+
+    #[test]
+    fn basename_patterns_match_at_any_depth() {
+        let paths = ["a.gen.rs", "src/deep/b.gen.rs", "src/main.rs"];
+        let attrs = attributes(&[("", "*.gen.rs linguist-generated\n")], &paths);
+        assert_eq!(linguist(&attrs, "a.gen.rs"), (false, true, false));
+        assert_eq!(linguist(&attrs, "src/deep/b.gen.rs"), (false, true, false));
+        assert_eq!(linguist(&attrs, "src/main.rs"), (false, false, false));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_are_anchored() {
+        let paths = ["docs/a.md", "src/docs/b.md", "x/vendor/c.rs", "vendor/d.rs"];
+        let attrs = attributes(
+            &[(
+                "",
+                "docs/** linguist-documentation\n/vendor/** linguist-vendored\nvendor linguist-vendored\n",
+            )],
+            &paths,
+        );
+        assert_eq!(linguist(&attrs, "docs/a.md"), (false, false, true));
+        assert_eq!(linguist(&attrs, "src/docs/b.md"), (false, false, false));
+        assert_eq!(linguist(&attrs, "vendor/d.rs"), (true, false, false));
+        // A pattern naming a directory does not reach into it.
+        assert_eq!(linguist(&attrs, "x/vendor/c.rs"), (false, false, false));
+    }
+
+    #[test]
+    fn later_lines_and_deeper_files_take_precedence() {
+        let paths = ["vendor/ours/a.rs", "vendor/theirs/b.rs", "gen/keep.rs"];
+        let attrs = attributes(
+            &[
+                (
+                    "",
+                    "vendor/** linguist-vendored\ngen/* linguist-generated\ngen/keep.rs -linguist-generated\n",
+                ),
+                ("vendor/ours", "* linguist-vendored=false\n"),
+            ],
+            &paths,
+        );
+        assert_eq!(linguist(&attrs, "vendor/ours/a.rs"), (false, false, false));
+        assert_eq!(linguist(&attrs, "vendor/theirs/b.rs"), (true, false, false));
+        assert_eq!(linguist(&attrs, "gen/keep.rs"), (false, false, false));
+    }
+
+    #[test]
+    fn nested_files_match_relative_to_their_directory() {
+        let paths = ["pkg/gen/a.rs", "gen/b.rs"];
+        let attrs = attributes(&[("pkg", "gen/** linguist-generated\n")], &paths);
+        assert_eq!(linguist(&attrs, "pkg/gen/a.rs"), (false, true, false));
+        assert_eq!(linguist(&attrs, "gen/b.rs"), (false, false, false));
+    }
+
+    #[test]
+    fn expands_top_level_macros() {
+        let paths = ["third_party/a.c", "pkg/b.c"];
+        let attrs = attributes(
+            &[
+                (
+                    "",
+                    "[attr]external linguist-vendored linguist-generated\nthird_party/** external\n",
+                ),
+                // Macros outside the top level are ignored, as git does.
+                ("pkg", "[attr]doc linguist-documentation\n*.c doc\n"),
+            ],
+            &paths,
+        );
+        assert_eq!(linguist(&attrs, "third_party/a.c"), (true, true, false));
+        assert_eq!(linguist(&attrs, "pkg/b.c"), (false, false, false));
+    }
+
+    #[test]
+    fn unspecified_resets_and_quoted_patterns() {
+        let paths = ["a b.rs", "c.rs"];
+        let attrs = attributes(
+            &[(
+                "",
+                "*.rs linguist-generated\n\"a b.rs\" !linguist-generated linguist-documentation\n!c.rs -linguist-generated\n",
+            )],
+            &paths,
+        );
+        assert_eq!(linguist(&attrs, "a b.rs"), (false, false, true));
+        // Negated patterns are invalid and skipped.
+        assert_eq!(linguist(&attrs, "c.rs"), (false, true, false));
+    }
+
+    #[test]
+    fn scan_root_below_the_top_sees_ancestor_files() {
+        let location = AttributesLocation {
+            top: PathBuf::new(),
+            prefix: PathBuf::from("crates/core"),
+            info: None,
+        };
+        let attrs = Attributes::load(&location, &[PathBuf::from("src/gen.rs")], |dir| {
+            Ok::<_, Infallible>(
+                (dir == Path::new(""))
+                    .then(|| "crates/core/src/gen.rs linguist-generated\n".into()),
+            )
+        })
+        .unwrap();
+        assert!(attrs.linguist(Path::new("src/gen.rs")).generated);
+    }
+}
@@ -0,0 +1,120 @@
+//! `.tracyignore` files of a git tree, applied as the walker applies them
+//! on disk.
+
+use super::AttributesLocation;
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// `.tracyignore` files for a set of paths under a scan root, for paths that
+/// are listed rather than walked.
+#[derive(Default)]
+pub struct IgnoreFiles {
+    prefix: PathBuf,
+    /// By directory relative to the top
+    files: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreFiles {
+    /// Load the `.tracyignore` of every directory that can affect `paths`
+    /// (relative to the scan root). `read` returns the content of the
+    /// `.tracyignore` in a directory relative to the top, if there is one.
+    pub fn load<E>(
+        location: &AttributesLocation,
+        paths: &[PathBuf],
+        mut read: impl FnMut(&Path) -> Result<Option<String>, E>,
+    ) -> Result<Self, E> {
+        let mut dirs = BTreeSet::new();
+        for path in paths {
+            let mut dir = location.prefix.join(path);
+            while dir.pop() && dirs.insert(dir.clone()) {}
+        }
+
+        let mut files = HashMap::new();
+        for dir in dirs {
+            if let Some(content) = read(&dir)? {
+                // Paths are matched relative to the directory already.
+                let mut builder = GitignoreBuilder::new(".");
+                for line in content.lines() {
+                    // Invalid patterns are skipped, as the walker skips them.
+                    let _ = builder.add_line(None, line);
+                }
+                if let Ok(file) = builder.build() {
+                    files.insert(dir, file);
+                }
+            }
+        }
+
+        Ok(IgnoreFiles {
+            prefix: location.prefix.clone(),
+            files,
+        })
+    }
+
+    /// Whether `path`, relative to the scan root, is ignored: it or one of
+    /// its directories is, as the walker does not descend into an ignored
+    /// directory.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let mut within = PathBuf::new();
+        let mut components = path.components().peekable();
+        while let Some(component) = components.next() {
+            within.push(component);
+            if self.matched(&within, components.peek().is_some()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether the deepest file with a pattern matching `path` ignores it, so
+    /// a deeper `!pattern` re-includes what a shallower one ignores.
+    fn matched(&self, path: &Path, is_dir: bool) -> bool {
+        let path = self.prefix.join(path);
+        for dir in path.ancestors().skip(1) {
+            let (Some(file), Ok(relative)) = (self.files.get(dir), path.strip_prefix(dir)) else {
+                continue;
+            };
+            match file.matched(relative, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[test]
+    fn deeper_files_win() {
+        let location = AttributesLocation::discover(Path::new("/nonexistent"));
+        let paths: Vec<PathBuf> = [
+            "a.rs",
+            "b.gen.rs",
+            "src/keep.gen.rs",
+            "legacy/c.rs",
+            "legacy/keep.rs",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let ignores = IgnoreFiles::load(&location, &paths, |dir| {
+            Ok::<_, Infallible>(match dir.to_str() {
+                Some("") => Some("*.gen.rs\nlegacy/\n".to_string()),
+                Some("src") => Some("!keep.gen.rs\n".to_string()),
+                // Too late: `legacy/` is not descended into.
+                Some("legacy") => Some("!keep.rs\n".to_string()),
+                _ => None,
+            })
+        })
+        .unwrap();
+
+        let ignored: Vec<&PathBuf> = paths.iter().filter(|p| ignores.is_ignored(p)).collect();
+        assert_eq!(ignored, [&paths[1], &paths[3], &paths[4]]);
+    }
+}
@@ -1,51 +1,144 @@
 pub mod args;
+mod attributes;
 mod error;
+mod ignores;
 
 pub use args::FilterArgs;
+pub use attributes::{Attributes, AttributesLocation, Linguist, RepoAttributes};
 pub use error::FilterError;
+pub use ignores::IgnoreFiles;
 
+use crate::git::nested_repos;
 use ignore::WalkBuilder;
+use std::convert::Infallible;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-struct Excludes {
-    vendored: Vec<glob::Pattern>,
-    generated: Vec<glob::Pattern>,
-}
-
 struct GlobFilters {
     include: Vec<glob::Pattern>,
     exclude: Vec<glob::Pattern>,
 }
 
+/// Per-directory ignore files in gitignore syntax, honored alongside `.gitignore`.
+pub const IGNORE_FILE: &str = ".tracyignore";
+
+/// Walk `root` for files that pass the filters.
+///
+/// `.tracyignore` files apply at every level, even with `include_gitignored`.
+/// Nested repositories are skipped unless `include_submodules` is set, in
+/// which case each one's files take their attributes from that repository.
 pub fn collect_files(root: &Path, args: &FilterArgs) -> Result<Vec<PathBuf>, FilterError> {
-    let excludes = parse_gitattributes(root);
+    collect_files_in(Path::new(""), root, args)
+}
+
+/// Walk `root` like [`collect_files`], matching globs against paths joined
+/// onto `within`, the directory of `root` relative to the scan root.
+pub fn collect_files_in(
+    within: &Path,
+    root: &Path,
+    args: &FilterArgs,
+) -> Result<Vec<PathBuf>, FilterError> {
     let filters = parse_globs(args)?;
     let mut files = Vec::new();
 
-    for entry in WalkBuilder::new(root)
-        .git_ignore(true)
-        .git_exclude(true)
-        .git_global(true)
-        .require_git(!args.include_submodules)
-        .build()
-    {
+    for entry in walker(root, args).build() {
         let entry = entry?;
 
         if !entry.file_type().is_some_and(|ft| ft.is_file()) {
             continue;
         }
 
-        let path = entry.path();
+        files.push(entry.path().to_path_buf());
+    }
 
-        if is_excluded(path, root, &excludes, &filters, args) {
-            continue;
+    let relative: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|path| path.strip_prefix(root).ok())
+        .map(Path::to_path_buf)
+        .collect();
+    let mut attributes = RepoAttributes::from(disk_attributes(root, &relative));
+    if args.include_submodules {
+        for repo in nested_repos(root, &relative) {
+            let paths: Vec<PathBuf> = relative
+                .iter()
+                .filter_map(|path| path.strip_prefix(&repo).ok())
+                .map(Path::to_path_buf)
+                .collect();
+            let repo_attributes = disk_attributes(&root.join(&repo), &paths);
+            attributes.insert(repo, repo_attributes);
         }
+    }
 
-        files.push(path.to_path_buf());
+    Ok(files
+        .into_iter()
+        .zip(relative)
+        .filter(|(_, relative)| !is_excluded(relative, within, &attributes, &filters, args))
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// A walker over `root` honoring ignore files and the walker options of `args`.
+pub fn walker(root: &Path, args: &FilterArgs) -> WalkBuilder {
+    let include_submodules = args.include_submodules;
+    let mut walker = WalkBuilder::new(root);
+    walker
+        .git_ignore(!args.include_gitignored)
+        .git_exclude(!args.include_gitignored)
+        .git_global(!args.include_gitignored)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .hidden(!args.include_hidden)
+        .follow_links(args.follow_symlinks)
+        .max_depth(args.max_depth)
+        .same_file_system(args.one_file_system)
+        .filter_entry(move |entry| {
+            include_submodules
+                || entry.depth() == 0
+                || !entry.file_type().is_some_and(|ft| ft.is_dir())
+                || !entry.path().join(".git").exists()
+        });
+    walker
+}
+
+/// Attributes for `paths` (relative to `root`) from the `.gitattributes` files on disk.
+fn disk_attributes(root: &Path, paths: &[PathBuf]) -> Attributes {
+    let location = AttributesLocation::discover(root);
+    let read = |dir: &Path| {
+        let path = location.top.join(dir).join(".gitattributes");
+        Ok::<_, Infallible>(fs::read_to_string(path).ok())
+    };
+    match Attributes::load(&location, paths, read) {
+        Ok(attributes) => attributes,
     }
+}
 
-    Ok(files)
+/// Filter paths listed from a git tree rather than walked from disk.
+///
+/// `paths` and globs are relative to the scan root. `attributes` should be
+/// loaded from the same tree, and from those of any submodules it includes,
+/// and likewise `ignores`. Hidden paths are skipped as the walker skips them.
+/// Git ignore files are not consulted since a tree only contains tracked
+/// files.
+pub fn filter_tree_paths(
+    paths: Vec<PathBuf>,
+    attributes: &RepoAttributes,
+    ignores: &IgnoreFiles,
+    args: &FilterArgs,
+) -> Result<Vec<PathBuf>, FilterError> {
+    let filters = parse_globs(args)?;
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| args.include_hidden || !is_hidden(path))
+        .filter(|path| !ignores.is_ignored(path))
+        .filter(|path| !is_excluded(path, Path::new(""), attributes, &filters, args))
+        .collect())
+}
+
+/// Whether a file or directory of `path` is dot-prefixed, as
+/// `WalkBuilder::hidden` treats it.
+pub fn is_hidden(path: &Path) -> bool {
+    path.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
 }
 
 fn parse_globs(args: &FilterArgs) -> Result<GlobFilters, FilterError> {
@@ -74,63 +167,16 @@ fn parse_globs(args: &FilterArgs) -> Result<GlobFilters, FilterError> {
     Ok(GlobFilters { include, exclude })
 }
 
-fn parse_gitattributes(root: &Path) -> Excludes {
-    let attr_path = root.join(".gitattributes");
-    let Ok(content) = fs::read_to_string(&attr_path) else {
-        return Excludes {
-            vendored: Vec::new(),
-            generated: Vec::new(),
-        };
-    };
-
-    let mut vendored = Vec::new();
-    let mut generated = Vec::new();
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        let Some(pattern_str) = line.split_whitespace().next() else {
-            continue;
-        };
-
-        let Ok(pattern) = glob::Pattern::new(pattern_str) else {
-            continue;
-        };
-
-        let is_vendored = line.contains("linguist-vendored");
-        let is_generated = line.contains("linguist-generated");
-
-        match (is_vendored, is_generated) {
-            (true, true) => {
-                vendored.push(pattern.clone());
-                generated.push(pattern);
-            }
-            (true, false) => vendored.push(pattern),
-            (false, true) => generated.push(pattern),
-            (false, false) => {}
-        }
-    }
-
-    Excludes {
-        vendored,
-        generated,
-    }
-}
-
+/// Whether `path`, relative to the root `attributes` were loaded for, is
+/// filtered out. Globs match it joined onto `within`.
 fn is_excluded(
     path: &Path,
-    root: &Path,
-    excludes: &Excludes,
+    within: &Path,
+    attributes: &RepoAttributes,
     filters: &GlobFilters,
     args: &FilterArgs,
 ) -> bool {
-    let Ok(relative) = path.strip_prefix(root) else {
-        return false;
-    };
-    let relative_str = relative.to_string_lossy();
+    let relative_str = within.join(path).to_string_lossy().into_owned();
 
     if !filters.include.is_empty() && !filters.include.iter().any(|p| p.matches(&relative_str)) {
         return true;
@@ -140,15 +186,10 @@ fn is_excluded(
         return true;
     }
 
-    if !args.include_vendored && excludes.vendored.iter().any(|p| p.matches(&relative_str)) {
-        return true;
-    }
-
-    if !args.include_generated && excludes.generated.iter().any(|p| p.matches(&relative_str)) {
-        return true;
-    }
-
-    false
+    let linguist = attributes.linguist(path);
+    (linguist.vendored && !args.include_vendored)
+        || (linguist.generated && !args.include_generated)
+        || (linguist.documentation && !args.include_documentation)
 }
 
 #[cfg(test)]
@@ -160,65 +201,253 @@ mod tests {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
     }
 
+    fn fixture_attributes(paths: &[&str]) -> RepoAttributes {
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        disk_attributes(&fixture_root(), &paths).into()
+    }
+
     #[test]
-    fn parse_gitattributes_finds_vendored_patterns() {
-        let excludes = parse_gitattributes(&fixture_root());
-        assert_eq!(excludes.vendored.len(), 2);
-        assert!(excludes.vendored[0].matches("vendor/foo/bar.rs"));
-        assert!(excludes.vendored[1].matches("third_party/lib.c"));
+    fn fixture_gitattributes_marks_vendored_paths() {
+        let attributes = fixture_attributes(&["vendor/foo/bar.rs", "third_party/lib.c"]);
+        assert!(attributes.linguist(Path::new("vendor/foo/bar.rs")).vendored);
+        assert!(attributes.linguist(Path::new("third_party/lib.c")).vendored);
+        assert!(
+            !attributes
+                .linguist(Path::new("third_party/nested/lib.c"))
+                .vendored
+        );
     }
 
     #[test]
-    fn parse_gitattributes_finds_generated_patterns() {
-        let excludes = parse_gitattributes(&fixture_root());
-        assert_eq!(excludes.generated.len(), 2);
-        assert!(excludes.generated[0].matches("foo.generated.rs"));
-        assert!(excludes.generated[1].matches("src/gen/types.rs"));
+    fn fixture_gitattributes_marks_generated_paths() {
+        let attributes = fixture_attributes(&["foo.generated.rs", "src/deep/bar.generated.rs"]);
+        assert!(attributes.linguist(Path::new("foo.generated.rs")).generated);
+        assert!(attributes.linguist(Path::new("src/gen/types.rs")).generated);
+        assert!(
+            attributes
+                .linguist(Path::new("src/deep/bar.generated.rs"))
+                .generated
+        );
     }
 
     #[test]
     fn is_excluded_respects_vendored_flag() {
-        let excludes = parse_gitattributes(&fixture_root());
+        let attributes = fixture_attributes(&["vendor/dep/lib.rs"]);
         let filters = parse_globs(&FilterArgs::default()).unwrap();
-        let root = Path::new("/repo");
-        let path = Path::new("/repo/vendor/dep/lib.rs");
+        let root = Path::new("");
+        let path = Path::new("vendor/dep/lib.rs");
 
         let args_exclude = FilterArgs::default();
-        assert!(is_excluded(path, root, &excludes, &filters, &args_exclude));
+        assert!(is_excluded(
+            path,
+            root,
+            &attributes,
+            &filters,
+            &args_exclude
+        ));
 
         let args_include = FilterArgs {
             include_vendored: true,
             ..Default::default()
         };
-        assert!(!is_excluded(path, root, &excludes, &filters, &args_include));
+        assert!(!is_excluded(
+            path,
+            root,
+            &attributes,
+            &filters,
+            &args_include
+        ));
     }
 
     #[test]
     fn is_excluded_respects_generated_flag() {
-        let excludes = parse_gitattributes(&fixture_root());
+        let attributes = fixture_attributes(&["types.generated.rs"]);
         let filters = parse_globs(&FilterArgs::default()).unwrap();
-        let root = Path::new("/repo");
-        let path = Path::new("/repo/types.generated.rs");
+        let root = Path::new("");
+        let path = Path::new("types.generated.rs");
 
         let args_exclude = FilterArgs::default();
-        assert!(is_excluded(path, root, &excludes, &filters, &args_exclude));
+        assert!(is_excluded(
+            path,
+            root,
+            &attributes,
+            &filters,
+            &args_exclude
+        ));
 
         let args_include = FilterArgs {
             include_generated: true,
             ..Default::default()
         };
-        assert!(!is_excluded(path, root, &excludes, &filters, &args_include));
+        assert!(!is_excluded(
+            path,
+            root,
+            &attributes,
+            &filters,
+            &args_include
+        ));
     }
 
     #[test]
-    fn include_globs_filter_paths() {
-        let excludes = Excludes {
-            vendored: Vec::new(),
-            generated: Vec::new(),
+    fn collect_files_reads_nested_gitattributes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("book/src")).unwrap();
+        fs::write(dir.path().join("lib.rs"), "").unwrap();
+        fs::write(dir.path().join("book/src/intro.rs"), "").unwrap();
+        fs::write(dir.path().join("book/build.rs"), "").unwrap();
+        fs::write(
+            dir.path().join("book/.gitattributes"),
+            "src/** linguist-documentation
+",
+        )
+        .unwrap();
+
+        let files = collect_files(dir.path(), &FilterArgs::default()).unwrap();
+        let mut names: Vec<_> = files
+            .iter()
+            .map(|f| f.strip_prefix(dir.path()).unwrap().to_path_buf())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![PathBuf::from("book/build.rs"), PathBuf::from("lib.rs")]
+        );
+
+        let args = FilterArgs {
+            include_documentation: true,
+            ..Default::default()
+        };
+        assert_eq!(collect_files(dir.path(), &args).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn collect_files_skips_nested_repositories_by_default() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("deps/lib/gen")).unwrap();
+        fs::write(dir.path().join("deps/lib/.git"), "gitdir: elsewhere\n").unwrap();
+        fs::write(dir.path().join("deps/lib/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("deps/lib/gen/types.rs"), "").unwrap();
+        fs::write(
+            dir.path().join("deps/lib/.gitattributes"),
+            "gen/** linguist-generated\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("main.rs"), "").unwrap();
+
+        let names = |args: &FilterArgs| {
+            let mut names: Vec<_> = collect_files(dir.path(), args)
+                .unwrap()
+                .iter()
+                .map(|f| f.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .filter(|f| f.extension().is_some_and(|e| e == "rs"))
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(
+            names(&FilterArgs::default()),
+            vec![PathBuf::from("main.rs")]
+        );
+        let args = FilterArgs {
+            include_submodules: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&args),
+            vec![PathBuf::from("deps/lib/lib.rs"), PathBuf::from("main.rs")]
+        );
+    }
+
+    #[test]
+    fn collect_files_applies_tracyignore_and_walker_options() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        write(".gitignore", "ignored.rs\n");
+        write(".tracyignore", "*.snap.rs\n");
+        write("nested/.tracyignore", "fixtures/\n");
+        write("main.rs", "");
+        write("ignored.rs", "");
+        write("a.snap.rs", "");
+        write(".hidden/lib.rs", "");
+        write("nested/fixtures/case.rs", "");
+        write("nested/deep/er/lib.rs", "");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.path().join("nested/deep"), dir.path().join("link"))
+            .unwrap();
+
+        let names = |args: &FilterArgs| {
+            let mut names: Vec<_> = collect_files(dir.path(), args)
+                .unwrap()
+                .iter()
+                .map(|f| f.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .filter(|f| f.extension().is_some_and(|e| e == "rs"))
+                .collect();
+            names.sort();
+            names
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(&FilterArgs::default()),
+            paths(&["main.rs", "nested/deep/er/lib.rs"])
+        );
+        assert_eq!(
+            names(&FilterArgs {
+                include_hidden: true,
+                include_gitignored: true,
+                max_depth: Some(2),
+                ..Default::default()
+            }),
+            paths(&[".hidden/lib.rs", "ignored.rs", "main.rs"])
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            names(&FilterArgs {
+                follow_symlinks: true,
+                ..Default::default()
+            }),
+            paths(&["link/er/lib.rs", "main.rs", "nested/deep/er/lib.rs"])
+        );
+    }
+
+    #[test]
+    fn filter_tree_paths_applies_attributes_and_globs() {
+        let paths = vec![
+            PathBuf::from("src/main.rs"),
+            PathBuf::from("src/gen/types.rs"),
+            PathBuf::from("vendor/dep.rs"),
+            PathBuf::from("tests/test.rs"),
+            PathBuf::from(".github/check.rs"),
+        ];
+        let args = FilterArgs {
+            exclude: vec!["tests/**".to_string()],
+            ..Default::default()
         };
-        let root = Path::new("/repo");
-        let path_in = Path::new("/repo/src/main.rs");
-        let path_out = Path::new("/repo/tests/test.rs");
+        let location = AttributesLocation::discover(Path::new("/nonexistent"));
+        let attributes = Attributes::load(&location, &paths, |dir| {
+            Ok::<_, Infallible>(dir.as_os_str().is_empty().then(|| {
+                "vendor/** linguist-vendored\nsrc/gen/** linguist-generated\n".to_string()
+            }))
+        })
+        .unwrap();
+
+        let kept =
+            filter_tree_paths(paths, &attributes.into(), &IgnoreFiles::default(), &args).unwrap();
+        assert_eq!(kept, vec![PathBuf::from("src/main.rs")]);
+    }
+
+    #[test]
+    fn include_globs_filter_paths() {
+        let attributes = Attributes::empty().into();
+        let root = Path::new("");
+        let path_in = Path::new("src/main.rs");
+        let path_out = Path::new("tests/test.rs");
 
         let args = FilterArgs {
             include: vec!["src/**".to_string()],
@@ -226,19 +455,16 @@ mod tests {
         };
         let filters = parse_globs(&args).unwrap();
 
-        assert!(!is_excluded(path_in, root, &excludes, &filters, &args));
-        assert!(is_excluded(path_out, root, &excludes, &filters, &args));
+        assert!(!is_excluded(path_in, root, &attributes, &filters, &args));
+        assert!(is_excluded(path_out, root, &attributes, &filters, &args));
     }
 
     #[test]
     fn exclude_globs_filter_paths() {
-        let excludes = Excludes {
-            vendored: Vec::new(),
-            generated: Vec::new(),
-        };
-        let root = Path::new("/repo");
-        let path_in = Path::new("/repo/src/main.rs");
-        let path_out = Path::new("/repo/src/gen/types.rs");
+        let attributes = Attributes::empty().into();
+        let root = Path::new("");
+        let path_in = Path::new("src/main.rs");
+        let path_out = Path::new("src/gen/types.rs");
 
         let args = FilterArgs {
             exclude: vec!["src/gen/**".to_string()],
@@ -246,7 +472,7 @@ mod tests {
         };
         let filters = parse_globs(&args).unwrap();
 
-        assert!(!is_excluded(path_in, root, &excludes, &filters, &args));
-        assert!(is_excluded(path_out, root, &excludes, &filters, &args));
+        assert!(!is_excluded(path_in, root, &attributes, &filters, &args));
+        assert!(is_excluded(path_out, root, &attributes, &filters, &args));
     }
 }
@@ -0,0 +1,35 @@
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Clone, Args)]
+pub struct BlameArgs {
+    #[arg(
+        long,
+        global = true,
+        help = "Blame through lines moved or copied within a file (git blame -M)"
+    )]
+    pub blame_moves: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Blame through lines moved or copied from other files (git blame -C)"
+    )]
+    pub blame_copies: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Skip commits listed in this file when blaming (default: .git-blame-ignore-revs if present)"
+    )]
+    pub blame_ignore_revs_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Mailmap file used to canonicalize blame authors, in addition to .mailmap"
+    )]
+    pub blame_mailmap_file: Option<PathBuf>,
+}
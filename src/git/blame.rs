@@ -0,0 +1,409 @@
+//! Per-line blame for scan results.
+//!
+//! Each file is blamed once, with one `-L` range per run of marker lines, and
+//! files are blamed in parallel.
+
+#[cfg(feature = "gix")]
+use super::gitoxide;
+use super::{BlameArgs, GitBackend, GitError, cli};
+use crate::scan::ScanResult;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Ignore-revs file picked up from the repository root when none is configured.
+const DEFAULT_IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlameInfo {
+    pub commit: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_mail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// Blames a single file for the given 1-based inclusive line ranges.
+pub(super) trait FileBlamer: Sync {
+    fn blame_file(
+        &self,
+        file: &Path,
+        ranges: &[(usize, usize)],
+    ) -> Result<BTreeMap<usize, BlameInfo>, GitError>;
+}
+
+pub(super) fn add_blame(
+    backend: GitBackend,
+    scan_root: &Path,
+    rev: Option<&str>,
+    args: &BlameArgs,
+    results: &mut ScanResult,
+) -> Result<(), GitError> {
+    // Files in a submodule are blamed in the submodule, as of the commit the
+    // superproject records for it when scanning a revision.
+    let mut blamers: BTreeMap<PathBuf, Box<dyn FileBlamer>> = BTreeMap::new();
+    blamers.insert(PathBuf::new(), blamer(backend, scan_root, rev, args)?);
+
+    let mut by_file: BTreeMap<PathBuf, (PathBuf, BTreeSet<usize>)> = BTreeMap::new();
+    for entries in results.values() {
+        for entry in entries {
+            let repo = entry
+                .submodule
+                .as_ref()
+                .map(|s| s.path.clone())
+                .unwrap_or_default();
+            if let Some(submodule) = &entry.submodule
+                && !blamers.contains_key(&repo)
+            {
+                let rev = rev.map(|_| submodule.commit.as_str());
+                blamers.insert(
+                    repo.clone(),
+                    blamer(backend, &scan_root.join(&repo), rev, args)?,
+                );
+            }
+            by_file
+                .entry(entry.file.clone())
+                .or_insert_with(|| (repo, BTreeSet::new()))
+                .1
+                .insert(entry.line);
+        }
+    }
+    let jobs: Vec<(&PathBuf, &(PathBuf, BTreeSet<usize>))> = by_file.iter().collect();
+
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(jobs.len());
+    let next = AtomicUsize::new(0);
+
+    let blame_by_file: BTreeMap<PathBuf, BTreeMap<usize, BlameInfo>> = thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut blamed = Vec::new();
+                    while let Some((file, (repo, lines))) =
+                        jobs.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        let relative = file.strip_prefix(repo).unwrap_or(file);
+                        if let Ok(map) = blamers[repo].blame_file(relative, &line_ranges(lines)) {
+                            blamed.push(((*file).clone(), map));
+                        }
+                    }
+                    blamed
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("blame worker panicked"))
+            .collect()
+    });
+
+    for entries in results.values_mut() {
+        for entry in entries {
+            if let Some(map) = blame_by_file.get(&entry.file) {
+                entry.blame = map.get(&entry.line).cloned();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn blamer(
+    backend: GitBackend,
+    repo: &Path,
+    rev: Option<&str>,
+    args: &BlameArgs,
+) -> Result<Box<dyn FileBlamer>, GitError> {
+    Ok(match backend {
+        #[cfg(feature = "gix")]
+        GitBackend::Gix if gitoxide::Blamer::supports(repo, args)? => {
+            Box::new(gitoxide::Blamer::new(repo, rev, args)?)
+        }
+        // What gitoxide cannot blame is left to git itself.
+        _ => Box::new(cli::Blamer::new(repo, rev, args)?),
+    })
+}
+
+/// The ignore-revs file to use: the configured one, or `.git-blame-ignore-revs`
+/// at the repository root if present.
+pub(super) fn ignore_revs_file(
+    repo_root: &Path,
+    args: &BlameArgs,
+) -> Result<Option<PathBuf>, GitError> {
+    match &args.blame_ignore_revs_file {
+        Some(path) => {
+            let path = std::path::absolute(path)?;
+            if !path.is_file() {
+                return Err(GitError::MissingIgnoreRevsFile(path));
+            }
+            Ok(Some(path))
+        }
+        None => Ok(Some(repo_root.join(DEFAULT_IGNORE_REVS_FILE)).filter(|p| p.is_file())),
+    }
+}
+
+/// Collapse sorted line numbers into inclusive runs of consecutive lines.
+fn line_ranges(lines: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::{git_in, init_repo};
+    use crate::scan::Entry;
+    use clap::ValueEnum;
+    use std::fs;
+
+    fn commit_as(dir: &Path, name: &str, message: &str) -> String {
+        git_in(dir, &["add", "-A"]).unwrap();
+        git_in(
+            dir,
+            &[
+                "-c",
+                &format!("user.name={name}"),
+                "commit",
+                "-m",
+                message,
+                "--date",
+                "2000-01-01T00:00:00Z",
+            ],
+        )
+        .unwrap();
+        GitBackend::Cli.resolve_revision(dir, "HEAD").unwrap()
+    }
+
+    fn results(entries: &[(&str, &str, usize)]) -> ScanResult {
+        let mut results: ScanResult = BTreeMap::new();
+        for (id, file, line) in entries {
+            results.entry(id.to_string()).or_default().push(Entry {
+                file: PathBuf::from(file),
+                line: *line,
+                comment_text: String::new(),
+                above: None,
+                below: None,
+                inline: None,
+                scope: Vec::new(),
+                blame: None,
+                url: None,
+                submodule: None,
+                root: None,
+                component: None,
+            });
+        }
+        results
+    }
+
+    fn blame_of<'a>(results: &'a ScanResult, id: &str) -> &'a BlameInfo {
+        results[id][0].blame.as_ref().unwrap()
+    }
+
+    #[test]
+    fn adds_blame_per_line() {
+        for &backend in GitBackend::value_variants() {
+            let dir = init_repo();
+            let file_path = dir.path().join("file.txt");
+            fs::write(&file_path, "REQ-1 first\nREQ-2 second\n").unwrap();
+            let first_sha = commit_as(dir.path(), "Test", "first");
+
+            fs::write(&file_path, "REQ-1 first\nREQ-2 second changed\n").unwrap();
+            let second_sha = commit_as(dir.path(), "Test", "second");
+
+            let mut blamed = results(&[("REQ-1", "file.txt", 1), ("REQ-2", "file.txt", 2)]);
+            backend
+                .add_blame(dir.path(), None, &BlameArgs::default(), &mut blamed)
+                .unwrap();
+
+            let blame = blame_of(&blamed, "REQ-1");
+            assert_eq!(blame.commit, first_sha, "{backend:?}");
+            assert_eq!(blame.author.as_deref(), Some("Test"));
+            assert_eq!(blame.author_mail.as_deref(), Some("test@example.com"));
+            assert_eq!(blame.author_time, Some(946684800));
+            assert_eq!(blame.summary.as_deref(), Some("first"));
+            assert_eq!(blame_of(&blamed, "REQ-2").commit, second_sha);
+
+            // Uncommitted lines are attributed the way `git blame` does.
+            fs::write(&file_path, "REQ-0 new\nREQ-1 first\nREQ-2 second changed\n").unwrap();
+            let mut blamed = results(&[("REQ-0", "file.txt", 1), ("REQ-1", "file.txt", 2)]);
+            backend
+                .add_blame(dir.path(), None, &BlameArgs::default(), &mut blamed)
+                .unwrap();
+            let blame = blame_of(&blamed, "REQ-0");
+            assert_eq!(blame.commit, "0".repeat(40), "{backend:?}");
+            assert_eq!(blame.author.as_deref(), Some("Not Committed Yet"));
+            assert_eq!(blame.author_mail.as_deref(), Some("not.committed.yet"));
+            assert_eq!(blame_of(&blamed, "REQ-1").commit, first_sha);
+
+            let mut blamed = results(&[("REQ-2", "file.txt", 2)]);
+            backend
+                .add_blame(
+                    dir.path(),
+                    Some("HEAD~1"),
+                    &BlameArgs::default(),
+                    &mut blamed,
+                )
+                .unwrap();
+            assert_eq!(blame_of(&blamed, "REQ-2").commit, first_sha, "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn blames_many_ranges_across_files() {
+        for &backend in GitBackend::value_variants() {
+            let dir = init_repo();
+            let body: String = (1..=10).map(|i| format!("line {i}\n")).collect();
+            fs::create_dir_all(dir.path().join("sub")).unwrap();
+            fs::write(dir.path().join("sub/a.txt"), &body).unwrap();
+            fs::write(dir.path().join("sub/b.txt"), &body).unwrap();
+            let sha = commit_as(dir.path(), "Test", "init");
+
+            let mut blamed = results(&[
+                ("REQ-1", "a.txt", 2),
+                ("REQ-2", "a.txt", 3),
+                ("REQ-3", "a.txt", 9),
+                ("REQ-4", "b.txt", 5),
+                ("REQ-5", "untracked.txt", 1),
+            ]);
+            fs::write(dir.path().join("sub/untracked.txt"), "x\n").unwrap();
+            backend
+                .add_blame(
+                    &dir.path().join("sub"),
+                    None,
+                    &BlameArgs::default(),
+                    &mut blamed,
+                )
+                .unwrap();
+
+            for id in ["REQ-1", "REQ-2", "REQ-3", "REQ-4"] {
+                assert_eq!(blame_of(&blamed, id).commit, sha, "{backend:?} {id}");
+            }
+            assert!(blamed["REQ-5"][0].blame.is_none());
+        }
+    }
+
+    #[test]
+    fn ignore_revs_file_skips_reformat_commits() {
+        for &backend in GitBackend::value_variants() {
+            let dir = init_repo();
+            let file_path = dir.path().join("file.rs");
+            fs::write(&file_path, "fn a() {}\n// REQ-1 keep\n").unwrap();
+            let original = commit_as(dir.path(), "Author", "add marker");
+
+            fs::write(&file_path, "fn a() {}\n//  REQ-1 keep\n").unwrap();
+            let reformat = commit_as(dir.path(), "Formatter", "reformat");
+
+            let mut plain = results(&[("REQ-1", "file.rs", 2)]);
+            backend
+                .add_blame(dir.path(), None, &BlameArgs::default(), &mut plain)
+                .unwrap();
+            assert_eq!(blame_of(&plain, "REQ-1").commit, reformat, "{backend:?}");
+
+            fs::write(
+                dir.path().join(DEFAULT_IGNORE_REVS_FILE),
+                format!("# formatting\n{reformat}\n"),
+            )
+            .unwrap();
+            let mut ignored = results(&[("REQ-1", "file.rs", 2)]);
+            backend
+                .add_blame(dir.path(), None, &BlameArgs::default(), &mut ignored)
+                .unwrap();
+            let blame = blame_of(&ignored, "REQ-1");
+            assert_eq!(blame.commit, original, "{backend:?}");
+            assert_eq!(blame.author.as_deref(), Some("Author"));
+
+            let missing = BlameArgs {
+                blame_ignore_revs_file: Some(dir.path().join("missing")),
+                ..BlameArgs::default()
+            };
+            assert!(matches!(
+                backend.add_blame(dir.path(), None, &missing, &mut ignored),
+                Err(GitError::MissingIgnoreRevsFile(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn blame_moves_and_copies_match_across_backends() {
+        let dir = init_repo();
+        fs::write(
+            dir.path().join("a.rs"),
+            "// REQ-1 a marker long enough for git to detect its move\nfn a() {}\n",
+        )
+        .unwrap();
+        let original = commit_as(dir.path(), "Author", "add marker");
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(
+            dir.path().join("b.rs"),
+            "// REQ-1 a marker long enough for git to detect its move\nfn b() {}\n",
+        )
+        .unwrap();
+        commit_as(dir.path(), "Mover", "move marker");
+
+        let args = BlameArgs {
+            blame_moves: true,
+            blame_copies: true,
+            ..BlameArgs::default()
+        };
+        for &backend in GitBackend::value_variants() {
+            let mut blamed = results(&[("REQ-1", "b.rs", 1)]);
+            backend
+                .add_blame(dir.path(), None, &args, &mut blamed)
+                .unwrap();
+            assert_eq!(blame_of(&blamed, "REQ-1").commit, original, "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn mailmap_canonicalizes_authors() {
+        for &backend in GitBackend::value_variants() {
+            let dir = init_repo();
+            fs::write(dir.path().join("file.rs"), "// REQ-1\n").unwrap();
+            commit_as(dir.path(), "Test", "init");
+
+            let mailmap = dir.path().join("people.mailmap");
+            fs::write(
+                &mailmap,
+                "Canonical Name <canonical@example.com> <test@example.com>\n",
+            )
+            .unwrap();
+            let args = BlameArgs {
+                blame_mailmap_file: Some(mailmap),
+                ..BlameArgs::default()
+            };
+
+            let mut blamed = results(&[("REQ-1", "file.rs", 1)]);
+            backend
+                .add_blame(dir.path(), None, &args, &mut blamed)
+                .unwrap();
+            let blame = blame_of(&blamed, "REQ-1");
+            assert_eq!(
+                blame.author.as_deref(),
+                Some("Canonical Name"),
+                "{backend:?}"
+            );
+            assert_eq!(blame.author_mail.as_deref(), Some("canonical@example.com"));
+        }
+    }
+
+    #[test]
+    fn collapses_consecutive_lines_into_ranges() {
+        let lines: BTreeSet<usize> = [1, 2, 3, 7, 9, 10].into_iter().collect();
+        assert_eq!(line_ranges(&lines), vec![(1, 3), (7, 7), (9, 10)]);
+    }
+}
@@ -0,0 +1,397 @@
+//! Git backend that shells out to the `git` binary.
+
+use super::blame::{FileBlamer, ignore_revs_file};
+use super::{BlameArgs, BlameInfo, GitError, GitMeta, GitRemote, TreeFile};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub(super) fn collect_git_meta(scan_root: &Path, rev: Option<&str>) -> Result<GitMeta, GitError> {
+    let repo_root = PathBuf::from(git(scan_root, &["rev-parse", "--show-toplevel"])?);
+
+    let (head_sha, head_ref, describe, dirty_files) = match rev {
+        None => {
+            let head_sha = git(scan_root, &["rev-parse", "HEAD"])?;
+            let head_ref = match git(scan_root, &["rev-parse", "--abbrev-ref", "HEAD"])? {
+                s if s == "HEAD" => None,
+                s if s.is_empty() => None,
+                s => Some(s),
+            };
+            let describe = git(scan_root, &["describe", "--tags", "--always", "--dirty"])?;
+            (head_sha, head_ref, describe, dirty_files(scan_root)?)
+        }
+        Some(rev) => {
+            let head_sha = resolve_revision(scan_root, rev)?;
+            // rev-parse echoes `--end-of-options` back, so only the last line is the name.
+            let output = git(
+                scan_root,
+                &["rev-parse", "--symbolic-full-name", "--end-of-options", rev],
+            )?;
+            let full_name = output.lines().last().unwrap_or_default();
+            let head_ref = full_name
+                .strip_prefix("refs/heads/")
+                .or_else(|| full_name.strip_prefix("refs/tags/"))
+                .map(str::to_string);
+            let describe = git(scan_root, &["describe", "--tags", "--always", &head_sha])?;
+            (head_sha, head_ref, describe, Vec::new())
+        }
+    };
+
+    let tags = git(scan_root, &["tag", "--points-at", &head_sha])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+
+    let times_cmd = ["log", "-1", "--format=%at %ct", &head_sha];
+    let times = git(scan_root, &times_cmd)?;
+    let malformed = || GitError::CommandFailed {
+        cmd: format!("git {}", times_cmd.join(" ")),
+        stderr: format!("malformed output: {times}"),
+    };
+    let (author_time, commit_time) = times.split_once(' ').ok_or_else(malformed)?;
+    let author_time = author_time.parse().map_err(|_| malformed())?;
+    let commit_time = commit_time.parse().map_err(|_| malformed())?;
+
+    let mut remotes = Vec::new();
+    let names = git(scan_root, &["remote"])?;
+    let mut names: Vec<&str> = names.lines().collect();
+    names.sort_unstable();
+    for name in names {
+        let url = git(scan_root, &["remote", "get-url", name])?;
+        remotes.push(GitRemote::new(name, &url));
+    }
+
+    Ok(GitMeta {
+        repo_root,
+        head_sha,
+        head_ref,
+        is_dirty: !dirty_files.is_empty(),
+        describe,
+        tags,
+        author_time,
+        commit_time,
+        remotes,
+        dirty_files,
+        submodules: Vec::new(),
+        roots: Vec::new(),
+    })
+}
+
+/// Staged, unstaged and untracked files, sorted and relative to the repository root.
+fn dirty_files(scan_root: &Path) -> Result<Vec<PathBuf>, GitError> {
+    let output = git_raw(
+        scan_root,
+        &[
+            "status",
+            "--porcelain",
+            "-z",
+            "--untracked-files=all",
+            "--no-renames",
+        ],
+    )?;
+    let output = String::from_utf8(output)?;
+
+    let files: BTreeSet<PathBuf> = output
+        .split('\0')
+        .filter_map(|record| record.get(3..))
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    Ok(files.into_iter().collect())
+}
+
+pub(super) fn resolve_revision(scan_root: &Path, rev: &str) -> Result<String, GitError> {
+    git(
+        scan_root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--end-of-options",
+            &format!("{rev}^{{commit}}"),
+        ],
+    )
+}
+
+pub(super) fn merge_base(scan_root: &Path, a: &str, b: &str) -> Result<String, GitError> {
+    git(scan_root, &["merge-base", "--end-of-options", a, b])
+}
+
+pub(super) fn list_tree(scan_root: &Path, rev: &str) -> Result<Vec<TreeFile>, GitError> {
+    ls_tree(scan_root, rev, |mode, kind| {
+        kind == "blob" && mode != "120000"
+    })
+}
+
+pub(super) fn list_gitlinks(scan_root: &Path, rev: &str) -> Result<Vec<TreeFile>, GitError> {
+    ls_tree(scan_root, rev, |_, kind| kind == "commit")
+}
+
+fn ls_tree(
+    scan_root: &Path,
+    rev: &str,
+    keep: impl Fn(&str, &str) -> bool,
+) -> Result<Vec<TreeFile>, GitError> {
+    let output = git_raw(scan_root, &["ls-tree", "-r", "-z", "--end-of-options", rev])?;
+    let output = String::from_utf8(output)?;
+
+    let mut files = Vec::new();
+    for record in output.split('\0') {
+        let Some((info, path)) = record.split_once('\t') else {
+            continue;
+        };
+        let mut parts = info.split_whitespace();
+        let (Some(mode), Some(kind), Some(oid)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        if !keep(mode, kind) {
+            continue;
+        }
+        files.push(TreeFile {
+            path: PathBuf::from(path),
+            oid: oid.to_string(),
+        });
+    }
+
+    Ok(files)
+}
+
+pub(super) fn read_blobs(scan_root: &Path, oids: &[&str]) -> Result<Vec<Vec<u8>>, GitError> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(scan_root)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from another thread so a full stdout pipe cannot deadlock us.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input: String = oids.iter().map(|oid| format!("{oid}\n")).collect();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    writer.join().expect("cat-file writer panicked")?;
+
+    if !output.status.success() {
+        return Err(GitError::CommandFailed {
+            cmd: "git cat-file --batch".to_string(),
+            stderr: String::from_utf8(output.stderr)?.trim().to_string(),
+        });
+    }
+
+    parse_cat_file_batch(&output.stdout, oids.len())
+}
+
+pub(super) fn show_file(
+    scan_root: &Path,
+    rev: &str,
+    path: &str,
+) -> Result<Option<String>, GitError> {
+    let spec = format!("{rev}:./{path}");
+    let exists = Command::new("git")
+        .arg("-C")
+        .arg(scan_root)
+        .args(["cat-file", "-e", &spec])
+        .output()?
+        .status
+        .success();
+    if !exists {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8(git_raw(
+        scan_root,
+        &["cat-file", "blob", &spec],
+    )?)?))
+}
+
+fn parse_cat_file_batch(mut output: &[u8], expected: usize) -> Result<Vec<Vec<u8>>, GitError> {
+    let malformed = || GitError::CommandFailed {
+        cmd: "git cat-file --batch".to_string(),
+        stderr: "malformed output".to_string(),
+    };
+
+    let mut blobs = Vec::with_capacity(expected);
+    while blobs.len() < expected {
+        let newline = output
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(malformed)?;
+        let header = std::str::from_utf8(&output[..newline]).map_err(|_| malformed())?;
+        output = &output[newline + 1..];
+
+        let size = match header.split(' ').collect::<Vec<_>>()[..] {
+            [_, _, size] => size.parse::<usize>().map_err(|_| malformed())?,
+            _ => {
+                return Err(GitError::CommandFailed {
+                    cmd: "git cat-file --batch".to_string(),
+                    stderr: header.to_string(),
+                });
+            }
+        };
+        if output.len() < size + 1 {
+            return Err(malformed());
+        }
+        blobs.push(output[..size].to_vec());
+        output = &output[size + 1..];
+    }
+
+    Ok(blobs)
+}
+
+pub(super) fn git(scan_root: &Path, args: &[&str]) -> Result<String, GitError> {
+    Ok(String::from_utf8(git_raw(scan_root, args)?)?
+        .trim()
+        .to_string())
+}
+
+fn git_raw(scan_root: &Path, args: &[&str]) -> Result<Vec<u8>, GitError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(scan_root)
+        .args(args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(GitError::CommandFailed {
+            cmd: format!("git {}", args.join(" ")),
+            stderr: String::from_utf8(output.stderr)?.trim().to_string(),
+        });
+    }
+
+    Ok(output.stdout)
+}
+
+pub(super) struct Blamer {
+    scan_root: PathBuf,
+    /// Everything but the line ranges and the path, which follow
+    options: Vec<String>,
+}
+
+impl Blamer {
+    pub(super) fn new(
+        scan_root: &Path,
+        rev: Option<&str>,
+        args: &BlameArgs,
+    ) -> Result<Self, GitError> {
+        let repo_root = PathBuf::from(git(scan_root, &["rev-parse", "--show-toplevel"])?);
+        let mut options = Vec::new();
+
+        // `git -C` changes directory, so user-supplied paths are made absolute first.
+        if let Some(mailmap) = &args.blame_mailmap_file {
+            let mailmap = std::path::absolute(mailmap)?;
+            options.push("-c".to_string());
+            options.push(format!("mailmap.file={}", mailmap.display()));
+        }
+
+        options.push("blame".to_string());
+        options.push("--line-porcelain".to_string());
+        if args.blame_moves {
+            options.push("-M".to_string());
+        }
+        if args.blame_copies {
+            options.push("-C".to_string());
+        }
+        if let Some(path) = ignore_revs_file(&repo_root, args)? {
+            options.push("--ignore-revs-file".to_string());
+            options.push(path.to_string_lossy().into_owned());
+        }
+        options.extend(rev.map(str::to_string));
+
+        Ok(Blamer {
+            scan_root: scan_root.to_path_buf(),
+            options,
+        })
+    }
+}
+
+impl FileBlamer for Blamer {
+    fn blame_file(
+        &self,
+        file: &Path,
+        ranges: &[(usize, usize)],
+    ) -> Result<BTreeMap<usize, BlameInfo>, GitError> {
+        let mut args = self.options.clone();
+        for (start, end) in ranges {
+            args.push("-L".to_string());
+            args.push(format!("{start},{end}"));
+        }
+        args.push("--".to_string());
+        args.push(file.to_string_lossy().into_owned());
+
+        let args_ref: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = git(&self.scan_root, &args_ref)?;
+
+        Ok(parse_blame_porcelain(&output))
+    }
+}
+
+fn parse_blame_porcelain(output: &str) -> BTreeMap<usize, BlameInfo> {
+    let mut result = BTreeMap::new();
+    let mut iter = output.lines();
+
+    while let Some(header) = iter.next() {
+        if header.trim().is_empty() {
+            continue;
+        }
+
+        let mut parts = header.split_whitespace();
+        let Some(commit) = parts.next() else {
+            continue;
+        };
+        let _orig_line = parts.next().and_then(|s| s.parse::<usize>().ok());
+        let final_line = match parts.next().and_then(|s| s.parse::<usize>().ok()) {
+            Some(n) => n,
+            None => continue,
+        };
+        let group_len = parts
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .unwrap_or(1);
+
+        let mut author = None;
+        let mut author_mail = None;
+        let mut author_time = None;
+        let mut summary = None;
+
+        for line in iter.by_ref() {
+            if line.starts_with('\t') {
+                break;
+            }
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            match key {
+                "author" => author = Some(value.to_string()),
+                "author-mail" => {
+                    author_mail = Some(
+                        value
+                            .trim()
+                            .trim_start_matches('<')
+                            .trim_end_matches('>')
+                            .to_string(),
+                    )
+                }
+                "author-time" => author_time = value.parse::<i64>().ok(),
+                "summary" => summary = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let info = BlameInfo {
+            commit: commit.to_string(),
+            author,
+            author_mail,
+            author_time,
+            summary,
+        };
+
+        for i in 0..group_len {
+            result.insert(final_line + i, info.clone());
+        }
+    }
+
+    result
+}
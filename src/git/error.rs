@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GitError {
+    #[error("failed to run git: {0}")]
+    Run(#[from] std::io::Error),
+
+    #[error("git command failed ({cmd}): {stderr}")]
+    CommandFailed { cmd: String, stderr: String },
+
+    #[error("git output was not valid utf-8: {0}")]
+    OutputUtf8(#[from] std::string::FromUtf8Error),
+
+    #[error("blame ignore-revs file not found: {0}")]
+    MissingIgnoreRevsFile(PathBuf),
+
+    #[error("no git remote to build permalinks from; set a permalink template")]
+    NoRemote,
+
+    #[error("cannot infer a permalink template from remote {0}; set a permalink template")]
+    UnknownForge(String),
+
+    #[error("unknown permalink template placeholder: {{{0}}}")]
+    PermalinkPlaceholder(String),
+
+    #[cfg(feature = "gix")]
+    #[error("gitoxide: {0}")]
+    Gitoxide(Box<dyn std::error::Error + Send + Sync>),
+}
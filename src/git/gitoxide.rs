@@ -0,0 +1,439 @@
+//! In-process git backend on gitoxide.
+//!
+//! Mirrors the `cli` backend's output exactly, including `git blame`'s
+//! attribution of uncommitted lines.
+
+use super::blame::{FileBlamer, ignore_revs_file};
+use super::{BlameArgs, BlameInfo, GitError, GitMeta, GitRemote, TreeFile};
+use gix::ObjectId;
+use gix::bstr::{BString, ByteSlice};
+use gix::objs::tree::EntryKind;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn err(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> GitError {
+    GitError::Gitoxide(e.into())
+}
+
+pub(super) fn collect_git_meta(scan_root: &Path, rev: Option<&str>) -> Result<GitMeta, GitError> {
+    let (repo, _) = open(scan_root)?;
+    let repo_root = workdir(&repo)?;
+
+    let (head_sha, head_ref, dirty_files) = match rev {
+        None => {
+            let head_sha = repo.head_id().map_err(err)?.detach();
+            let head_ref = repo
+                .head_name()
+                .map_err(err)?
+                .map(|name| name.shorten().to_string());
+            (head_sha, head_ref, dirty_files(&repo)?)
+        }
+        Some(rev) => {
+            let head_sha = resolve(&repo, rev)?;
+            // Like `rev-parse --symbolic-full-name`: symbolic refs such as HEAD name their target.
+            let full_name = repo
+                .try_find_reference(rev)
+                .ok()
+                .flatten()
+                .map(|reference| match reference.target().try_name() {
+                    Some(target) => target.as_bstr().to_string(),
+                    None => reference.name().as_bstr().to_string(),
+                })
+                .unwrap_or_default();
+            let head_ref = full_name
+                .strip_prefix("refs/heads/")
+                .or_else(|| full_name.strip_prefix("refs/tags/"))
+                .map(str::to_string);
+            (head_sha, head_ref, Vec::new())
+        }
+    };
+
+    let commit = repo.find_commit(head_sha).map_err(err)?;
+    let resolution = commit
+        .describe()
+        .names(gix::commit::describe::SelectRef::AllTags)
+        .id_as_fallback(true)
+        .try_resolve()
+        .map_err(err)?
+        .ok_or_else(|| err(format!("cannot describe {head_sha}")))?;
+    // `git describe --dirty` ignores untracked files, as `is_dirty()` does.
+    let dirty_suffix = rev.is_none().then(|| "dirty".to_string());
+    let describe = resolution
+        .format_with_dirty_suffix(dirty_suffix)
+        .map_err(err)?
+        .to_string();
+
+    let mut tags = Vec::new();
+    for reference in repo.references().map_err(err)?.tags().map_err(err)? {
+        let mut reference = reference.map_err(err)?;
+        if reference.peel_to_id().map_err(err)? == head_sha {
+            tags.push(reference.name().shorten().to_string());
+        }
+    }
+    tags.sort_unstable();
+
+    let author_time = commit.author().map_err(err)?.time().map_err(err)?.seconds;
+    let commit_time = commit
+        .committer()
+        .map_err(err)?
+        .time()
+        .map_err(err)?
+        .seconds;
+
+    let mut names: Vec<String> = repo
+        .remote_names()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect();
+    names.sort_unstable();
+    let mut remotes = Vec::new();
+    for name in names {
+        let remote = repo.find_remote(name.as_str()).map_err(err)?;
+        if let Some(url) = remote.url(gix::remote::Direction::Fetch) {
+            remotes.push(GitRemote::new(&name, &url.to_bstring().to_string()));
+        }
+    }
+
+    Ok(GitMeta {
+        repo_root,
+        head_sha: head_sha.to_string(),
+        head_ref,
+        is_dirty: !dirty_files.is_empty(),
+        describe,
+        tags,
+        author_time,
+        commit_time,
+        remotes,
+        dirty_files,
+        submodules: Vec::new(),
+        roots: Vec::new(),
+    })
+}
+
+pub(super) fn resolve_revision(scan_root: &Path, rev: &str) -> Result<String, GitError> {
+    let (repo, _) = open(scan_root)?;
+    Ok(resolve(&repo, rev)?.to_string())
+}
+
+pub(super) fn merge_base(scan_root: &Path, a: &str, b: &str) -> Result<String, GitError> {
+    let (repo, _) = open(scan_root)?;
+    let base = repo
+        .merge_base(resolve(&repo, a)?, resolve(&repo, b)?)
+        .map_err(err)?
+        .ok_or_else(|| err(format!("no merge base between {a} and {b}")))?;
+    Ok(base.to_string())
+}
+
+pub(super) fn list_tree(scan_root: &Path, rev: &str) -> Result<Vec<TreeFile>, GitError> {
+    ls_tree(scan_root, rev, |kind| {
+        matches!(kind, EntryKind::Blob | EntryKind::BlobExecutable)
+    })
+}
+
+pub(super) fn list_gitlinks(scan_root: &Path, rev: &str) -> Result<Vec<TreeFile>, GitError> {
+    ls_tree(scan_root, rev, |kind| kind == EntryKind::Commit)
+}
+
+fn ls_tree(
+    scan_root: &Path,
+    rev: &str,
+    keep: impl Fn(EntryKind) -> bool,
+) -> Result<Vec<TreeFile>, GitError> {
+    let (repo, prefix) = open(scan_root)?;
+    let tree = repo
+        .rev_parse_single(rev)
+        .map_err(err)?
+        .object()
+        .map_err(err)?
+        .peel_to_tree()
+        .map_err(err)?;
+
+    let tree = if prefix.as_os_str().is_empty() {
+        tree
+    } else {
+        match tree.lookup_entry_by_path(&prefix).map_err(err)? {
+            Some(entry) if entry.mode().is_tree() => {
+                entry.object().map_err(err)?.peel_to_tree().map_err(err)?
+            }
+            _ => return Ok(Vec::new()),
+        }
+    };
+
+    let mut records = tree.traverse().breadthfirst.files().map_err(err)?;
+    records.retain(|r| keep(r.mode.kind()));
+    // Full-path byte order is the order `git ls-tree -r` lists in.
+    records.sort_by(|a, b| a.filepath.cmp(&b.filepath));
+
+    records
+        .into_iter()
+        .map(|r| {
+            Ok(TreeFile {
+                path: gix::path::from_bstr(r.filepath.as_bstr())
+                    .map_err(err)?
+                    .into_owned(),
+                oid: r.oid.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub(super) fn read_blobs(scan_root: &Path, oids: &[&str]) -> Result<Vec<Vec<u8>>, GitError> {
+    let (repo, _) = open(scan_root)?;
+    oids.iter()
+        .map(|oid| {
+            let id = ObjectId::from_hex(oid.as_bytes()).map_err(err)?;
+            Ok(repo.find_object(id).map_err(err)?.detach().data)
+        })
+        .collect()
+}
+
+pub(super) fn show_file(
+    scan_root: &Path,
+    rev: &str,
+    path: &str,
+) -> Result<Option<String>, GitError> {
+    let (repo, prefix) = open(scan_root)?;
+    let tree = repo
+        .rev_parse_single(rev)
+        .map_err(err)?
+        .object()
+        .map_err(err)?
+        .peel_to_tree()
+        .map_err(err)?;
+
+    match tree.lookup_entry_by_path(prefix.join(path)).map_err(err)? {
+        Some(entry) if entry.mode().is_blob() => {
+            let data = entry.object().map_err(err)?.detach().data;
+            Ok(Some(String::from_utf8(data)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+pub(super) struct Blamer {
+    repo: gix::ThreadSafeRepository,
+    /// Scan root relative to the work tree
+    prefix: PathBuf,
+    /// The commit to blame from
+    suspect: ObjectId,
+    /// Blame the working tree file on top of `suspect`
+    worktree: bool,
+    mailmap: gix::mailmap::Snapshot,
+}
+
+impl Blamer {
+    /// Whether blame with `args` can be done in process: gitoxide does not
+    /// follow moves and copies, nor skip revisions from an ignore-revs file.
+    pub(super) fn supports(scan_root: &Path, args: &BlameArgs) -> Result<bool, GitError> {
+        let (repo, _) = open(scan_root)?;
+        Ok(!args.blame_moves
+            && !args.blame_copies
+            && ignore_revs_file(&workdir(&repo)?, args)?.is_none())
+    }
+
+    pub(super) fn new(
+        scan_root: &Path,
+        rev: Option<&str>,
+        args: &BlameArgs,
+    ) -> Result<Self, GitError> {
+        let (repo, prefix) = open(scan_root)?;
+
+        let mut mailmap = repo.open_mailmap();
+        if let Some(path) = &args.blame_mailmap_file {
+            let data = fs::read(path)?;
+            mailmap.merge(gix::mailmap::parse_ignore_errors(&data));
+        }
+
+        let suspect = match rev {
+            Some(rev) => resolve(&repo, rev)?,
+            None => repo.head_id().map_err(err)?.detach(),
+        };
+
+        Ok(Blamer {
+            repo: repo.into_sync(),
+            prefix,
+            suspect,
+            worktree: rev.is_none(),
+            mailmap,
+        })
+    }
+
+    fn commit_info(&self, repo: &gix::Repository, id: ObjectId) -> Result<BlameInfo, GitError> {
+        let commit = repo.find_commit(id).map_err(err)?;
+        let author = self.mailmap.resolve(commit.author().map_err(err)?);
+        let message = commit.message_raw().map_err(err)?;
+        let summary = message.lines().find(|line| !line.trim().is_empty());
+
+        Ok(BlameInfo {
+            commit: id.to_string(),
+            author: Some(author.name.to_string()),
+            author_mail: Some(author.email.to_string()),
+            author_time: Some(author.time.seconds),
+            summary: Some(summary.unwrap_or_default().to_str_lossy().into_owned()),
+        })
+    }
+}
+
+impl FileBlamer for Blamer {
+    fn blame_file(
+        &self,
+        file: &Path,
+        ranges: &[(usize, usize)],
+    ) -> Result<BTreeMap<usize, BlameInfo>, GitError> {
+        let repo = self.repo.to_thread_local();
+        let path = self.prefix.join(file);
+        let repo_path: BString =
+            gix::path::to_unix_separators_on_windows(gix::path::into_bstr(&path).map_err(err)?)
+                .into_owned();
+
+        // Like `git blame`, refuse paths the starting commit does not have.
+        let tree = repo
+            .find_commit(self.suspect)
+            .map_err(err)?
+            .tree()
+            .map_err(err)?;
+        if tree.lookup_entry_by_path(&path).map_err(err)?.is_none() {
+            return Err(err(format!("no such path {repo_path} in {}", self.suspect)));
+        }
+
+        let start = if self.worktree {
+            let contents = fs::read(workdir(&repo)?.join(&path))?;
+            gix::blame::Start::Contents {
+                first_suspect: self.suspect,
+                contents: Cow::Owned(contents),
+            }
+        } else {
+            gix::blame::Start::Commit(self.suspect)
+        };
+
+        let ranges = gix::blame::BlameRanges::from_one_based_inclusive_ranges(
+            ranges
+                .iter()
+                .map(|&(start, end)| start as u32..=end as u32)
+                .collect(),
+        )
+        .map_err(err)?;
+        let options = gix::blame::Options {
+            diff_algorithm: repo.diff_algorithm().map_err(err)?,
+            ranges,
+            since: None,
+            rewrites: Some(Default::default()),
+            debug_track_path: false,
+        };
+
+        let outcome = gix::blame::file(
+            &repo.objects,
+            start,
+            repo.commit_graph_if_enabled().map_err(err)?,
+            &mut repo.diff_resource_cache_for_tree_diff().map_err(err)?,
+            repo_path.as_ref(),
+            options,
+        )
+        .map_err(err)?;
+
+        let mut commits: HashMap<ObjectId, BlameInfo> = HashMap::new();
+        let mut result = BTreeMap::new();
+        for entry in outcome.entries {
+            let info = match commits.get(&entry.commit_id) {
+                Some(info) => info.clone(),
+                None => {
+                    let info = if entry.commit_id.is_null() {
+                        not_committed_yet(&repo_path)
+                    } else {
+                        self.commit_info(&repo, entry.commit_id)?
+                    };
+                    commits.insert(entry.commit_id, info.clone());
+                    info
+                }
+            };
+
+            let first = entry.start_in_blamed_file as usize + 1;
+            for line in first..first + entry.len.get() as usize {
+                result.insert(line, info.clone());
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// The pseudo-commit `git blame` attributes working tree changes to.
+fn not_committed_yet(path: &BString) -> BlameInfo {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    BlameInfo {
+        commit: ObjectId::null(gix::hash::Kind::Sha1).to_string(),
+        author: Some("Not Committed Yet".to_string()),
+        author_mail: Some("not.committed.yet".to_string()),
+        author_time: Some(now),
+        summary: Some(format!("Version of {path} from {path}")),
+    }
+}
+
+/// Open the repository containing `scan_root`, along with the scan root's path
+/// relative to its work tree.
+fn open(scan_root: &Path) -> Result<(gix::Repository, PathBuf), GitError> {
+    let repo = gix::discover(scan_root).map_err(err)?;
+    let root = fs::canonicalize(scan_root)?;
+    let prefix = root
+        .strip_prefix(workdir(&repo)?)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    Ok((repo, prefix))
+}
+
+/// The canonical work tree root, as `git rev-parse --show-toplevel` reports it.
+fn workdir(repo: &gix::Repository) -> Result<PathBuf, GitError> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| err("this operation must be run in a work tree"))?;
+    Ok(fs::canonicalize(workdir)?)
+}
+
+fn resolve(repo: &gix::Repository, rev: &str) -> Result<ObjectId, GitError> {
+    Ok(repo
+        .rev_parse_single(rev)
+        .map_err(err)?
+        .object()
+        .map_err(err)?
+        .peel_to_commit()
+        .map_err(err)?
+        .id)
+}
+
+/// Staged, unstaged and untracked files, sorted and relative to the repository
+/// root, as `git status --porcelain --untracked-files=all --no-renames` lists them.
+fn dirty_files(repo: &gix::Repository) -> Result<Vec<PathBuf>, GitError> {
+    let changes = repo
+        .status(gix::progress::Discard)
+        .map_err(err)?
+        .untracked_files(gix::status::UntrackedFiles::Files)
+        .index_worktree_rewrites(None)
+        .tree_index_track_renames(gix::status::tree_index::TrackRenames::Disabled)
+        // `:/` covers the whole work tree, not just the current directory.
+        .into_iter([BString::from(":/")])
+        .map_err(err)?;
+
+    let mut files = BTreeSet::new();
+    for change in changes {
+        let change = change.map_err(err)?;
+        // Items without a summary only refresh stale index stat data.
+        if let gix::status::Item::IndexWorktree(item) = &change
+            && item.summary().is_none()
+        {
+            continue;
+        }
+        files.insert(
+            gix::path::from_bstr(change.location())
+                .map_err(err)?
+                .into_owned(),
+        );
+    }
+    Ok(files.into_iter().collect())
+}
@@ -0,0 +1,428 @@
+//! Forge permalinks to each reference, pinned to the scanned commit.
+//!
+//! A template is either a preset name or a url with `{host}`, `{owner}`,
+//! `{repo}`, `{sha}`, `{path}` and `{line}` placeholders. Without one, the
+//! preset is inferred from the host of the `origin` remote.
+
+use super::{GitError, GitMeta, GitRemote};
+use crate::scan::ScanResult;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const PRESETS: [(&str, &str); 4] = [
+    (
+        "github",
+        "https://{host}/{owner}/{repo}/blob/{sha}/{path}#L{line}",
+    ),
+    (
+        "gitlab",
+        "https://{host}/{owner}/{repo}/-/blob/{sha}/{path}#L{line}",
+    ),
+    (
+        "gitea",
+        "https://{host}/{owner}/{repo}/src/commit/{sha}/{path}#L{line}",
+    ),
+    (
+        "bitbucket",
+        "https://{host}/{owner}/{repo}/src/{sha}/{path}#lines-{line}",
+    ),
+];
+
+/// Host fragments that identify a preset, for inference from a remote url.
+const KNOWN_HOSTS: [(&str, &str); 6] = [
+    ("github", "github"),
+    ("gitlab", "gitlab"),
+    ("bitbucket", "bitbucket"),
+    ("gitea", "gitea"),
+    ("codeberg", "gitea"),
+    ("forgejo", "gitea"),
+];
+
+/// Where a remote's repository lives on its forge.
+#[derive(Debug, PartialEq, Eq)]
+struct ForgeRepo {
+    host: String,
+    /// Everything before the repository name, e.g. a GitLab group path
+    owner: String,
+    repo: String,
+}
+
+/// Links into one repository: the scanned one or a submodule.
+struct Linker<'a> {
+    template: &'a str,
+    remote: Option<&'a GitRemote>,
+    forge: Option<ForgeRepo>,
+    sha: &'a str,
+    /// The repository's directory relative to its root
+    prefix: PathBuf,
+}
+
+/// Set `url` on every entry, linking to its line at `meta.head_sha`.
+///
+/// Entries found in a submodule link into the submodule's repository at its
+/// commit, if `meta.submodules` has its metadata.
+pub fn add_permalinks(
+    meta: &GitMeta,
+    scan_root: &Path,
+    template: Option<&str>,
+    results: &mut ScanResult,
+) -> Result<(), GitError> {
+    let root = Linker::new(meta, scan_root, template)?;
+    let submodules = meta
+        .submodules
+        .iter()
+        .map(|s| {
+            let linker = Linker::new(&s.meta, &scan_root.join(&s.path), template)?;
+            Ok((s.path.as_path(), linker))
+        })
+        .collect::<Result<Vec<_>, GitError>>()?;
+
+    for entry in results.values_mut().flatten() {
+        let submodule = entry
+            .submodule
+            .as_ref()
+            .and_then(|submodule| submodules.iter().find(|(path, _)| *path == submodule.path));
+        entry.url = Some(match submodule {
+            Some((path, linker)) => {
+                let file = entry.file.strip_prefix(path).unwrap_or(&entry.file);
+                linker.link(file, entry.line)?
+            }
+            None => root.link(&entry.file, entry.line)?,
+        });
+    }
+
+    Ok(())
+}
+
+impl<'a> Linker<'a> {
+    fn new(meta: &'a GitMeta, dir: &Path, template: Option<&'a str>) -> Result<Self, GitError> {
+        let remote = meta
+            .remotes
+            .iter()
+            .find(|r| r.name == "origin")
+            .or(meta.remotes.first());
+        let forge = remote.and_then(|r| parse_remote_url(&r.url));
+
+        let template = match template {
+            Some(template) => preset(template).unwrap_or(template),
+            None => {
+                let remote = remote.ok_or(GitError::NoRemote)?;
+                forge
+                    .as_ref()
+                    .and_then(|forge| infer_preset(&forge.host))
+                    .ok_or_else(|| GitError::UnknownForge(remote.url.clone()))?
+            }
+        };
+
+        // Entries are relative to the scan root; links need repo-relative paths.
+        let prefix = fs::canonicalize(dir)?;
+        let prefix = prefix
+            .strip_prefix(fs::canonicalize(&meta.repo_root)?)
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+
+        let linker = Linker {
+            template,
+            remote,
+            forge,
+            sha: &meta.head_sha,
+            prefix,
+        };
+        // Validate the template even when there is nothing to link.
+        linker.link(Path::new(""), 1)?;
+        Ok(linker)
+    }
+
+    /// The url of `line` in `path`, relative to the repository's directory.
+    fn link(&self, path: &Path, line: usize) -> Result<String, GitError> {
+        render(self.template, |name| {
+            let forge = || {
+                self.forge.as_ref().ok_or_else(|| match self.remote {
+                    Some(remote) => GitError::UnknownForge(remote.url.clone()),
+                    None => GitError::NoRemote,
+                })
+            };
+            Ok(match name {
+                "host" => forge()?.host.clone(),
+                "owner" => forge()?.owner.clone(),
+                "repo" => forge()?.repo.clone(),
+                "sha" => self.sha.to_string(),
+                "path" => url_path(&self.prefix.join(path)),
+                "line" => line.to_string(),
+                _ => return Err(GitError::PermalinkPlaceholder(name.to_string())),
+            })
+        })
+    }
+}
+
+fn preset(name: &str) -> Option<&'static str> {
+    PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, template)| *template)
+}
+
+fn infer_preset(host: &str) -> Option<&'static str> {
+    let host = host.to_ascii_lowercase();
+    KNOWN_HOSTS
+        .iter()
+        .find(|(fragment, _)| host.contains(fragment))
+        .and_then(|(_, name)| preset(name))
+}
+
+/// Split an `https://`, `ssh://` or scp-like `user@host:path` url into its
+/// host and repository path.
+fn parse_remote_url(url: &str) -> Option<ForgeRepo> {
+    let (host, path) = match url.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            // An ssh port says nothing about the web ui's.
+            let host = if scheme.starts_with("http") {
+                host
+            } else {
+                host.split(':').next()?
+            };
+            (host, path)
+        }
+        None => {
+            let (authority, path) = url.split_once(':')?;
+            (authority.rsplit('@').next()?, path)
+        }
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.rsplit_once('/')?;
+    if host.is_empty() || owner.is_empty() || repo.is_empty() {
+        return None;
+    }
+
+    Some(ForgeRepo {
+        host: host.to_string(),
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+    })
+}
+
+/// Substitute each `{name}` in `template`.
+fn render(
+    template: &str,
+    value: impl Fn(&str) -> Result<String, GitError>,
+) -> Result<String, GitError> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| GitError::PermalinkPlaceholder(rest[open..].to_string()))?;
+        out.push_str(&value(&rest[open + 1..open + close])?);
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A relative path as a percent-encoded url path with `/` separators.
+fn url_path(path: &Path) -> String {
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(segment) => Some(segment.to_string_lossy()),
+            _ => None,
+        })
+        .map(|segment| {
+            segment
+                .bytes()
+                .map(|b| match b {
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                        (b as char).to_string()
+                    }
+                    _ => format!("%{b:02X}"),
+                })
+                .collect()
+        })
+        .collect();
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::Entry;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn meta(root: &Path, remote: Option<&str>) -> GitMeta {
+        GitMeta {
+            repo_root: root.to_path_buf(),
+            head_sha: "a".repeat(40),
+            head_ref: Some("main".to_string()),
+            is_dirty: false,
+            describe: "aaaaaaa".to_string(),
+            tags: Vec::new(),
+            author_time: 0,
+            commit_time: 0,
+            remotes: remote
+                .map(|url| {
+                    vec![GitRemote {
+                        name: "origin".to_string(),
+                        url: url.to_string(),
+                    }]
+                })
+                .unwrap_or_default(),
+            dirty_files: Vec::new(),
+            submodules: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    fn results(file: &str, line: usize) -> ScanResult {
+        let mut results: ScanResult = BTreeMap::new();
+        results.insert(
+            "REQ-1".to_string(),
+            vec![Entry {
+                file: PathBuf::from(file),
+                line,
+                comment_text: "// REQ-1".to_string(),
+                above: None,
+                below: None,
+                inline: None,
+                scope: Vec::new(),
+                blame: None,
+                url: None,
+                submodule: None,
+                root: None,
+                component: None,
+            }],
+        );
+        results
+    }
+
+    fn url(results: &ScanResult) -> &str {
+        results["REQ-1"][0].url.as_deref().unwrap()
+    }
+
+    #[test]
+    fn parses_remote_urls() {
+        let repo = |host: &str, owner: &str, repo: &str| {
+            Some(ForgeRepo {
+                host: host.to_string(),
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+            })
+        };
+        assert_eq!(
+            parse_remote_url("https://***@github.com/org/tool.git"),
+            repo("github.com", "org", "tool")
+        );
+        assert_eq!(
+            parse_remote_url("git@gitlab.com:group/sub/tool.git"),
+            repo("gitlab.com", "group/sub", "tool")
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@code.example.com:2222/org/tool"),
+            repo("code.example.com", "org", "tool")
+        );
+        assert_eq!(
+            parse_remote_url("http://localhost:3000/org/tool/"),
+            repo("localhost:3000", "org", "tool")
+        );
+        assert_eq!(parse_remote_url("/srv/git/tool.git"), None);
+    }
+
+    #[test]
+    fn infers_preset_from_remote() {
+        let dir = TempDir::new().unwrap();
+        let cases = [
+            (
+                "git@github.com:org/tool.git",
+                "https://github.com/org/tool/blob/{sha}/src/a%20b.rs#L7",
+            ),
+            (
+                "https://gitlab.example.com/group/sub/tool.git",
+                "https://gitlab.example.com/group/sub/tool/-/blob/{sha}/src/a%20b.rs#L7",
+            ),
+            (
+                "https://codeberg.org/org/tool.git",
+                "https://codeberg.org/org/tool/src/commit/{sha}/src/a%20b.rs#L7",
+            ),
+            (
+                "git@bitbucket.org:org/tool.git",
+                "https://bitbucket.org/org/tool/src/{sha}/src/a%20b.rs#lines-7",
+            ),
+        ];
+        for (remote, expected) in cases {
+            let mut results = results("src/a b.rs", 7);
+            add_permalinks(
+                &meta(dir.path(), Some(remote)),
+                dir.path(),
+                None,
+                &mut results,
+            )
+            .unwrap();
+            assert_eq!(url(&results), expected.replace("{sha}", &"a".repeat(40)));
+        }
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_repo_root() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("crates")).unwrap();
+        let mut results = results("lib.rs", 3);
+        add_permalinks(
+            &meta(dir.path(), Some("https://github.com/org/tool")),
+            &dir.path().join("crates"),
+            Some("gitea"),
+            &mut results,
+        )
+        .unwrap();
+        assert_eq!(
+            url(&results),
+            format!(
+                "https://github.com/org/tool/src/commit/{}/crates/lib.rs#L3",
+                "a".repeat(40)
+            )
+        );
+    }
+
+    #[test]
+    fn custom_templates() {
+        let dir = TempDir::new().unwrap();
+        let mut results = results("lib.rs", 3);
+        add_permalinks(
+            &meta(dir.path(), None),
+            dir.path(),
+            Some("https://code.internal/{sha}/{path}?line={line}"),
+            &mut results,
+        )
+        .unwrap();
+        assert_eq!(
+            url(&results),
+            format!("https://code.internal/{}/lib.rs?line=3", "a".repeat(40))
+        );
+
+        let err = add_permalinks(
+            &meta(dir.path(), None),
+            dir.path(),
+            Some("https://code.internal/{commit}/{path}"),
+            &mut results,
+        )
+        .unwrap_err();
+        assert!(matches!(err, GitError::PermalinkPlaceholder(name) if name == "commit"));
+
+        let err =
+            add_permalinks(&meta(dir.path(), None), dir.path(), None, &mut results).unwrap_err();
+        assert!(matches!(err, GitError::NoRemote));
+
+        let err = add_permalinks(
+            &meta(dir.path(), Some("https://git.internal/org/tool")),
+            dir.path(),
+            None,
+            &mut results,
+        )
+        .unwrap_err();
+        assert!(matches!(err, GitError::UnknownForge(_)));
+    }
+}
@@ -0,0 +1,237 @@
+//! Submodules, and nested repositories generally, scanned as repositories of
+//! their own: their entries are tagged, and blame and metadata come from the
+//! submodule rather than the superproject.
+
+use super::{GitBackend, GitError, GitMeta};
+use crate::scan::ScanResult;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// A repository nested under the scan root.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Submodule {
+    /// Path relative to the scan root
+    pub path: PathBuf,
+    /// The checked out commit, or the one the superproject records with `--rev`
+    pub commit: String,
+}
+
+/// Git metadata of a submodule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubmoduleMeta {
+    /// Path relative to the scan root
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub meta: GitMeta,
+}
+
+/// Directories holding a nested repository (a `.git` file or directory) that
+/// contain any of `files`. Both are relative to `root`.
+pub fn nested_repos(root: &Path, files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut checked: HashMap<&Path, bool> = HashMap::new();
+    let mut repos = BTreeSet::new();
+    for file in files {
+        for dir in file.ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            if checked.contains_key(dir) {
+                // Its ancestors were seen along with it.
+                break;
+            }
+            let is_repo = root.join(dir).join(".git").exists();
+            checked.insert(dir, is_repo);
+            if is_repo {
+                repos.insert(dir.to_path_buf());
+            }
+        }
+    }
+    repos.into_iter().collect()
+}
+
+/// The innermost of `submodules` containing `path`.
+pub fn containing<'a>(submodules: &'a [Submodule], path: &Path) -> Option<&'a Submodule> {
+    submodules
+        .iter()
+        .filter(|s| path.starts_with(&s.path))
+        .max_by_key(|s| s.path.components().count())
+}
+
+/// Tag each entry with the submodule it was found in.
+pub fn tag_submodules(submodules: &[Submodule], results: &mut ScanResult) {
+    for entry in results.values_mut().flatten() {
+        entry.submodule = containing(submodules, &entry.file).cloned();
+    }
+}
+
+impl GitBackend {
+    /// The submodules among `dirs` (relative to the scan root), at their
+    /// checked out commits.
+    pub fn worktree_submodules(
+        self,
+        scan_root: &Path,
+        dirs: Vec<PathBuf>,
+    ) -> Result<Vec<Submodule>, GitError> {
+        dirs.into_iter()
+            .map(|path| {
+                Ok(Submodule {
+                    commit: self.resolve_revision(&scan_root.join(&path), "HEAD")?,
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// Submodules recorded in the tree of `rev`, nested ones included, at the
+    /// commits the tree records. Submodules that are not checked out are
+    /// skipped, since their objects are not available.
+    pub fn revision_submodules(
+        self,
+        scan_root: &Path,
+        rev: &str,
+    ) -> Result<Vec<Submodule>, GitError> {
+        let mut submodules = Vec::new();
+        for link in self.list_gitlinks(scan_root, rev)? {
+            let dir = scan_root.join(&link.path);
+            if !dir.join(".git").exists() {
+                continue;
+            }
+            for nested in self.revision_submodules(&dir, &link.oid)? {
+                submodules.push(Submodule {
+                    path: link.path.join(nested.path),
+                    commit: nested.commit,
+                });
+            }
+            submodules.push(Submodule {
+                path: link.path,
+                commit: link.oid,
+            });
+        }
+        submodules.sort();
+        Ok(submodules)
+    }
+
+    /// Metadata for each submodule, as of its tagged commit when scanning `rev`.
+    pub fn collect_submodule_meta(
+        self,
+        scan_root: &Path,
+        rev: Option<&str>,
+        submodules: &[Submodule],
+    ) -> Result<Vec<SubmoduleMeta>, GitError> {
+        submodules
+            .iter()
+            .map(|submodule| {
+                let rev = rev.map(|_| submodule.commit.as_str());
+                Ok(SubmoduleMeta {
+                    path: submodule.path.clone(),
+                    meta: self.collect_git_meta(&scan_root.join(&submodule.path), rev)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::{git_in, init_repo};
+    use clap::ValueEnum;
+    use std::fs;
+
+    fn submodule(path: &str) -> Submodule {
+        Submodule {
+            path: PathBuf::from(path),
+            commit: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_nested_repositories_holding_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("a/b/.git")).unwrap();
+        fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+        fs::write(dir.path().join("a/b/c/.git"), "gitdir: elsewhere\n").unwrap();
+        fs::create_dir_all(dir.path().join("d/.git")).unwrap();
+
+        let files = [
+            PathBuf::from("top.rs"),
+            PathBuf::from("a/b/lib.rs"),
+            PathBuf::from("a/b/c/deep/lib.rs"),
+            PathBuf::from("a/b/c/other.rs"),
+        ];
+        assert_eq!(
+            nested_repos(dir.path(), &files),
+            vec![PathBuf::from("a/b"), PathBuf::from("a/b/c")]
+        );
+    }
+
+    #[test]
+    fn picks_the_innermost_submodule() {
+        let submodules = [submodule("a"), submodule("a/b"), submodule("ab")];
+        let path = |path: &str| containing(&submodules, Path::new(path)).map(|s| &s.commit);
+        assert_eq!(path("a/b/c.rs").map(String::as_str), Some("a/b"));
+        assert_eq!(path("a/c.rs").map(String::as_str), Some("a"));
+        assert_eq!(path("ab/c.rs").map(String::as_str), Some("ab"));
+        assert_eq!(path("abc.rs"), None);
+    }
+
+    #[test]
+    fn lists_recorded_submodules_recursively() {
+        for &backend in GitBackend::value_variants() {
+            let inner = init_repo();
+            fs::write(inner.path().join("inner.rs"), "").unwrap();
+            git_in(inner.path(), &["add", "-A"]).unwrap();
+            git_in(inner.path(), &["commit", "-m", "inner"]).unwrap();
+
+            let outer = init_repo();
+            let add = |repo: &Path, url: &Path, path: &str| {
+                git_in(
+                    repo,
+                    &[
+                        "-c",
+                        "protocol.file.allow=always",
+                        "submodule",
+                        "add",
+                        url.to_str().unwrap(),
+                        path,
+                    ],
+                )
+                .unwrap();
+                git_in(repo, &["commit", "-m", path]).unwrap();
+            };
+            add(outer.path(), inner.path(), "deps/inner");
+            let repo = init_repo();
+            add(repo.path(), outer.path(), "outer");
+            git_in(
+                repo.path(),
+                &[
+                    "-c",
+                    "protocol.file.allow=always",
+                    "submodule",
+                    "update",
+                    "--init",
+                    "--recursive",
+                ],
+            )
+            .unwrap();
+
+            let head = |dir: &Path| backend.resolve_revision(dir, "HEAD").unwrap();
+            let submodules = backend.revision_submodules(repo.path(), "HEAD").unwrap();
+            assert_eq!(
+                submodules,
+                vec![
+                    Submodule {
+                        path: PathBuf::from("outer"),
+                        commit: head(outer.path()),
+                    },
+                    Submodule {
+                        path: PathBuf::from("outer/deps/inner"),
+                        commit: head(inner.path()),
+                    },
+                ],
+                "{backend:?}"
+            );
+        }
+    }
+}
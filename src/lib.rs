@@ -1,7 +1,11 @@
 pub mod args;
+pub mod check;
+pub mod component;
 pub mod config;
+pub mod diff;
 pub mod error;
 pub mod filter;
 pub mod git;
 pub mod output;
+pub mod revision;
 pub mod scan;
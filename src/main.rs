@@ -1,19 +1,26 @@
 use clap::Parser;
+use std::collections::BTreeSet;
 use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use tracy::args::Args;
-use tracy::args::resolve_args;
-use tracy::config::{find_config, load_config};
+use tracy::args::{Command, ConfigCommand, ResolvedArgs, ScanRoot, resolve_args};
+use tracy::check::{Rule, check_rules, configured_rules, max_severity};
+use tracy::component::{Component, collect_component_files, discover_components, tag_component};
+use tracy::config::{effective_config, find_config, json_schema, load_config, show_config};
+use tracy::diff::{DiffReport, RevRange, diff_results};
 use tracy::error::TracyError;
-use tracy::filter::collect_files;
-use tracy::git::{add_blame, collect_git_meta};
-use tracy::output::format_output;
-use tracy::scan::scan_files;
+use tracy::git::{GitMeta, RootMeta, Submodule, add_permalinks, nested_repos, tag_submodules};
+use tracy::output::{FormatOptions, Output, OutputFormat, SourceFiles, format_diff, format_output};
+use tracy::revision::{revision_components, scan_revision};
+use tracy::scan::{NearMiss, ScanResult, apply_fixes, lint_files, merge_results, scan_files};
 
 fn main() -> ExitCode {
     match run() {
-        Ok(()) => ExitCode::SUCCESS,
+        Ok(code) => code,
         Err(e) => {
             eprintln!("error: {e}");
             ExitCode::FAILURE
@@ -21,14 +28,22 @@ fn main() -> ExitCode {
     }
 }
 
-fn run() -> Result<(), TracyError> {
+fn run() -> Result<ExitCode, TracyError> {
     let cli = Args::parse();
 
+    if let Some(Command::Config {
+        command: ConfigCommand::Schema,
+    }) = &cli.command
+    {
+        println!("{}", serde_json::to_string_pretty(&json_schema())?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
     let cwd = std::env::current_dir()?;
     let search_start = cli
         .root
-        .clone()
-        .map(|p| if p.is_absolute() { p } else { cwd.join(p) })
+        .first()
+        .map(|root| cwd.join(&root.path))
         .unwrap_or_else(|| cwd.clone());
 
     let config_path = if cli.no_config {
@@ -43,7 +58,7 @@ fn run() -> Result<(), TracyError> {
         find_config(&search_start).map(|p| if p.is_absolute() { p } else { cwd.join(p) })
     };
 
-    let (config, config_dir) = match config_path {
+    let (config, config_dir) = match config_path.clone() {
         Some(path) => {
             let config = load_config(&path)?;
             let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or(cwd);
@@ -54,31 +69,371 @@ fn run() -> Result<(), TracyError> {
 
     let args = resolve_args(cli, config, config_dir.as_deref())?;
 
-    let files = collect_files(&args.root, &args.filter)?;
-    let mut matches = scan_files(&args.root, &files, &args.scan)?;
+    if let Some(Command::Config {
+        command: ConfigCommand::Show,
+    }) = &args.command
+    {
+        // Printed whatever `quiet` and `output` are, since they may be what is
+        // being looked into.
+        println!("{}", show_config(&args));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if let Some(Command::Config {
+        command: ConfigCommand::Validate,
+    }) = &args.command
+    {
+        // Loading the config and resolving the arguments checked the config
+        // itself; component configs are checked as they are discovered.
+        let path = config_path.ok_or(TracyError::NoConfig)?;
+        let mut checked = vec![path];
+        for root in &args.roots {
+            let components = components(&args, root, None)?;
+            checked.extend(
+                components
+                    .into_iter()
+                    .filter_map(|component| component.config)
+                    .map(|config| root.path.join(config)),
+            );
+        }
+        for path in &checked {
+            println!("ok: {}", path.display());
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
 
-    if args.include_blame {
-        add_blame(&args.root, &mut matches)?;
+    if let Some(Command::Diff { range }) = &args.command {
+        let output = run_diff(&args, range)?;
+        emit(&args, output)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut lint_violations = Vec::new();
+    if let Some(Command::Lint { fix }) = args.command {
+        if args.rev.is_some() {
+            return Err(TracyError::RevUnsupported("lint"));
+        }
+        for root in &args.roots {
+            let components = components(&args, root, None)?;
+            let mut near_misses = Vec::new();
+            for component in &components {
+                let files = collect_component_files(&root.path, &components, component)?;
+                near_misses.extend(lint_files(&root.path, &files, &component.scan)?);
+            }
+            if fix {
+                let fixed = apply_fixes(&root.path, &near_misses)?;
+                for near_miss in &fixed {
+                    eprintln!(
+                        "fixed: {}:{}: {} -> {}",
+                        labeled(root, &near_miss.file).display(),
+                        near_miss.line,
+                        near_miss.found,
+                        near_miss.suggestion.as_deref().unwrap_or_default()
+                    );
+                }
+                near_misses.retain(|n| !fixed.contains(n));
+            }
+            for near_miss in &mut near_misses {
+                near_miss.file = labeled(root, &near_miss.file);
+            }
+            lint_violations.extend(near_misses.iter().map(NearMiss::violation));
+        }
+    }
+
+    let checking = args.command == Some(Command::Check);
+
+    let mut matches = ScanResult::new();
+    let mut metas = Vec::new();
+    let mut sources = SourceFiles::new();
+    for root in &args.roots {
+        let (results, meta) = scan_root(&args, root, checking)?;
+        if args.format.shows_sources() {
+            sources.extend(read_sources(&args, root, &results));
+        }
+        merge_root(root, results, &mut matches);
+        metas.extend(meta.map(|meta| (root, meta)));
     }
 
     if args.fail_on_empty && matches.is_empty() {
         return Err(TracyError::NoResults);
     }
 
-    let meta = if args.include_git_meta {
-        Some(collect_git_meta(&args.root)?)
+    // The first root's metadata, with the others' listed under `roots`.
+    let mut metas = metas.into_iter();
+    let meta = metas
+        .next()
+        .map(|(_, mut meta)| {
+            meta.roots = metas
+                .map(|(root, meta)| RootMeta {
+                    label: root.label.clone().unwrap_or_default(),
+                    path: root.path.clone(),
+                    meta,
+                })
+                .collect();
+            meta
+        })
+        .filter(|_| args.include_git_meta);
+
+    let mut violations = lint_violations;
+    if checking {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        violations.extend(check_rules(&matches, &args.rules, now)?);
+    }
+
+    for violation in &violations {
+        eprintln!(
+            "{}: {}:{}: [{}] {}",
+            violation.severity.as_str(),
+            violation.file.display(),
+            violation.line,
+            violation.rule.id(),
+            violation.message
+        );
+    }
+
+    let mut options = format_options(&args);
+    if matches!(args.command, Some(Command::Lint { .. })) {
+        options.rules.push(Rule::MalformedMarker);
+        options.checking = true;
+    }
+    if checking {
+        options.rules.extend(configured_rules(&args.rules));
+        options.checking = true;
+    }
+    let output = format_output(
+        args.format,
+        &options,
+        meta.as_ref(),
+        &matches,
+        &violations,
+        &sources,
+    )?;
+    emit(&args, output)?;
+
+    Ok(max_severity(&violations)
+        .map(|s| ExitCode::from(s.exit_code()))
+        .unwrap_or(ExitCode::SUCCESS))
+}
+
+fn run_diff(args: &ResolvedArgs, range: &str) -> Result<Output, TracyError> {
+    if args.rev.is_some() {
+        return Err(TracyError::RevUnsupported("diff"));
+    }
+
+    let [root] = args.roots.as_slice() else {
+        return Err(TracyError::MultipleRootsUnsupported("diff"));
+    };
+    let range = RevRange::parse(range);
+
+    let base_rev = match &range.head {
+        Some(head) if range.symmetric => {
+            args.git_backend.merge_base(&root.path, &range.base, head)?
+        }
+        _ => range.base.clone(),
+    };
+    let base_components = components(args, root, Some(&base_rev))?;
+    let base = scan_revision(args.git_backend, &root.path, &base_rev, &base_components)?;
+
+    let head_components = components(args, root, range.head.as_deref())?;
+    let (head, head_sha) = match &range.head {
+        Some(head) => {
+            let head = scan_revision(args.git_backend, &root.path, head, &head_components)?;
+            (head.results, Some(head.sha))
+        }
+        None => (scan_tree(args, root, &head_components)?.0, None),
+    };
+
+    let report = DiffReport {
+        base: range.base,
+        base_sha: base.sha,
+        head: range.head,
+        head_sha,
+        changes: diff_results(base.results, head),
+    };
+
+    Ok(format_diff(args.format, &format_options(args), &report)?)
+}
+
+/// The format options, with the effective configuration and command line
+/// for formats that report them.
+fn format_options(args: &ResolvedArgs) -> FormatOptions {
+    let mut options = args.format_options.clone();
+    options.command_line = std::env::args().collect();
+    options.roots = args
+        .roots
+        .iter()
+        .filter_map(|root| {
+            let path = fs::canonicalize(&root.path).unwrap_or_else(|_| root.path.clone());
+            Some((root.label.clone()?, path))
+        })
+        .collect();
+    if args.format == OutputFormat::Envelope {
+        options.config = effective_config(args);
+    }
+    options
+}
+
+/// Scan one root, with blame and permalinks, and collect its git metadata
+/// when the output or permalinks need it.
+fn scan_root(
+    args: &ResolvedArgs,
+    root: &ScanRoot,
+    checking: bool,
+) -> Result<(ScanResult, Option<GitMeta>), TracyError> {
+    let components = components(args, root, args.rev.as_deref())?;
+    let (mut results, submodules) = match &args.rev {
+        Some(rev) => {
+            let scan = scan_revision(args.git_backend, &root.path, rev, &components)?;
+            (scan.results, scan.submodules)
+        }
+        None => scan_tree(args, root, &components)?,
+    };
+
+    if args.include_blame || (checking && args.rules.max_age.is_some()) {
+        args.git_backend
+            .add_blame(&root.path, args.rev.as_deref(), &args.blame, &mut results)?;
+    }
+
+    if !args.include_git_meta && !args.include_permalinks {
+        return Ok((results, None));
+    }
+
+    let mut meta = args
+        .git_backend
+        .collect_git_meta(&root.path, args.rev.as_deref())?;
+    meta.submodules =
+        args.git_backend
+            .collect_submodule_meta(&root.path, args.rev.as_deref(), &submodules)?;
+
+    if args.include_permalinks {
+        add_permalinks(
+            &meta,
+            &root.path,
+            args.permalink_template.as_deref(),
+            &mut results,
+        )?;
+    }
+
+    Ok((results, Some(meta)))
+}
+
+/// The components of a root as of `rev`, or in the working tree: the root
+/// itself and any `tracy.toml` below it.
+fn components(
+    args: &ResolvedArgs,
+    root: &ScanRoot,
+    rev: Option<&str>,
+) -> Result<Vec<Component>, TracyError> {
+    match rev {
+        Some(rev) => revision_components(
+            args.git_backend,
+            root,
+            rev,
+            &args.scan,
+            &args.cli,
+            args.component_configs,
+        ),
+        None => discover_components(root, &args.scan, &args.cli, args.component_configs),
+    }
+}
+
+/// Scan the working tree, each component with its own settings, tagging
+/// entries found in submodules.
+fn scan_tree(
+    args: &ResolvedArgs,
+    root: &ScanRoot,
+    components: &[Component],
+) -> Result<(ScanResult, Vec<Submodule>), TracyError> {
+    let mut files = Vec::new();
+    let mut results = ScanResult::new();
+    for component in components {
+        let component_files = collect_component_files(&root.path, components, component)?;
+        let mut found = scan_files(&root.path, &component_files, &component.scan)?;
+        tag_component(component, &mut found);
+        merge_results(found, &mut results);
+        files.extend(component_files);
+    }
+
+    let submodules = if root.filter.include_submodules {
+        let relative: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|path| path.strip_prefix(&root.path).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        args.git_backend
+            .worktree_submodules(&root.path, nested_repos(&root.path, &relative))?
     } else {
-        None
+        Vec::new()
     };
+    tag_submodules(&submodules, &mut results);
+
+    Ok((results, submodules))
+}
 
-    let output = format_output(args.format, meta.as_ref(), &matches)?;
+/// The text of the files with entries under a root, by their reported path.
+/// Files that cannot be read, such as those of submodules at `--rev`, are
+/// left out.
+fn read_sources(args: &ResolvedArgs, root: &ScanRoot, results: &ScanResult) -> SourceFiles {
+    let files: BTreeSet<&Path> = results
+        .values()
+        .flatten()
+        .map(|e| e.file.as_path())
+        .collect();
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let text = match &args.rev {
+                Some(rev) => args
+                    .git_backend
+                    .show_file(&root.path, rev, &file.to_string_lossy())
+                    .ok()
+                    .flatten(),
+                None => fs::read_to_string(root.path.join(file)).ok(),
+            }?;
+            Some((labeled(root, file), text))
+        })
+        .collect()
+}
+
+/// Add a root's results to `into`, tagging entries with the root's label and
+/// prefixing their paths with it.
+fn merge_root(root: &ScanRoot, mut results: ScanResult, into: &mut ScanResult) {
+    for entry in results.values_mut().flatten() {
+        entry.file = labeled(root, &entry.file);
+        entry.component = entry.component.as_deref().map(|path| labeled(root, path));
+        entry.root = root.label.clone();
+    }
+    merge_results(results, into);
+}
 
+/// `path`, relative to `root`, as reported: prefixed with the root's label.
+fn labeled(root: &ScanRoot, path: &Path) -> PathBuf {
+    match &root.label {
+        Some(label) => Path::new(label).join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+fn emit(args: &ResolvedArgs, output: impl Into<Output>) -> Result<(), TracyError> {
+    let output = output.into();
     if !args.quiet {
-        println!("{output}");
+        match &output {
+            Output::Text(text) => println!("{text}"),
+            // Not shown, but fine when kept in the output file.
+            Output::Binary(_) if io::stdout().is_terminal() => {
+                if args.output.is_none() {
+                    return Err(TracyError::BinaryToTerminal);
+                }
+            }
+            Output::Binary(bytes) => io::stdout().write_all(bytes)?,
+        }
     }
 
     if let Some(path) = &args.output {
-        fs::write(path, &output)?;
+        fs::write(path, output.as_bytes())?;
     }
 
     Ok(())
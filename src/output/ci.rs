@@ -0,0 +1,514 @@
+//! Reports CI systems ingest: GitLab Code Quality, Checkstyle and JUnit.
+
+use super::FormatOptions;
+use super::sarif::{Fingerprints, REFERENCE_RULE, change_message};
+use super::spreadsheet::xml;
+use crate::check::{Severity, Violation, slug_of};
+use crate::diff::{ChangeKind, DiffReport};
+use crate::scan::{Entry, ScanResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+/// A reference, violation or change, as Code Quality and Checkstyle list
+/// them.
+struct Finding<'a> {
+    check: String,
+    severity: Severity,
+    file: &'a Path,
+    line: usize,
+    message: String,
+    fingerprint: String,
+}
+
+/// References as notes under a check per slug, then violations under their
+/// rule, fingerprinted as in SARIF.
+fn scan_findings<'a>(results: &'a ScanResult, violations: &'a [Violation]) -> Vec<Finding<'a>> {
+    let mut fingerprints = Fingerprints::default();
+    let mut findings = Vec::new();
+    for (requirement_id, entries) in results {
+        for entry in entries {
+            findings.push(Finding {
+                check: format!("{REFERENCE_RULE}/{}", slug_of(requirement_id)),
+                severity: Severity::Note,
+                file: &entry.file,
+                line: entry.line,
+                message: format!("Requirement reference: {requirement_id}"),
+                fingerprint: fingerprints.entry(REFERENCE_RULE, requirement_id, entry),
+            });
+        }
+    }
+    for violation in violations {
+        findings.push(Finding {
+            check: violation.rule.id().to_string(),
+            severity: violation.severity,
+            file: &violation.file,
+            line: violation.line,
+            message: violation.message.clone(),
+            fingerprint: fingerprints.violation(violation),
+        });
+    }
+    findings
+}
+
+/// Each change as a note under `diff.<kind>`, where the reference now lives
+/// or, when removed, where it was.
+fn diff_findings(report: &DiffReport) -> Vec<Finding<'_>> {
+    let mut fingerprints = Fingerprints::default();
+    report
+        .changes
+        .iter()
+        .filter_map(|change| {
+            let entry = change.head.as_ref().or(change.base.as_ref())?;
+            let check = format!("diff.{}", change.kind.as_str());
+            Some(Finding {
+                fingerprint: fingerprints.entry(&check, &change.requirement_id, entry),
+                check,
+                severity: Severity::Note,
+                file: &entry.file,
+                line: entry.line,
+                message: change_message(change),
+            })
+        })
+        .collect()
+}
+
+fn path(file: &Path) -> String {
+    file.to_string_lossy().replace('\\', "/")
+}
+
+pub(super) fn format_codequality(
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Result<String, serde_json::Error> {
+    codequality(&scan_findings(results, violations))
+}
+
+pub(super) fn format_diff_codequality(report: &DiffReport) -> Result<String, serde_json::Error> {
+    codequality(&diff_findings(report))
+}
+
+/// A GitLab Code Quality report: a JSON array of issues.
+fn codequality(findings: &[Finding]) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct Issue<'a> {
+        description: &'a str,
+        check_name: &'a str,
+        fingerprint: &'a str,
+        severity: &'static str,
+        location: Location,
+    }
+
+    #[derive(Serialize)]
+    struct Location {
+        path: String,
+        lines: Lines,
+    }
+
+    #[derive(Serialize)]
+    struct Lines {
+        begin: usize,
+    }
+
+    let issues: Vec<Issue> = findings
+        .iter()
+        .map(|finding| Issue {
+            description: &finding.message,
+            check_name: &finding.check,
+            fingerprint: &finding.fingerprint,
+            severity: match finding.severity {
+                Severity::Note => "info",
+                Severity::Warning => "minor",
+                Severity::Error => "major",
+            },
+            location: Location {
+                path: path(finding.file),
+                lines: Lines {
+                    begin: finding.line,
+                },
+            },
+        })
+        .collect();
+    serde_json::to_string_pretty(&issues)
+}
+
+pub(super) fn format_checkstyle(results: &ScanResult, violations: &[Violation]) -> String {
+    checkstyle(&scan_findings(results, violations))
+}
+
+pub(super) fn format_diff_checkstyle(report: &DiffReport) -> String {
+    checkstyle(&diff_findings(report))
+}
+
+/// A Checkstyle report: the findings of each file, in line order.
+fn checkstyle(findings: &[Finding]) -> String {
+    let mut files: BTreeMap<&Path, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        files.entry(finding.file).or_default().push(finding);
+    }
+
+    let mut out = String::from(XML_HEADER);
+    out.push_str("<checkstyle version=\"4.3\">\n");
+    for (file, mut findings) in files {
+        findings.sort_by_key(|finding| finding.line);
+        let _ = writeln!(out, "  <file name=\"{}\">", xml(&path(file)));
+        for finding in findings {
+            let _ = writeln!(
+                out,
+                "    <error line=\"{}\" severity=\"{}\" message=\"{}\" source=\"{}\"/>",
+                finding.line,
+                match finding.severity {
+                    Severity::Note => "info",
+                    Severity::Warning => "warning",
+                    Severity::Error => "error",
+                },
+                xml(&finding.message),
+                xml(&finding.check)
+            );
+        }
+        out.push_str("  </file>\n");
+    }
+    out.push_str("</checkstyle>");
+    out
+}
+
+/// A JUnit test case.
+struct TestCase<'a> {
+    name: &'a str,
+    classname: String,
+    /// Where the test case is reported, if anywhere
+    location: Option<(&'a Path, usize)>,
+    url: Option<String>,
+    /// Failure type, message and text
+    failures: Vec<(&'a str, String, String)>,
+    system_out: String,
+}
+
+/// Each requirement as a test case in a suite per slug, passing when it is
+/// referenced and has no warning or error. Notes are listed with the
+/// references.
+pub(super) fn format_junit(
+    options: &FormatOptions,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> String {
+    const NO_ENTRIES: &[Entry] = &[];
+    let mut requirements: BTreeMap<&str, (&[Entry], Vec<&Violation>)> = results
+        .iter()
+        .map(|(id, entries)| (id.as_str(), (entries.as_slice(), Vec::new())))
+        .collect();
+    for violation in violations {
+        requirements
+            .entry(&violation.requirement_id)
+            .or_insert((NO_ENTRIES, Vec::new()))
+            .1
+            .push(violation);
+    }
+
+    let mut suites: BTreeMap<&str, Vec<TestCase>> = BTreeMap::new();
+    for (id, (entries, violations)) in requirements {
+        let mut case = TestCase {
+            name: id,
+            classname: slug_of(id).to_string(),
+            location: entries.first().map(|e| (e.file.as_path(), e.line)),
+            url: options.requirement_url(id),
+            failures: Vec::new(),
+            system_out: String::new(),
+        };
+        if entries.is_empty() {
+            case.failures
+                .push(("untraced", format!("{id} has no references"), String::new()));
+        }
+        for entry in entries {
+            let _ = writeln!(
+                case.system_out,
+                "{}:{}: {}",
+                path(&entry.file),
+                entry.line,
+                entry.comment_text.trim_end()
+            );
+        }
+        for violation in violations {
+            let location = format!("{}:{}", path(&violation.file), violation.line);
+            if violation.severity == Severity::Note {
+                let _ = writeln!(case.system_out, "{location}: note: {}", violation.message);
+            } else {
+                case.failures
+                    .push((violation.rule.id(), violation.message.clone(), location));
+            }
+        }
+        suites.entry(slug_of(id)).or_default().push(case);
+    }
+    junit(suites)
+}
+
+/// Each change as a passing test case in a suite per change kind.
+pub(super) fn format_diff_junit(options: &FormatOptions, report: &DiffReport) -> String {
+    let mut suites: BTreeMap<ChangeKind, Vec<TestCase>> = BTreeMap::new();
+    for change in &report.changes {
+        let entry = change.head.as_ref().or(change.base.as_ref());
+        suites.entry(change.kind).or_default().push(TestCase {
+            name: &change.requirement_id,
+            classname: format!("diff.{}", change.kind.as_str()),
+            location: entry.map(|e| (e.file.as_path(), e.line)),
+            url: options.requirement_url(&change.requirement_id),
+            failures: Vec::new(),
+            system_out: format!("{}\n", change_message(change)),
+        });
+    }
+    junit(
+        suites
+            .into_iter()
+            .map(|(kind, cases)| (kind.as_str(), cases)),
+    )
+}
+
+fn junit<'a>(suites: impl IntoIterator<Item = (&'a str, Vec<TestCase<'a>>)>) -> String {
+    let suites: Vec<_> = suites.into_iter().collect();
+    let count = |cases: &[TestCase]| {
+        let failures = cases.iter().filter(|c| !c.failures.is_empty()).count();
+        (cases.len(), failures)
+    };
+
+    let mut out = String::from(XML_HEADER);
+    let (tests, failures) = suites
+        .iter()
+        .map(|(_, cases)| count(cases))
+        .fold((0, 0), |(t, f), (tests, failures)| {
+            (t + tests, f + failures)
+        });
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"tracy\" tests=\"{tests}\" failures=\"{failures}\">"
+    );
+    for (name, cases) in &suites {
+        let (tests, failures) = count(cases);
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{tests}\" failures=\"{failures}\">",
+            xml(name)
+        );
+        for case in cases {
+            let _ = write!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\"",
+                xml(case.name),
+                xml(&case.classname)
+            );
+            if let Some((file, line)) = case.location {
+                let _ = write!(out, " file=\"{}\" line=\"{line}\"", xml(&path(file)));
+            }
+            out.push_str(">\n");
+            if let Some(url) = &case.url {
+                let _ = writeln!(
+                    out,
+                    "      <properties>\n        <property name=\"requirement_url\" value=\"{}\"/>\n      </properties>",
+                    xml(url)
+                );
+            }
+            for (kind, message, text) in &case.failures {
+                let _ = writeln!(
+                    out,
+                    "      <failure type=\"{}\" message=\"{}\">{}</failure>",
+                    xml(kind),
+                    xml(message),
+                    xml(text)
+                );
+            }
+            if !case.system_out.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "      <system-out>{}</system-out>",
+                    xml(&case.system_out)
+                );
+            }
+            out.push_str("    </testcase>\n");
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::Rule;
+    use crate::diff::Change;
+    use std::path::PathBuf;
+
+    fn entry(file: &str, line: usize, text: &str) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line,
+            comment_text: text.to_string(),
+            above: None,
+            below: None,
+            inline: None,
+            scope: Vec::new(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    fn results() -> ScanResult {
+        let mut results = ScanResult::new();
+        results.insert(
+            "REQ-1".to_string(),
+            vec![
+                entry("src/main.rs", 7, "// REQ-1"),
+                entry("src/lib.rs", 3, "// REQ-1 <a & b>"),
+            ],
+        );
+        results.insert(
+            "SYS-2".to_string(),
+            vec![entry("src/lib.rs", 1, "// SYS-2")],
+        );
+        results
+    }
+
+    fn violation(rule: Rule, severity: Severity, id: &str) -> Violation {
+        Violation {
+            rule,
+            severity,
+            requirement_id: id.to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 3,
+            message: format!("{id} is \"wrong\""),
+        }
+    }
+
+    #[test]
+    fn codequality_lists_references_and_violations() {
+        let violations = [violation(Rule::MinReferences, Severity::Error, "SYS-2")];
+        let out = format_codequality(&results(), &violations).unwrap();
+        let issues: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let issues = issues.as_array().unwrap();
+        assert_eq!(issues.len(), 4);
+        assert_eq!(issues[0]["check_name"], "traceability.requirement_ref/REQ");
+        assert_eq!(issues[0]["severity"], "info");
+        assert_eq!(issues[0]["location"]["path"], "src/main.rs");
+        assert_eq!(issues[0]["location"]["lines"]["begin"], 7);
+        assert_eq!(issues[3]["check_name"], "policy.min_references");
+        assert_eq!(issues[3]["severity"], "major");
+
+        let fingerprints: std::collections::BTreeSet<_> =
+            issues.iter().map(|i| i["fingerprint"].as_str()).collect();
+        assert_eq!(fingerprints.len(), issues.len());
+    }
+
+    #[test]
+    fn checkstyle_groups_by_file_in_line_order() {
+        let violations = [violation(Rule::MaxAge, Severity::Warning, "REQ-1")];
+        assert_eq!(
+            format_checkstyle(&results(), &violations),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <checkstyle version=\"4.3\">\n  \
+             <file name=\"src/lib.rs\">\n    \
+             <error line=\"1\" severity=\"info\" message=\"Requirement reference: SYS-2\" source=\"traceability.requirement_ref/SYS\"/>\n    \
+             <error line=\"3\" severity=\"info\" message=\"Requirement reference: REQ-1\" source=\"traceability.requirement_ref/REQ\"/>\n    \
+             <error line=\"3\" severity=\"warning\" message=\"REQ-1 is &quot;wrong&quot;\" source=\"policy.max_age\"/>\n  \
+             </file>\n  \
+             <file name=\"src/main.rs\">\n    \
+             <error line=\"7\" severity=\"info\" message=\"Requirement reference: REQ-1\" source=\"traceability.requirement_ref/REQ\"/>\n  \
+             </file>\n\
+             </checkstyle>"
+        );
+    }
+
+    #[test]
+    fn junit_fails_requirements_with_violations_or_no_references() {
+        let options = FormatOptions {
+            requirement_url: Some("https://issues.example.com/{id}".to_string()),
+            ..FormatOptions::default()
+        };
+        let violations = [
+            violation(Rule::MinReferences, Severity::Error, "SYS-2"),
+            violation(Rule::MaxAge, Severity::Note, "REQ-1"),
+            violation(Rule::MalformedMarker, Severity::Warning, "REQ-"),
+        ];
+        assert_eq!(
+            format_junit(&options, &results(), &violations),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuites name=\"tracy\" tests=\"3\" failures=\"2\">\n  \
+             <testsuite name=\"REQ\" tests=\"2\" failures=\"1\">\n    \
+             <testcase name=\"REQ-\" classname=\"REQ\">\n      \
+             <properties>\n        \
+             <property name=\"requirement_url\" value=\"https://issues.example.com/REQ-\"/>\n      \
+             </properties>\n      \
+             <failure type=\"untraced\" message=\"REQ- has no references\"></failure>\n      \
+             <failure type=\"lint.malformed_marker\" message=\"REQ- is &quot;wrong&quot;\">src/lib.rs:3</failure>\n    \
+             </testcase>\n    \
+             <testcase name=\"REQ-1\" classname=\"REQ\" file=\"src/main.rs\" line=\"7\">\n      \
+             <properties>\n        \
+             <property name=\"requirement_url\" value=\"https://issues.example.com/REQ-1\"/>\n      \
+             </properties>\n      \
+             <system-out>src/main.rs:7: // REQ-1\n\
+             src/lib.rs:3: // REQ-1 &lt;a &amp; b&gt;\n\
+             src/lib.rs:3: note: REQ-1 is &quot;wrong&quot;\n\
+             </system-out>\n    \
+             </testcase>\n  \
+             </testsuite>\n  \
+             <testsuite name=\"SYS\" tests=\"1\" failures=\"1\">\n    \
+             <testcase name=\"SYS-2\" classname=\"SYS\" file=\"src/lib.rs\" line=\"1\">\n      \
+             <properties>\n        \
+             <property name=\"requirement_url\" value=\"https://issues.example.com/SYS-2\"/>\n      \
+             </properties>\n      \
+             <failure type=\"policy.min_references\" message=\"SYS-2 is &quot;wrong&quot;\">src/lib.rs:3</failure>\n      \
+             <system-out>src/lib.rs:1: // SYS-2\n\
+             </system-out>\n    \
+             </testcase>\n  \
+             </testsuite>\n\
+             </testsuites>"
+        );
+    }
+
+    #[test]
+    fn diffs_report_each_change() {
+        let report = DiffReport {
+            base: "main".to_string(),
+            base_sha: "a".repeat(40),
+            head: None,
+            head_sha: None,
+            changes: vec![
+                Change {
+                    kind: ChangeKind::Removed,
+                    requirement_id: "REQ-2".to_string(),
+                    base: Some(entry("src/old.rs", 4, "// REQ-2")),
+                    head: None,
+                },
+                Change {
+                    kind: ChangeKind::Added,
+                    requirement_id: "REQ-1".to_string(),
+                    base: None,
+                    head: Some(entry("src/lib.rs", 3, "// REQ-1")),
+                },
+            ],
+        };
+
+        let issues: serde_json::Value =
+            serde_json::from_str(&format_diff_codequality(&report).unwrap()).unwrap();
+        assert_eq!(issues[0]["check_name"], "diff.removed");
+        assert_eq!(issues[0]["description"], "REQ-2 removed");
+        assert_eq!(issues[0]["location"]["path"], "src/old.rs");
+
+        assert!(format_diff_checkstyle(&report).contains(
+            "<error line=\"3\" severity=\"info\" message=\"REQ-1 added\" source=\"diff.added\"/>"
+        ));
+
+        let junit = format_diff_junit(&FormatOptions::default(), &report);
+        assert!(junit.contains("<testsuites name=\"tracy\" tests=\"2\" failures=\"0\">"));
+        assert!(
+            junit.find("<testsuite name=\"added\"") < junit.find("<testsuite name=\"removed\"")
+        );
+        assert!(junit.contains(
+            "<testcase name=\"REQ-2\" classname=\"diff.removed\" file=\"src/old.rs\" line=\"4\">"
+        ));
+    }
+}
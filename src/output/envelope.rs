@@ -0,0 +1,65 @@
+//! The `envelope` format: JSON of a stable shape, versioned, with the run's
+//! configuration and totals alongside the results.
+
+use super::summary::{Summary, TOOL, Tool, diff_summary};
+use crate::check::Violation;
+use crate::diff::DiffReport;
+use crate::git::GitMeta;
+use crate::scan::ScanResult;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The version of the `envelope` format's shape, raised when it changes
+/// incompatibly.
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub(super) fn format_envelope(
+    config: &toml::Table,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        schema_version: u32,
+        tool: Tool,
+        config: &'a toml::Table,
+        summary: Summary<'a>,
+        meta: Option<&'a GitMeta>,
+        results: &'a ScanResult,
+        violations: &'a [Violation],
+    }
+
+    serde_json::to_string_pretty(&Envelope {
+        schema_version: SCHEMA_VERSION,
+        tool: TOOL,
+        config,
+        summary: Summary::new(results, violations),
+        meta,
+        results,
+        violations,
+    })
+}
+
+pub(super) fn format_diff_envelope(
+    config: &toml::Table,
+    report: &DiffReport,
+) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        schema_version: u32,
+        tool: Tool,
+        config: &'a toml::Table,
+        /// Changes by kind
+        summary: BTreeMap<&'static str, usize>,
+        diff: &'a DiffReport,
+    }
+
+    serde_json::to_string_pretty(&Envelope {
+        schema_version: SCHEMA_VERSION,
+        tool: TOOL,
+        config,
+        summary: diff_summary(report),
+        diff: report,
+    })
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OutputError {
+    #[error("failed to serialize output: {0}")]
+    Serialize(#[from] serde_json::Error),
+
+    #[error("failed to write spreadsheet: {0}")]
+    Spreadsheet(#[from] zip::result::ZipError),
+
+    #[error("the template format needs a template (use --template or `template` in the config)")]
+    NoTemplate,
+
+    #[error("unknown built-in template `{name}` (expected one of: {known})")]
+    UnknownTemplate { name: String, known: String },
+
+    #[error("failed to read template {path}: {source}")]
+    ReadTemplate {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("template error: {0}")]
+    Template(#[from] minijinja::Error),
+}
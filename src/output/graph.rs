@@ -0,0 +1,399 @@
+//! Requirement-to-code graphs, as Graphviz DOT or Mermaid flowcharts.
+//! Requirements link to the files and scopes they are referenced in; files
+//! contain their scopes, outermost first.
+
+use super::FormatOptions;
+use crate::diff::{ChangeKind, DiffReport};
+use crate::scan::{Entry, ScanResult};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::Component;
+
+/// What code nodes are merged into, instead of files and their scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphCollapse {
+    /// The directory of each file
+    Directory,
+    /// The component config each file was scanned with
+    Component,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Requirement,
+    Component,
+    Directory,
+    File,
+    Scope,
+}
+
+struct Node {
+    kind: Kind,
+    label: String,
+}
+
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    /// Node indices by key
+    ids: HashMap<String, usize>,
+    /// Parent and child of each containment edge
+    contains: BTreeSet<(usize, usize)>,
+    /// Requirement, code node and change kind of each reference edge, with
+    /// the number of references it stands for
+    references: BTreeMap<(usize, usize, Option<ChangeKind>), usize>,
+}
+
+impl Graph {
+    fn scan(options: &FormatOptions, results: &ScanResult) -> Graph {
+        let mut graph = Graph::default();
+        for (requirement_id, entries) in results {
+            for entry in entries {
+                graph.add(options, requirement_id, entry, None);
+            }
+        }
+        graph
+    }
+
+    /// The references changed between two revisions, labelled with the change.
+    fn diff(options: &FormatOptions, report: &DiffReport) -> Graph {
+        let mut graph = Graph::default();
+        for change in &report.changes {
+            if let Some(entry) = change.head.as_ref().or(change.base.as_ref()) {
+                graph.add(options, &change.requirement_id, entry, Some(change.kind));
+            }
+        }
+        graph
+    }
+
+    fn node(&mut self, key: String, kind: Kind, label: String) -> usize {
+        let next = self.nodes.len();
+        *self.ids.entry(key).or_insert_with(|| {
+            self.nodes.push(Node { kind, label });
+            next
+        })
+    }
+
+    fn add(
+        &mut self,
+        options: &FormatOptions,
+        requirement_id: &str,
+        entry: &Entry,
+        change: Option<ChangeKind>,
+    ) {
+        let requirement = self.node(
+            format!("req:{requirement_id}"),
+            Kind::Requirement,
+            requirement_id.to_string(),
+        );
+        let code = self.code_node(options, entry);
+        *self
+            .references
+            .entry((requirement, code, change))
+            .or_default() += 1;
+    }
+
+    /// The node standing for where `entry` is, adding the file's scopes down
+    /// to it.
+    fn code_node(&mut self, options: &FormatOptions, entry: &Entry) -> usize {
+        let depth = options.graph_depth.unwrap_or(usize::MAX).max(1);
+        let dirs: Vec<_> = entry
+            .file
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.components())
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let directory = |graph: &mut Graph, dirs: &[String]| {
+            let path = if dirs.is_empty() {
+                ".".to_string()
+            } else {
+                dirs.join("/") + "/"
+            };
+            graph.node(format!("dir:{path}"), Kind::Directory, path)
+        };
+
+        match options.graph_collapse {
+            Some(GraphCollapse::Component) => {
+                let path = match (&entry.component, &entry.root) {
+                    // The directory of the component's config file.
+                    (Some(config), _) => config
+                        .parent()
+                        .map(|dir| dir.display().to_string())
+                        .unwrap_or_default(),
+                    (None, Some(root)) => root.clone(),
+                    (None, None) => ".".to_string(),
+                };
+                self.node(format!("component:{path}"), Kind::Component, path)
+            }
+            Some(GraphCollapse::Directory) => directory(self, &dirs[..dirs.len().min(depth)]),
+            None if depth <= dirs.len() => directory(self, &dirs[..depth]),
+            None => {
+                let file = entry.file.display().to_string();
+                let mut node = self.node(format!("file:{file}"), Kind::File, file.clone());
+                let mut key = format!("scope:{file}");
+                let levels = depth - dirs.len() - 1;
+                for item in entry.scope.iter().rev().take(levels) {
+                    let _ = write!(key, "#{}", item.line);
+                    let label = match &item.name {
+                        Some(name) => format!("{} {name}", item.kind),
+                        None => item.kind.clone(),
+                    };
+                    let scope = self.node(key.clone(), Kind::Scope, label);
+                    self.contains.insert((node, scope));
+                    node = scope;
+                }
+                node
+            }
+        }
+    }
+
+    fn dot(&self) -> String {
+        let mut out = String::from("digraph tracy {\n    rankdir=LR;\n");
+        out.push_str("    node [fontname=\"Helvetica\", fontsize=10];\n");
+        for (id, node) in self.nodes.iter().enumerate() {
+            let shape = match node.kind {
+                Kind::Requirement => "shape=box, style=\"rounded,filled\", fillcolor=\"#dde4ee\"",
+                Kind::Component => "shape=component",
+                Kind::Directory => "shape=folder",
+                Kind::File => "shape=note",
+                Kind::Scope => "shape=ellipse",
+            };
+            let _ = writeln!(
+                out,
+                "    n{id} [label=\"{}\", {shape}];",
+                dot_escape(&node.label)
+            );
+        }
+        for (parent, child) in &self.contains {
+            let _ = writeln!(
+                out,
+                "    n{parent} -> n{child} [style=dashed, arrowhead=none];"
+            );
+        }
+        for ((requirement, code, change), count) in &self.references {
+            let mut attributes = Vec::new();
+            let label = edge_label(*change, *count);
+            if !label.is_empty() {
+                attributes.push(format!("label=\"{label}\""));
+            }
+            let color = change.map(|kind| match kind {
+                ChangeKind::Added => "#2da44e",
+                ChangeKind::Removed => "#cf222e",
+                ChangeKind::Moved => "#0969da",
+                ChangeKind::ChangedText => "#bf8700",
+            });
+            if let Some(color) = color {
+                attributes.push(format!("color=\"{color}\", fontcolor=\"{color}\""));
+            }
+            if *change == Some(ChangeKind::Removed) {
+                attributes.push("style=dashed".to_string());
+            }
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+            let _ = writeln!(out, "    n{requirement} -> n{code}{attributes};");
+        }
+        out.push('}');
+        out
+    }
+
+    fn mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (id, node) in self.nodes.iter().enumerate() {
+            let label = mermaid_escape(&node.label);
+            let _ = match node.kind {
+                Kind::Requirement => writeln!(out, "    n{id}([\"{label}\"]):::requirement"),
+                Kind::Component => writeln!(out, "    n{id}{{{{\"{label}\"}}}}"),
+                Kind::Directory => writeln!(out, "    n{id}[[\"{label}\"]]"),
+                Kind::File => writeln!(out, "    n{id}[\"{label}\"]"),
+                Kind::Scope => writeln!(out, "    n{id}(\"{label}\")"),
+            };
+        }
+        for (parent, child) in &self.contains {
+            let _ = writeln!(out, "    n{parent} -.- n{child}");
+        }
+        for ((requirement, code, change), count) in &self.references {
+            let label = edge_label(*change, *count);
+            let arrow = if *change == Some(ChangeKind::Removed) {
+                "-.->"
+            } else {
+                "-->"
+            };
+            if label.is_empty() {
+                let _ = writeln!(out, "    n{requirement} {arrow} n{code}");
+            } else {
+                let _ = writeln!(out, "    n{requirement} {arrow}|{label}| n{code}");
+            }
+        }
+        out.push_str("    classDef requirement fill:#dde4ee,stroke:#57606a");
+        out
+    }
+}
+
+pub(super) fn format_dot(options: &FormatOptions, results: &ScanResult) -> String {
+    Graph::scan(options, results).dot()
+}
+
+pub(super) fn format_mermaid(options: &FormatOptions, results: &ScanResult) -> String {
+    Graph::scan(options, results).mermaid()
+}
+
+pub(super) fn format_diff_dot(options: &FormatOptions, report: &DiffReport) -> String {
+    Graph::diff(options, report).dot()
+}
+
+pub(super) fn format_diff_mermaid(options: &FormatOptions, report: &DiffReport) -> String {
+    Graph::diff(options, report).mermaid()
+}
+
+/// The change and, when it stands for several references, how many.
+fn edge_label(change: Option<ChangeKind>, count: usize) -> String {
+    let change = change.map(|kind| kind.as_str().replace('_', " "));
+    match (change, count) {
+        (Some(change), 1) => change,
+        (Some(change), count) => format!("{change} ×{count}"),
+        (None, 1) => String::new(),
+        (None, count) => count.to_string(),
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::ScopeItem;
+    use std::path::PathBuf;
+
+    fn entry(file: &str, scope: &[(&str, &str, usize)]) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line: 1,
+            comment_text: String::new(),
+            above: None,
+            below: None,
+            inline: None,
+            // Innermost first, as scanned.
+            scope: scope
+                .iter()
+                .rev()
+                .map(|&(kind, name, line)| ScopeItem {
+                    kind: kind.into(),
+                    name: Some(name.into()),
+                    line,
+                })
+                .collect(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    fn results() -> ScanResult {
+        let check = [("mod_item", "tests", 1), ("function_item", "check", 3)];
+        ScanResult::from([
+            (
+                "REQ-1".to_string(),
+                vec![
+                    entry("src/output/mod.rs", &check),
+                    entry("src/output/mod.rs", &check),
+                ],
+            ),
+            (
+                "REQ-2".to_string(),
+                vec![
+                    entry("src/lib.rs", &[]),
+                    entry("src/output/mod.rs", &check[..1]),
+                ],
+            ),
+        ])
+    }
+
+    #[test]
+    fn links_requirements_to_scopes_within_files() {
+        let out = format_dot(&FormatOptions::default(), &results());
+        assert_eq!(
+            out,
+            "digraph tracy {\n    rankdir=LR;\n    node [fontname=\"Helvetica\", fontsize=10];\n\
+             \x20   n0 [label=\"REQ-1\", shape=box, style=\"rounded,filled\", fillcolor=\"#dde4ee\"];\n\
+             \x20   n1 [label=\"src/output/mod.rs\", shape=note];\n\
+             \x20   n2 [label=\"mod_item tests\", shape=ellipse];\n\
+             \x20   n3 [label=\"function_item check\", shape=ellipse];\n\
+             \x20   n4 [label=\"REQ-2\", shape=box, style=\"rounded,filled\", fillcolor=\"#dde4ee\"];\n\
+             \x20   n5 [label=\"src/lib.rs\", shape=note];\n\
+             \x20   n1 -> n2 [style=dashed, arrowhead=none];\n\
+             \x20   n2 -> n3 [style=dashed, arrowhead=none];\n\
+             \x20   n0 -> n3 [label=\"2\"];\n\
+             \x20   n4 -> n2;\n\
+             \x20   n4 -> n5;\n\
+             }"
+        );
+    }
+
+    #[test]
+    fn collapses_and_limits_depth() {
+        let options = FormatOptions {
+            graph_collapse: Some(GraphCollapse::Directory),
+            ..FormatOptions::default()
+        };
+        let out = format_mermaid(&options, &results());
+        assert_eq!(
+            out,
+            "flowchart LR\n\
+             \x20   n0([\"REQ-1\"]):::requirement\n\
+             \x20   n1[[\"src/output/\"]]\n\
+             \x20   n2([\"REQ-2\"]):::requirement\n\
+             \x20   n3[[\"src/\"]]\n\
+             \x20   n0 -->|2| n1\n\
+             \x20   n2 --> n1\n\
+             \x20   n2 --> n3\n\
+             \x20   classDef requirement fill:#dde4ee,stroke:#57606a"
+        );
+
+        // src/ and the file: no scopes below it.
+        let options = FormatOptions {
+            graph_depth: Some(3),
+            ..FormatOptions::default()
+        };
+        let out = format_mermaid(&options, &results());
+        assert!(out.contains("n1[\"src/output/mod.rs\"]"), "{out}");
+        assert!(out.contains("n3[\"src/lib.rs\"]"), "{out}");
+        assert!(!out.contains("-.-"), "{out}");
+
+        let options = FormatOptions {
+            graph_depth: Some(1),
+            ..FormatOptions::default()
+        };
+        let out = format_mermaid(&options, &results());
+        assert!(out.contains("n1[[\"src/\"]]"), "{out}");
+        assert!(out.contains("n0 -->|2| n1\n    n2 -->|2| n1\n"), "{out}");
+    }
+
+    #[test]
+    fn escapes_labels() {
+        assert_eq!(dot_escape("say \"hi\"\\"), "say \\\"hi\\\"\\\\");
+        assert_eq!(mermaid_escape("a<\"#\">"), "a#lt;#quot;#35;#quot;#gt;");
+    }
+}
@@ -0,0 +1,131 @@
+//! Syntax highlighting for the HTML report's source views, from the same
+//! tree-sitter grammars the scanner parses with.
+
+use super::html::escape;
+use ast_grep_language::{Language, LanguageExt, SupportLang};
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::Path;
+
+/// A kind of highlighted token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Comment,
+    String,
+    Keyword,
+    Number,
+}
+
+impl Token {
+    /// The CSS class of the token.
+    fn class(self) -> &'static str {
+        match self {
+            Token::Comment => "c",
+            Token::String => "s",
+            Token::Keyword => "k",
+            Token::Number => "n",
+        }
+    }
+}
+
+/// Each line of `source` as HTML, highlighted when tracy parses its language.
+pub(super) fn highlighted_lines(path: &Path, source: &str) -> Vec<String> {
+    let tokens = tokens(path, source);
+    let mut lines = Vec::new();
+    let mut next = 0;
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        let end = start + line.trim_end_matches(['\n', '\r']).len();
+        let mut html = String::new();
+        let mut at = start;
+        // Tokens spanning lines are closed at the end of each and reopened.
+        while let Some((range, token)) = tokens.get(next)
+            && range.start < end
+        {
+            let from = range.start.max(at);
+            let to = range.end.min(end);
+            html.push_str(&escape(&source[at..from]));
+            if from < to {
+                let _ = write!(
+                    html,
+                    "<span class=\"{}\">{}</span>",
+                    token.class(),
+                    escape(&source[from..to])
+                );
+            }
+            at = to.max(at);
+            if range.end > end {
+                break;
+            }
+            next += 1;
+        }
+        html.push_str(&escape(&source[at..end]));
+        lines.push(html);
+        start += line.len();
+    }
+    lines
+}
+
+/// The highlighted byte ranges of `source`, in order and not overlapping.
+fn tokens(path: &Path, source: &str) -> Vec<(Range<usize>, Token)> {
+    let Some(lang) = SupportLang::from_path(path) else {
+        return Vec::new();
+    };
+    let root = lang.ast_grep(source);
+    let mut tokens = Vec::new();
+    let mut covered = 0;
+    // Depth first is document order, so a comment or string covers the
+    // nodes inside it.
+    for node in root.root().dfs() {
+        let range = node.range();
+        if range.start < covered || range.is_empty() {
+            continue;
+        }
+        let kind = node.kind();
+        let token = if kind.contains("comment") {
+            Token::Comment
+        } else if kind.contains("string") || kind.contains("char_literal") {
+            Token::String
+        } else if !node.is_leaf() {
+            continue;
+        } else if ["integer", "float", "number"]
+            .iter()
+            .any(|number| kind.contains(number))
+        {
+            Token::Number
+        } else if !node.is_named() && node.text().chars().all(|c| c.is_alphabetic() || c == '_') {
+            Token::Keyword
+        } else {
+            continue;
+        };
+        covered = range.end;
+        tokens.push((range, token));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_comments_strings_and_keywords_per_line() {
+        let source = "/* REQ-1\n spans */\nfn main() { let s = \"a<b\"; }\n";
+        let lines = highlighted_lines(Path::new("lib.rs"), source);
+        assert_eq!(
+            lines,
+            vec![
+                "<span class=\"c\">/* REQ-1</span>",
+                "<span class=\"c\"> spans */</span>",
+                "<span class=\"k\">fn</span> main() { <span class=\"k\">let</span> s = \
+                 <span class=\"s\">&quot;a&lt;b&quot;</span>; }",
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_unparsed_languages() {
+        let lines = highlighted_lines(Path::new("notes.txt"), "a < b\r\nc");
+        assert_eq!(lines, vec!["a &lt; b", "c"]);
+    }
+}
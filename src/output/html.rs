@@ -0,0 +1,518 @@
+//! `--format html`: a self-contained traceability report that works offline.
+//!
+//! One file holds every page: the requirement index, a page per requirement
+//! with each reference's comment, context and blame, and a highlighted view
+//! of each file with its markers anchored. An inline script shows the page
+//! the URL fragment names and filters the index.
+
+use super::SourceFiles;
+use super::highlight::highlighted_lines;
+use crate::check::{Violation, slug_of};
+use crate::diff::DiffReport;
+use crate::git::{BlameInfo, GitMeta};
+use crate::scan::{CodeContext, Entry, ScanResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+const STYLE: &str = r#"
+body { font: 14px/1.5 system-ui, sans-serif; margin: 0 auto; max-width: 72rem; padding: 1rem 2rem; color: #1f2328; }
+a { color: #0969da; text-decoration: none; }
+a:hover { text-decoration: underline; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; vertical-align: top; padding: .25rem .5rem; border-bottom: 1px solid #d0d7de; }
+pre, code { font: 12px/1.45 ui-monospace, monospace; }
+pre.comment { background: #f6f8fa; padding: .5rem; white-space: pre-wrap; }
+.entry { border: 1px solid #d0d7de; border-radius: 6px; padding: 0 1rem; margin: 1rem 0; }
+.muted { color: #59636e; }
+.filters { display: flex; gap: .5rem; margin: 1rem 0; }
+.filters input { flex: 1; padding: .25rem .5rem; }
+table.code td { border: none; padding: 0 .5rem; white-space: pre; }
+table.code td.ln { text-align: right; user-select: none; }
+table.code td.ln a { color: #59636e; }
+tr.marker { background: #fff8c5; }
+.current { outline: 2px solid #0969da; }
+.c { color: #6e7781; font-style: italic; }
+.s { color: #0a3069; }
+.k { color: #cf222e; }
+.n { color: #0550ae; }
+.error { color: #cf222e; }
+.warning { color: #9a6700; }
+"#;
+
+const SCRIPT: &str = r#"
+const pages = document.querySelectorAll('.page');
+function show() {
+  const id = decodeURIComponent(location.hash.slice(1));
+  let target = id && document.getElementById(id);
+  let page = target && target.closest('.page');
+  if (!page) { page = document.getElementById('index'); target = null; }
+  pages.forEach(p => { p.hidden = p !== page; });
+  document.querySelectorAll('.current').forEach(e => e.classList.remove('current'));
+  if (target && target !== page) {
+    target.classList.add('current');
+    target.scrollIntoView({ block: 'center' });
+  } else {
+    window.scrollTo(0, 0);
+  }
+}
+const search = document.getElementById('search');
+const slug = document.getElementById('slug');
+function filter() {
+  const query = search.value.trim().toLowerCase();
+  let shown = 0;
+  document.querySelectorAll('#requirements tbody tr').forEach(row => {
+    const match = (!slug.value || row.dataset.slug === slug.value)
+      && (!query || row.dataset.search.includes(query));
+    row.hidden = !match;
+    if (match) shown++;
+  });
+  document.getElementById('shown').textContent = shown;
+}
+window.addEventListener('hashchange', show);
+search.addEventListener('input', filter);
+slug.addEventListener('change', filter);
+show();
+"#;
+
+pub(super) fn format_html(
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+    sources: &SourceFiles,
+) -> String {
+    let mut out = String::new();
+    let references: usize = results.values().map(Vec::len).sum();
+
+    head(&mut out, "Traceability");
+    let _ = writeln!(out, "<section class=\"page\" id=\"index\">");
+    let _ = writeln!(out, "<h1>Traceability</h1>");
+    let _ = writeln!(
+        out,
+        "<p>{} requirement(s), {references} reference(s)</p>",
+        results.len()
+    );
+    if let Some(meta) = meta {
+        commit(&mut out, meta);
+    }
+    requirement_index(&mut out, results, sources);
+    if !violations.is_empty() {
+        violation_table(&mut out, violations, sources);
+    }
+    let _ = writeln!(out, "</section>");
+
+    for (requirement_id, entries) in results {
+        requirement_page(&mut out, requirement_id, entries, sources);
+    }
+
+    // Markers by file and line, to anchor them in the source views.
+    let mut markers: BTreeMap<&Path, BTreeMap<usize, BTreeSet<&str>>> = BTreeMap::new();
+    for (requirement_id, entries) in results {
+        for entry in entries {
+            markers
+                .entry(&entry.file)
+                .or_default()
+                .entry(entry.line)
+                .or_default()
+                .insert(requirement_id);
+        }
+    }
+    for (file, markers) in markers {
+        if let Some(source) = sources.get(file) {
+            source_page(&mut out, file, source, &markers);
+        }
+    }
+
+    let _ = writeln!(out, "<script>{SCRIPT}</script>");
+    let _ = write!(out, "</body>\n</html>");
+    out
+}
+
+pub(super) fn format_diff_html(report: &DiffReport) -> String {
+    let mut out = String::new();
+    let head_name = report.head.as_deref().unwrap_or("working tree");
+    let title = format!("Trace link changes ({}..{head_name})", report.base);
+
+    head(&mut out, &title);
+    let _ = writeln!(out, "<h1>{}</h1>", escape(&title));
+    if report.changes.is_empty() {
+        let _ = writeln!(out, "<p>No trace link changes.</p>");
+    } else {
+        let _ = writeln!(
+            out,
+            "<table>\n<thead><tr><th>Change</th><th>Requirement</th><th>Base</th><th>Head</th></tr></thead>\n<tbody>"
+        );
+        for change in &report.changes {
+            let side = |entry: Option<&Entry>| entry.map(external_location).unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                change.kind.as_str().replace('_', " "),
+                escape(&change.requirement_id),
+                side(change.base.as_ref()),
+                side(change.head.as_ref())
+            );
+        }
+        let _ = writeln!(out, "</tbody>\n</table>");
+    }
+    let _ = write!(out, "</body>\n</html>");
+    out
+}
+
+fn head(out: &mut String, title: &str) {
+    let _ = writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>");
+    let _ = writeln!(out, "<meta charset=\"utf-8\">");
+    let _ = writeln!(
+        out,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    );
+    let _ = writeln!(out, "<title>{}</title>", escape(title));
+    let _ = writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>");
+}
+
+fn commit(out: &mut String, meta: &GitMeta) {
+    let dirty = if meta.is_dirty { " (dirty)" } else { "" };
+    let _ = write!(
+        out,
+        "<p class=\"muted\">Commit <code>{}</code> (<code>{}</code>){dirty}",
+        escape(&meta.head_sha),
+        escape(&meta.describe)
+    );
+    if let Some(head_ref) = &meta.head_ref {
+        let _ = write!(out, " on <code>{}</code>", escape(head_ref));
+    }
+    let _ = writeln!(out, "</p>");
+}
+
+fn requirement_index(out: &mut String, results: &ScanResult, sources: &SourceFiles) {
+    let slugs: BTreeSet<&str> = results.keys().map(|id| slug_of(id)).collect();
+    let _ = writeln!(out, "<div class=\"filters\">");
+    let _ = writeln!(
+        out,
+        "<input id=\"search\" type=\"search\" placeholder=\"Search requirements, files and comments\">"
+    );
+    let _ = writeln!(
+        out,
+        "<select id=\"slug\"><option value=\"\">All slugs</option>"
+    );
+    for slug in slugs {
+        let _ = writeln!(out, "<option>{}</option>", escape(slug));
+    }
+    let _ = writeln!(out, "</select>\n</div>");
+    let _ = writeln!(
+        out,
+        "<p class=\"muted\"><span id=\"shown\">{}</span> shown</p>",
+        results.len()
+    );
+
+    let _ = writeln!(
+        out,
+        "<table id=\"requirements\">\n<thead><tr><th>Requirement</th><th>References</th><th>Files</th></tr></thead>\n<tbody>"
+    );
+    for (requirement_id, entries) in results {
+        let files: BTreeSet<&Path> = entries.iter().map(|e| e.file.as_path()).collect();
+        let mut search = requirement_id.to_string();
+        for entry in entries {
+            let _ = write!(search, " {} {}", entry.file.display(), entry.comment_text);
+        }
+        let files: Vec<String> = files
+            .into_iter()
+            .map(|file| {
+                let name = escape(&file.display().to_string());
+                match sources.contains_key(file) {
+                    true => format!("<a href=\"#{}\">{name}</a>", fragment(&file_id(file))),
+                    false => name,
+                }
+            })
+            .collect();
+        let _ = writeln!(
+            out,
+            "<tr data-slug=\"{}\" data-search=\"{}\"><td><a href=\"#{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+            escape(slug_of(requirement_id)),
+            escape(
+                &search
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase()
+            ),
+            fragment(&requirement_id_page(requirement_id)),
+            escape(requirement_id),
+            entries.len(),
+            files.join(", ")
+        );
+    }
+    let _ = writeln!(out, "</tbody>\n</table>");
+}
+
+fn violation_table(out: &mut String, violations: &[Violation], sources: &SourceFiles) {
+    let _ = writeln!(out, "<h2>Violations</h2>");
+    let _ = writeln!(
+        out,
+        "<table>\n<thead><tr><th>Severity</th><th>Rule</th><th>Location</th><th>Message</th></tr></thead>\n<tbody>"
+    );
+    for violation in violations {
+        let severity = violation.severity.as_str();
+        let _ = writeln!(
+            out,
+            "<tr><td class=\"{severity}\">{severity}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+            violation.rule.id(),
+            location(&violation.file, violation.line, sources),
+            escape(&violation.message)
+        );
+    }
+    let _ = writeln!(out, "</tbody>\n</table>");
+}
+
+fn requirement_page(
+    out: &mut String,
+    requirement_id: &str,
+    entries: &[Entry],
+    sources: &SourceFiles,
+) {
+    let _ = writeln!(
+        out,
+        "<section class=\"page\" id=\"{}\" hidden>",
+        escape(&requirement_id_page(requirement_id))
+    );
+    let _ = writeln!(out, "<p><a href=\"#index\">&larr; Index</a></p>");
+    let _ = writeln!(out, "<h1>{}</h1>", escape(requirement_id));
+    let _ = writeln!(out, "<p>{} reference(s)</p>", entries.len());
+
+    for entry in entries {
+        let _ = writeln!(out, "<article class=\"entry\">");
+        let _ = write!(out, "<h3>{}", location(&entry.file, entry.line, sources));
+        if let Some(url) = &entry.url {
+            let _ = write!(
+                out,
+                " <a class=\"muted\" href=\"{}\">permalink</a>",
+                escape(url)
+            );
+        }
+        let _ = writeln!(out, "</h3>");
+        let _ = writeln!(
+            out,
+            "<pre class=\"comment\">{}</pre>",
+            escape(entry.comment_text.trim_end())
+        );
+
+        let mut details = Vec::new();
+        for (name, context) in [
+            ("Above", &entry.above),
+            ("Below", &entry.below),
+            ("Inline", &entry.inline),
+        ] {
+            if let Some(context) = context {
+                details.push((name, code_context(context)));
+            }
+        }
+        if !entry.scope.is_empty() {
+            details.push((
+                "Scope",
+                format!("<code>{}</code>", escape(&entry.scope_path())),
+            ));
+        }
+        if let Some(blame) = &entry.blame {
+            details.push(("Blame", blame_info(blame)));
+        }
+        if let Some(root) = &entry.root {
+            details.push(("Root", escape(root)));
+        }
+        if let Some(component) = &entry.component {
+            details.push((
+                "Component",
+                format!("<code>{}</code>", escape(&component.display().to_string())),
+            ));
+        }
+        if let Some(submodule) = &entry.submodule {
+            details.push((
+                "Submodule",
+                format!(
+                    "<code>{}</code> at <code>{}</code>",
+                    escape(&submodule.path.display().to_string()),
+                    escape(&submodule.commit)
+                ),
+            ));
+        }
+        if !details.is_empty() {
+            let _ = writeln!(out, "<dl>");
+            for (name, value) in details {
+                let _ = writeln!(out, "<dt>{name}</dt><dd>{value}</dd>");
+            }
+            let _ = writeln!(out, "</dl>");
+        }
+        let _ = writeln!(out, "</article>");
+    }
+    let _ = writeln!(out, "</section>");
+}
+
+fn source_page(
+    out: &mut String,
+    file: &Path,
+    source: &str,
+    markers: &BTreeMap<usize, BTreeSet<&str>>,
+) {
+    let id = file_id(file);
+    let _ = writeln!(
+        out,
+        "<section class=\"page\" id=\"{}\" hidden>",
+        escape(&id)
+    );
+    let _ = writeln!(out, "<p><a href=\"#index\">&larr; Index</a></p>");
+    let _ = writeln!(
+        out,
+        "<h1><code>{}</code></h1>",
+        escape(&file.display().to_string())
+    );
+    let _ = writeln!(out, "<table class=\"code\">");
+    for (index, html) in highlighted_lines(file, source).iter().enumerate() {
+        let line = index + 1;
+        let line_id = format!("{id}:{line}");
+        let (class, links) = match markers.get(&line) {
+            Some(ids) => {
+                let links: Vec<String> = ids
+                    .iter()
+                    .map(|id| {
+                        format!(
+                            "<a href=\"#{}\">{}</a>",
+                            fragment(&requirement_id_page(id)),
+                            escape(id)
+                        )
+                    })
+                    .collect();
+                (" class=\"marker\"", links.join(" "))
+            }
+            None => ("", String::new()),
+        };
+        let _ = writeln!(
+            out,
+            "<tr id=\"{}\"{class}><td class=\"ln\"><a href=\"#{}\">{line}</a></td><td>{links}</td><td><code>{html}</code></td></tr>",
+            escape(&line_id),
+            fragment(&line_id)
+        );
+    }
+    let _ = writeln!(out, "</table>\n</section>");
+}
+
+fn code_context(context: &CodeContext) -> String {
+    let name = context
+        .name
+        .as_ref()
+        .map(|name| format!(" <code>{}</code>", escape(name)))
+        .unwrap_or_default();
+    format!(
+        "<code>{}</code> <span class=\"muted\">({}{name}, line {})</span>",
+        escape(&context.text),
+        escape(&context.kind),
+        context.line
+    )
+}
+
+fn blame_info(blame: &BlameInfo) -> String {
+    let mut html = format!(
+        "<code>{}</code>",
+        escape(&blame.commit[..blame.commit.len().min(12)])
+    );
+    if let Some(author) = &blame.author {
+        let _ = write!(html, " by {}", escape(author));
+    }
+    if let Some(mail) = &blame.author_mail {
+        let _ = write!(html, " &lt;{}&gt;", escape(mail.trim_matches(['<', '>'])));
+    }
+    if let Some(time) = blame.author_time {
+        let _ = write!(html, " on {}", date(time));
+    }
+    if let Some(summary) = &blame.summary {
+        let _ = write!(html, ": {}", escape(summary));
+    }
+    html
+}
+
+/// A `file:line` link to the line's source view, or plain text when the
+/// report has no view of the file.
+fn location(file: &Path, line: usize, sources: &SourceFiles) -> String {
+    let text = escape(&format!("{}:{line}", file.display()));
+    if sources.contains_key(file) {
+        let id = format!("{}:{line}", file_id(file));
+        format!("<a href=\"#{}\"><code>{text}</code></a>", fragment(&id))
+    } else {
+        format!("<code>{text}</code>")
+    }
+}
+
+/// A `file:line` linking to the entry's permalink, if it has one.
+fn external_location(entry: &Entry) -> String {
+    let text = format!(
+        "<code>{}</code>",
+        escape(&format!("{}:{}", entry.file.display(), entry.line))
+    );
+    match &entry.url {
+        Some(url) => format!("<a href=\"{}\">{text}</a>", escape(url)),
+        None => text,
+    }
+}
+
+fn requirement_id_page(requirement_id: &str) -> String {
+    format!("req:{requirement_id}")
+}
+
+fn file_id(file: &Path) -> String {
+    format!("file:{}", file.display())
+}
+
+/// An element id as a URL fragment.
+fn fragment(id: &str) -> String {
+    let mut out = String::new();
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/:".contains(&byte) {
+            out.push(byte as char);
+        } else {
+            let _ = write!(out, "%{byte:02X}");
+        }
+    }
+    out
+}
+
+/// Escape text for HTML content and attribute values.
+pub(super) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A unix timestamp as a UTC `YYYY-MM-DD` date.
+pub(super) fn date(timestamp: i64) -> String {
+    // Days to civil date, after Howard Hinnant's `civil_from_days`.
+    let z = timestamp.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_dates_and_fragments() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(1_700_000_000), "2023-11-14");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(fragment("file:src/a b#.rs:3"), "file:src/a%20b%23.rs:3");
+    }
+}
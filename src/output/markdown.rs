@@ -0,0 +1,288 @@
+use super::FormatOptions;
+use crate::check::slug_of;
+use crate::diff::{ChangeKind, DiffReport};
+use crate::git::GitMeta;
+use crate::scan::{Entry, ScanResult};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Locations listed per requirement before the rest are counted.
+const LOCATIONS_PER_REQUIREMENT: usize = 10;
+
+pub(super) fn format_markdown(
+    options: &FormatOptions,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+) -> String {
+    let mut out = String::new();
+    let references: usize = results.values().map(Vec::len).sum();
+
+    let _ = writeln!(out, "## Traceability");
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "{} requirement(s), {references} reference(s)",
+        results.len()
+    );
+    if let Some(meta) = meta {
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "- Commit: `{}` (`{}`){}",
+            meta.head_sha,
+            meta.describe,
+            dirty_suffix(meta)
+        );
+        if !meta.tags.is_empty() {
+            let _ = writeln!(out, "- Tags: {}", code_list(&meta.tags));
+        }
+        for remote in &meta.remotes {
+            let _ = writeln!(out, "- Remote `{}`: {}", remote.name, remote.url);
+        }
+        if !meta.dirty_files.is_empty() {
+            let files: Vec<_> = meta.dirty_files.iter().map(|f| f.display()).collect();
+            let _ = writeln!(out, "- Dirty files: {}", code_list(&files));
+        }
+        for submodule in &meta.submodules {
+            let _ = writeln!(
+                out,
+                "- Submodule `{}`: `{}` (`{}`){}",
+                submodule.path.display(),
+                submodule.meta.head_sha,
+                submodule.meta.describe,
+                dirty_suffix(&submodule.meta)
+            );
+        }
+        for root in &meta.roots {
+            let _ = writeln!(
+                out,
+                "- Root `{}`: `{}` (`{}`){}",
+                root.label,
+                root.meta.head_sha,
+                root.meta.describe,
+                dirty_suffix(&root.meta)
+            );
+        }
+    }
+
+    if results.is_empty() {
+        return out.trim_end().to_string();
+    }
+
+    let mut slugs: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for (requirement_id, entries) in results {
+        let totals = slugs.entry(slug_of(requirement_id)).or_default();
+        totals.0 += 1;
+        totals.1 += entries.len();
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Slug | Requirements | References |");
+    let _ = writeln!(out, "| --- | --- | --- |");
+    for (slug, (requirements, references)) in &slugs {
+        let _ = writeln!(out, "| {slug} | {requirements} | {references} |");
+    }
+
+    let _ = writeln!(out);
+    let _ = writeln!(out, "<details>");
+    let _ = writeln!(out, "<summary>Requirements ({})</summary>", results.len());
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Requirement | References | Locations |");
+    let _ = writeln!(out, "| --- | --- | --- |");
+    let rows = results.iter().map(|(requirement_id, entries)| {
+        let mut locations: Vec<_> = entries
+            .iter()
+            .take(LOCATIONS_PER_REQUIREMENT)
+            .map(location)
+            .collect();
+        if entries.len() > LOCATIONS_PER_REQUIREMENT {
+            locations.push(format!(
+                "and {} more",
+                entries.len() - LOCATIONS_PER_REQUIREMENT
+            ));
+        }
+        format!(
+            "| {requirement_id} | {} | {} |\n",
+            entries.len(),
+            locations.join(", ")
+        )
+    });
+    push_rows(
+        &mut out,
+        rows,
+        "\n</details>",
+        options.markdown_limit,
+        "requirement(s)",
+    );
+
+    out
+}
+
+pub(super) fn format_diff_markdown(options: &FormatOptions, report: &DiffReport) -> String {
+    let mut out = String::new();
+
+    let head = report.head.as_deref().unwrap_or("working tree");
+    let _ = writeln!(out, "## Trace link changes (`{}`..`{head}`)", report.base);
+    let _ = writeln!(out);
+
+    if report.changes.is_empty() {
+        let _ = writeln!(out, "No trace link changes.");
+        return out.trim_end().to_string();
+    }
+
+    let count = |kind: ChangeKind| report.changes.iter().filter(|c| c.kind == kind).count();
+    let _ = writeln!(
+        out,
+        "{} added, {} removed, {} moved, {} changed text",
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Moved),
+        count(ChangeKind::ChangedText)
+    );
+    let _ = writeln!(out);
+    let _ = writeln!(out, "| Change | Requirement | Base | Head |");
+    let _ = writeln!(out, "| --- | --- | --- | --- |");
+    let rows = report.changes.iter().map(|change| {
+        format!(
+            "| {} | {} | {} | {} |\n",
+            change.kind.as_str().replace('_', " "),
+            change.requirement_id,
+            change.base.as_ref().map(location).unwrap_or_default(),
+            change.head.as_ref().map(location).unwrap_or_default()
+        )
+    });
+    push_rows(&mut out, rows, "", options.markdown_limit, "change(s)");
+
+    out.trim_end().to_string()
+}
+
+/// Append table `rows` to `out`, then `closing`. With a `limit`, only the
+/// rows that keep the whole within `limit` bytes are kept, followed by a
+/// note of how many were omitted.
+fn push_rows(
+    out: &mut String,
+    rows: impl ExactSizeIterator<Item = String>,
+    closing: &str,
+    limit: usize,
+    what: &str,
+) {
+    let total = rows.len();
+    let note = |omitted: usize| {
+        format!("\n_{omitted} of {total} {what} omitted to stay within {limit} bytes._\n")
+    };
+    // Room for the note with the most digits it can have.
+    let budget = limit.saturating_sub(closing.len() + note(total).len());
+    let mut shown = 0;
+    for row in rows {
+        if limit > 0 && out.len() + row.len() > budget {
+            break;
+        }
+        out.push_str(&row);
+        shown += 1;
+    }
+    if shown < total {
+        out.push_str(&note(total - shown));
+    }
+    out.push_str(closing);
+}
+
+fn dirty_suffix(meta: &GitMeta) -> &'static str {
+    if meta.is_dirty { " (dirty)" } else { "" }
+}
+
+fn code_list(items: &[impl std::fmt::Display]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{item}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn location(entry: &Entry) -> String {
+    let location = format!("`{}:{}`", entry.file.display(), entry.line);
+    match &entry.url {
+        Some(url) => format!("[{location}]({url})"),
+        None => location,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn results(requirements: &[(&str, usize)]) -> ScanResult {
+        requirements
+            .iter()
+            .map(|&(requirement_id, references)| {
+                let entries = (1..=references)
+                    .map(|line| Entry {
+                        file: PathBuf::from("src/lib.rs"),
+                        line,
+                        comment_text: format!("// {requirement_id}"),
+                        above: None,
+                        below: None,
+                        inline: None,
+                        scope: Vec::new(),
+                        blame: None,
+                        url: None,
+                        submodule: None,
+                        root: None,
+                        component: None,
+                    })
+                    .collect();
+                (requirement_id.to_string(), entries)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summarizes_per_slug_with_collapsed_locations() {
+        let results = results(&[("LIN-1", 1), ("REQ-1", 12), ("REQ-2", 1)]);
+        let out = format_markdown(&FormatOptions::default(), None, &results);
+
+        assert!(out.starts_with("## Traceability\n\n3 requirement(s), 14 reference(s)\n"));
+        assert!(out.contains(
+            "| Slug | Requirements | References |\n\
+             | --- | --- | --- |\n\
+             | LIN | 1 | 1 |\n\
+             | REQ | 2 | 13 |\n"
+        ));
+        assert!(out.contains("<details>\n<summary>Requirements (3)</summary>\n"));
+        assert!(out.contains("| REQ-2 | 1 | `src/lib.rs:1` |\n"));
+        assert!(out.contains("`src/lib.rs:10`, and 2 more |\n"), "{out}");
+        assert!(out.ends_with("\n</details>"));
+    }
+
+    #[test]
+    fn truncates_rows_to_the_limit_and_says_how_many() {
+        let ids: Vec<_> = (1..=200).map(|n| format!("REQ-{n}")).collect();
+        let results = results(&ids.iter().map(|id| (id.as_str(), 1)).collect::<Vec<_>>());
+        let options = FormatOptions {
+            markdown_limit: 2_000,
+            ..FormatOptions::default()
+        };
+        let out = format_markdown(&options, None, &results);
+
+        assert!(out.len() <= 2_000, "{}", out.len());
+        let shown = out.lines().filter(|l| l.starts_with("| REQ-")).count();
+        assert!(shown > 0 && shown < 200);
+        assert!(out.contains(&format!(
+            "_{} of 200 requirement(s) omitted to stay within 2000 bytes._\n\n</details>",
+            200 - shown
+        )));
+
+        let options = FormatOptions {
+            markdown_limit: 0,
+            ..FormatOptions::default()
+        };
+        let unlimited = format_markdown(&options, None, &results);
+        assert_eq!(
+            unlimited
+                .lines()
+                .filter(|l| l.starts_with("| REQ-"))
+                .count(),
+            200
+        );
+        assert!(!unlimited.contains("omitted"));
+    }
+}
@@ -0,0 +1,992 @@
+mod ci;
+mod envelope;
+mod error;
+mod graph;
+mod highlight;
+mod html;
+mod markdown;
+mod sarif;
+mod spreadsheet;
+mod summary;
+mod template;
+
+pub use envelope::SCHEMA_VERSION;
+pub use error::OutputError;
+pub use graph::GraphCollapse;
+pub use template::TemplateSource;
+
+use crate::check::{Rule, Violation, slug_of};
+use crate::diff::DiffReport;
+use crate::git::GitMeta;
+use crate::scan::{Entry, ScanResult};
+use ci::{
+    format_checkstyle, format_codequality, format_diff_checkstyle, format_diff_codequality,
+    format_diff_junit, format_junit,
+};
+use clap::ValueEnum;
+use envelope::{format_diff_envelope, format_envelope};
+use graph::{format_diff_dot, format_diff_mermaid, format_dot, format_mermaid};
+use html::{format_diff_html, format_html};
+use markdown::{format_diff_markdown, format_markdown};
+use sarif::{format_diff_sarif, format_sarif};
+use serde::{Deserialize, Serialize};
+use spreadsheet::{diff_sheets, scan_sheets, write_ods, write_xlsx};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use template::{format_diff_template, format_template};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Json,
+    Jsonl,
+    Csv,
+    Sarif,
+    Markdown,
+    Html,
+    Xlsx,
+    Ods,
+    Dot,
+    Mermaid,
+    Template,
+    Envelope,
+    Codequality,
+    Checkstyle,
+    Junit,
+}
+
+impl OutputFormat {
+    /// Whether the format shows the scanned files, which are then read into
+    /// [`SourceFiles`].
+    pub fn shows_sources(self) -> bool {
+        matches!(self, OutputFormat::Html | OutputFormat::Sarif)
+    }
+
+    /// Whether the format is a binary file rather than text.
+    pub fn is_binary(self) -> bool {
+        matches!(self, OutputFormat::Xlsx | OutputFormat::Ods)
+    }
+}
+
+/// The default `markdown_limit`: GitHub's limit on a comment body. Job
+/// summaries allow up to 1 MiB.
+pub const DEFAULT_MARKDOWN_LIMIT: usize = 65_536;
+
+/// Settings that shape a format's output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// The most bytes `markdown` output may take, or 0 for no limit
+    pub markdown_limit: usize,
+    /// What `dot` and `mermaid` merge code nodes into
+    pub graph_collapse: Option<GraphCollapse>,
+    /// The levels of directories, file and scopes `dot` and `mermaid` show
+    pub graph_depth: Option<usize>,
+    /// What the `template` format renders through
+    pub template: Option<TemplateSource>,
+    /// Link for each requirement, with `{id}` and `{slug}` replaced
+    pub requirement_url: Option<String>,
+    /// The effective configuration, reported by `envelope`
+    pub config: toml::Table,
+    /// The command line tracy was run with, reported by `sarif`
+    pub command_line: Vec<String>,
+    /// The directories of labeled scan roots, which `sarif` locations are
+    /// relative to
+    pub roots: BTreeMap<String, PathBuf>,
+    /// The checks the run applies, listed as rules by `sarif` whether or not
+    /// they found anything
+    pub rules: Vec<Rule>,
+    /// Whether the run looks for violations (`check` and `lint`), so `json`
+    /// reports them, if only as an empty list
+    pub checking: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            markdown_limit: DEFAULT_MARKDOWN_LIMIT,
+            graph_collapse: None,
+            graph_depth: None,
+            template: None,
+            requirement_url: None,
+            config: toml::Table::new(),
+            command_line: Vec::new(),
+            roots: BTreeMap::new(),
+            rules: Vec::new(),
+            checking: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// The link of a requirement.
+    pub fn requirement_url(&self, id: &str) -> Option<String> {
+        self.expand_requirement_url(id, slug_of(id))
+    }
+
+    /// The link of a slug's requirements as a whole, if the requirement link
+    /// is the same for all of them: one without `{id}`.
+    pub fn slug_url(&self, slug: &str) -> Option<String> {
+        let template = self.requirement_url.as_ref()?;
+        (!template.contains("{id}")).then(|| template.replace("{slug}", slug))
+    }
+
+    fn expand_requirement_url(&self, id: &str, slug: &str) -> Option<String> {
+        let template = self.requirement_url.as_ref()?;
+        Some(template.replace("{id}", id).replace("{slug}", slug))
+    }
+}
+
+/// Formatted output: text, or the bytes of a binary format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Output {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Output::Text(text) => text.as_bytes(),
+            Output::Binary(bytes) => bytes,
+        }
+    }
+}
+
+impl From<String> for Output {
+    fn from(text: String) -> Self {
+        Output::Text(text)
+    }
+}
+
+/// The text of the files with entries, by their path as reported.
+pub type SourceFiles = BTreeMap<PathBuf, String>;
+
+pub fn format_output(
+    format: OutputFormat,
+    options: &FormatOptions,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+    sources: &SourceFiles,
+) -> Result<Output, OutputError> {
+    let text = match format {
+        OutputFormat::Json => format_json(options, meta, results, violations)?,
+        OutputFormat::Jsonl => format_jsonl(meta, results, violations)?,
+        OutputFormat::Csv => format_csv(meta, results),
+        OutputFormat::Sarif => format_sarif(options, meta, results, violations, sources)?,
+        OutputFormat::Markdown => format_markdown(options, meta, results),
+        OutputFormat::Html => format_html(meta, results, violations, sources),
+        OutputFormat::Dot => format_dot(options, results),
+        OutputFormat::Mermaid => format_mermaid(options, results),
+        OutputFormat::Template => {
+            format_template(options.template.as_ref(), meta, results, violations)?
+        }
+        OutputFormat::Envelope => format_envelope(&options.config, meta, results, violations)?,
+        OutputFormat::Codequality => format_codequality(results, violations)?,
+        OutputFormat::Checkstyle => format_checkstyle(results, violations),
+        OutputFormat::Junit => format_junit(options, results, violations),
+        OutputFormat::Xlsx => {
+            return Ok(Output::Binary(write_xlsx(&scan_sheets(
+                meta, results, violations,
+            ))?));
+        }
+        OutputFormat::Ods => {
+            return Ok(Output::Binary(write_ods(&scan_sheets(
+                meta, results, violations,
+            ))?));
+        }
+    };
+    Ok(Output::Text(text))
+}
+
+pub fn format_diff(
+    format: OutputFormat,
+    options: &FormatOptions,
+    report: &DiffReport,
+) -> Result<Output, OutputError> {
+    let text = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(report)?,
+        OutputFormat::Jsonl => format_diff_jsonl(report)?,
+        OutputFormat::Csv => format_diff_csv(report),
+        OutputFormat::Sarif => format_diff_sarif(options, report)?,
+        OutputFormat::Markdown => format_diff_markdown(options, report),
+        OutputFormat::Html => format_diff_html(report),
+        OutputFormat::Dot => format_diff_dot(options, report),
+        OutputFormat::Mermaid => format_diff_mermaid(options, report),
+        OutputFormat::Template => format_diff_template(options.template.as_ref(), report)?,
+        OutputFormat::Envelope => format_diff_envelope(&options.config, report)?,
+        OutputFormat::Codequality => format_diff_codequality(report)?,
+        OutputFormat::Checkstyle => format_diff_checkstyle(report),
+        OutputFormat::Junit => format_diff_junit(options, report),
+        OutputFormat::Xlsx => return Ok(Output::Binary(write_xlsx(&diff_sheets(report))?)),
+        OutputFormat::Ods => return Ok(Output::Binary(write_ods(&diff_sheets(report))?)),
+    };
+    Ok(Output::Text(text))
+}
+
+/// The results alone, or wrapped with `meta` when there is metadata, and
+/// always with `violations` when the run looks for them, so a check has the
+/// same shape whether or not it finds any.
+fn format_json(
+    options: &FormatOptions,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct JsonReport<'a> {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        meta: Option<&'a GitMeta>,
+        results: &'a ScanResult,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        violations: Option<&'a [Violation]>,
+    }
+
+    let violations = (options.checking || !violations.is_empty()).then_some(violations);
+    if meta.is_none() && violations.is_none() {
+        return serde_json::to_string_pretty(results);
+    }
+
+    serde_json::to_string_pretty(&JsonReport {
+        meta,
+        results,
+        violations,
+    })
+}
+
+fn format_jsonl(
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct JsonlMeta<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        meta: &'a GitMeta,
+    }
+
+    #[derive(Serialize)]
+    struct JsonlMatch<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        requirement_id: &'a str,
+        entry: &'a Entry,
+    }
+
+    #[derive(Serialize)]
+    struct JsonlViolation<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        violation: &'a Violation,
+    }
+
+    let mut lines = Vec::new();
+
+    if let Some(meta) = meta {
+        lines.push(serde_json::to_string(&JsonlMeta { kind: "meta", meta })?);
+    }
+
+    for (requirement_id, entries) in results {
+        for entry in entries {
+            lines.push(serde_json::to_string(&JsonlMatch {
+                kind: "match",
+                requirement_id,
+                entry,
+            })?);
+        }
+    }
+
+    for violation in violations {
+        lines.push(serde_json::to_string(&JsonlViolation {
+            kind: "violation",
+            violation,
+        })?);
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// A list as a JSON cell, or an empty cell when there is nothing in it.
+fn json_list<T: Serialize>(items: &[T]) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        serde_json::to_string(items).unwrap_or_default()
+    }
+}
+
+fn format_csv(meta: Option<&GitMeta>, results: &ScanResult) -> String {
+    let mut lines = Vec::new();
+
+    let mut header = vec![
+        "requirement_id",
+        "file",
+        "line",
+        "comment_text",
+        "above",
+        "below",
+        "inline",
+        "scope",
+        "blame",
+    ];
+    // Only with --include-permalinks, so the default columns stay put.
+    let urls = results.values().flatten().any(|e| e.url.is_some());
+    if urls {
+        header.push("url");
+    }
+    let submodules = results.values().flatten().any(|e| e.submodule.is_some());
+    if submodules {
+        header.push("submodule");
+    }
+    let roots = results.values().flatten().any(|e| e.root.is_some());
+    if roots {
+        header.push("root");
+    }
+    let components = results.values().flatten().any(|e| e.component.is_some());
+    if components {
+        header.push("component");
+    }
+    if meta.is_some() {
+        header.extend([
+            "repo_root",
+            "head_sha",
+            "head_ref",
+            "is_dirty",
+            "describe",
+            "tags",
+            "author_time",
+            "commit_time",
+            "remotes",
+            "dirty_files",
+            "submodules",
+            "roots",
+        ]);
+    }
+    lines.push(header.join(","));
+
+    for (requirement_id, entries) in results {
+        for entry in entries {
+            let above = entry
+                .above
+                .as_ref()
+                .map(|c| serde_json::to_string(c).unwrap_or_default())
+                .unwrap_or_default();
+            let below = entry
+                .below
+                .as_ref()
+                .map(|c| serde_json::to_string(c).unwrap_or_default())
+                .unwrap_or_default();
+            let inline = entry
+                .inline
+                .as_ref()
+                .map(|c| serde_json::to_string(c).unwrap_or_default())
+                .unwrap_or_default();
+            let scope = json_list(&entry.scope);
+            let blame = entry
+                .blame
+                .as_ref()
+                .map(|b| serde_json::to_string(b).unwrap_or_default())
+                .unwrap_or_default();
+
+            let mut row = vec![
+                requirement_id.to_string(),
+                entry.file.display().to_string(),
+                entry.line.to_string(),
+                entry.comment_text.clone(),
+                above,
+                below,
+                inline,
+                scope,
+                blame,
+            ];
+
+            if urls {
+                row.push(entry.url.clone().unwrap_or_default());
+            }
+            if submodules {
+                row.push(
+                    entry
+                        .submodule
+                        .as_ref()
+                        .map(|s| serde_json::to_string(s).unwrap_or_default())
+                        .unwrap_or_default(),
+                );
+            }
+            if roots {
+                row.push(entry.root.clone().unwrap_or_default());
+            }
+            if components {
+                row.push(
+                    entry
+                        .component
+                        .as_ref()
+                        .map(|c| c.display().to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            if let Some(meta) = meta {
+                row.push(meta.repo_root.display().to_string());
+                row.push(meta.head_sha.clone());
+                row.push(meta.head_ref.clone().unwrap_or_default());
+                row.push(meta.is_dirty.to_string());
+                row.push(meta.describe.clone());
+                row.push(json_list(&meta.tags));
+                row.push(meta.author_time.to_string());
+                row.push(meta.commit_time.to_string());
+                row.push(json_list(&meta.remotes));
+                row.push(json_list(&meta.dirty_files));
+                row.push(json_list(&meta.submodules));
+                row.push(json_list(&meta.roots));
+            }
+
+            lines.push(
+                row.iter()
+                    .map(|v| csv_escape(v))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn csv_escape(value: &str) -> String {
+    let needs_quotes = value.chars().any(|c| matches!(c, ',' | '"' | '\n' | '\r'));
+    if !needs_quotes {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+fn format_diff_jsonl(report: &DiffReport) -> Result<String, serde_json::Error> {
+    #[derive(Serialize)]
+    struct JsonlDiff<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        base: &'a str,
+        base_sha: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        head: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        head_sha: Option<&'a str>,
+    }
+
+    #[derive(Serialize)]
+    struct JsonlChange<'a> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        #[serde(flatten)]
+        change: &'a crate::diff::Change,
+    }
+
+    let mut lines = vec![serde_json::to_string(&JsonlDiff {
+        kind: "diff",
+        base: &report.base,
+        base_sha: &report.base_sha,
+        head: report.head.as_deref(),
+        head_sha: report.head_sha.as_deref(),
+    })?];
+
+    for change in &report.changes {
+        lines.push(serde_json::to_string(&JsonlChange {
+            kind: "change",
+            change,
+        })?);
+    }
+
+    Ok(lines.join("\n"))
+}
+
+fn format_diff_csv(report: &DiffReport) -> String {
+    let mut lines = vec![
+        "change,requirement_id,base_file,base_line,head_file,head_line,base_comment_text,head_comment_text"
+            .to_string(),
+    ];
+
+    for change in &report.changes {
+        let file = |e: Option<&Entry>| e.map(|e| e.file.display().to_string()).unwrap_or_default();
+        let line = |e: Option<&Entry>| e.map(|e| e.line.to_string()).unwrap_or_default();
+        let text = |e: Option<&Entry>| e.map(|e| e.comment_text.clone()).unwrap_or_default();
+        let (base, head) = (change.base.as_ref(), change.head.as_ref());
+
+        let row = [
+            change.kind.as_str().to_string(),
+            change.requirement_id.clone(),
+            file(base),
+            line(base),
+            file(head),
+            line(head),
+            text(base),
+            text(head),
+        ];
+        lines.push(
+            row.iter()
+                .map(|v| csv_escape(v))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::{Rule, Severity};
+    use crate::git::GitRemote;
+    use crate::scan::ScopeItem;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    /// The text of a text format's output.
+    fn text(output: Result<Output, OutputError>) -> String {
+        match output.unwrap() {
+            Output::Text(text) => text,
+            Output::Binary(_) => panic!("binary output"),
+        }
+    }
+
+    fn one_result() -> ScanResult {
+        let mut results: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+        results.insert(
+            "REQ-1".to_string(),
+            vec![Entry {
+                file: PathBuf::from("src/lib.rs"),
+                line: 1,
+                comment_text: "// REQ-1: validate input".to_string(),
+                above: None,
+                below: None,
+                inline: None,
+                scope: Vec::new(),
+                blame: None,
+                url: None,
+                submodule: None,
+                root: None,
+                component: None,
+            }],
+        );
+        results
+    }
+
+    fn git_meta(is_dirty: bool) -> GitMeta {
+        GitMeta {
+            repo_root: PathBuf::from("/repo"),
+            head_sha: "a".repeat(40),
+            head_ref: Some("main".to_string()),
+            is_dirty,
+            describe: "v1.0-2-gaaaaaaa".to_string(),
+            tags: vec!["v1.0.1".to_string()],
+            author_time: 1700000000,
+            commit_time: 1700000100,
+            remotes: vec![GitRemote {
+                name: "origin".to_string(),
+                url: "https://***@example.com/repo.git".to_string(),
+            }],
+            dirty_files: if is_dirty {
+                vec![PathBuf::from("src/lib.rs")]
+            } else {
+                Vec::new()
+            },
+            submodules: Vec::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn json_without_meta_is_plain_results() {
+        let results = one_result();
+        let out = text(format_output(
+            OutputFormat::Json,
+            &FormatOptions::default(),
+            None,
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(value.get("REQ-1").is_some());
+        assert!(value.get("meta").is_none());
+        assert!(value.get("results").is_none());
+    }
+
+    #[test]
+    fn json_with_meta_wraps_results() {
+        let results = one_result();
+        let meta = git_meta(false);
+
+        let out = text(format_output(
+            OutputFormat::Json,
+            &FormatOptions::default(),
+            Some(&meta),
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(value.get("meta").is_some());
+        assert!(value.get("results").is_some());
+        assert!(value.get("REQ-1").is_none());
+    }
+
+    #[test]
+    fn jsonl_emits_meta_then_matches() {
+        let results = one_result();
+        let meta = git_meta(true);
+
+        let out = text(format_output(
+            OutputFormat::Jsonl,
+            &FormatOptions::default(),
+            Some(&meta),
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let lines: Vec<&str> = out.split('\n').collect();
+        assert_eq!(lines.len(), 2);
+
+        let meta_line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(meta_line["type"], "meta");
+        assert!(meta_line["meta"].is_object());
+
+        let match_line: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(match_line["type"], "match");
+        assert_eq!(match_line["requirement_id"], "REQ-1");
+        assert!(match_line["entry"].is_object());
+    }
+
+    #[test]
+    fn csv_escapes_commas_and_quotes() {
+        let mut results = one_result();
+        results.get_mut("REQ-1").unwrap()[0].comment_text = "// REQ-1, \"quoted\"".to_string();
+
+        let out = text(format_output(
+            OutputFormat::Csv,
+            &FormatOptions::default(),
+            None,
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let lines: Vec<&str> = out.split('\n').collect();
+        assert_eq!(
+            lines[0],
+            "requirement_id,file,line,comment_text,above,below,inline,scope,blame"
+        );
+        assert!(
+            lines[1].contains("\"// REQ-1, \"\"quoted\"\"\""),
+            "expected csv escaping, got: {}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn csv_with_meta_appends_meta_columns() {
+        let results = one_result();
+        let out = text(format_output(
+            OutputFormat::Csv,
+            &FormatOptions::default(),
+            Some(&git_meta(true)),
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let lines: Vec<&str> = out.split('\n').collect();
+        assert!(lines[0].ends_with(
+            "repo_root,head_sha,head_ref,is_dirty,describe,tags,author_time,commit_time,remotes,dirty_files,submodules,roots"
+        ));
+        assert!(
+            lines[1].ends_with(
+                ",true,v1.0-2-gaaaaaaa,\"[\"\"v1.0.1\"\"]\",1700000000,1700000100,\
+                 \"[{\"\"name\"\":\"\"origin\"\",\"\"url\"\":\"\"https://***@example.com/repo.git\"\"}]\",\
+                 \"[\"\"src/lib.rs\"\"]\",,"
+            ),
+            "{}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn markdown_lists_commit_details() {
+        let results = one_result();
+        let out = text(format_output(
+            OutputFormat::Markdown,
+            &FormatOptions::default(),
+            Some(&git_meta(true)),
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        assert!(out.contains(&format!(
+            "- Commit: `{}` (`v1.0-2-gaaaaaaa`) (dirty)\n\
+             - Tags: `v1.0.1`\n\
+             - Remote `origin`: https://***@example.com/repo.git\n\
+             - Dirty files: `src/lib.rs`\n",
+            "a".repeat(40)
+        )));
+    }
+
+    #[test]
+    fn sarif_records_version_control_provenance() {
+        let results = one_result();
+        let out = text(format_output(
+            OutputFormat::Sarif,
+            &FormatOptions::default(),
+            Some(&git_meta(false)),
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let provenance = &value["runs"][0]["versionControlProvenance"][0];
+        assert_eq!(
+            provenance["repositoryUri"],
+            "https://***@example.com/repo.git"
+        );
+        assert_eq!(provenance["revisionId"], "a".repeat(40));
+        assert_eq!(provenance["branch"], "main");
+        assert_eq!(provenance["revisionTag"], "v1.0.1");
+    }
+
+    #[test]
+    fn sarif_has_basic_structure() {
+        let results = one_result();
+        let out = text(format_output(
+            OutputFormat::Sarif,
+            &FormatOptions::default(),
+            None,
+            &results,
+            &[],
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value["version"], "2.1.0");
+
+        let run = &value["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "tracy");
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "traceability.requirement_ref/REQ");
+        assert_eq!(result["properties"]["requirement_id"], "REQ-1");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/lib.rs"
+        );
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            1
+        );
+    }
+
+    #[test]
+    fn sarif_includes_policy_violations() {
+        let results = one_result();
+        let violations = vec![Violation {
+            rule: Rule::MinReferences,
+            severity: Severity::Warning,
+            requirement_id: "REQ-1".to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 1,
+            message: "REQ-1 has 1 reference(s), expected at least 2".to_string(),
+        }];
+
+        let options = FormatOptions {
+            rules: vec![Rule::MinReferences, Rule::MaxAge],
+            ..FormatOptions::default()
+        };
+        let rule_ids = |violations: &[Violation]| {
+            let out = text(format_output(
+                OutputFormat::Sarif,
+                &options,
+                None,
+                &results,
+                violations,
+                &SourceFiles::new(),
+            ));
+            let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+            value["runs"][0]["tool"]["driver"]["rules"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r["id"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        // A check is listed whether or not it found anything.
+        let expected = [
+            "traceability.requirement_ref/REQ",
+            "policy.min_references",
+            "policy.max_age",
+        ];
+        assert_eq!(rule_ids(&violations), expected);
+        assert_eq!(rule_ids(&[]), expected);
+
+        let out = text(format_output(
+            OutputFormat::Sarif,
+            &FormatOptions::default(),
+            None,
+            &results,
+            &violations,
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let run = &value["runs"][0];
+        let violation = &run["results"][1];
+        assert_eq!(violation["ruleId"], "policy.min_references");
+        assert_eq!(violation["level"], "warning");
+    }
+
+    #[test]
+    fn sarif_quotes_snippets_and_locates_scopes() {
+        let mut results = one_result();
+        let entry = &mut results.get_mut("REQ-1").unwrap()[0];
+        entry.line = 3;
+        entry.scope = vec![
+            ScopeItem {
+                kind: "function_item".to_string(),
+                name: Some("parse".to_string()),
+                line: 2,
+            },
+            ScopeItem {
+                kind: "mod_item".to_string(),
+                name: Some("input".to_string()),
+                line: 1,
+            },
+        ];
+        let sources = SourceFiles::from([(
+            PathBuf::from("src/lib.rs"),
+            "mod input {\nfn parse() {\n    // REQ-1: validate input\n}\n}\n".to_string(),
+        )]);
+        let options = FormatOptions {
+            requirement_url: Some("https://tracker.example.com/{slug}/{id}".to_string()),
+            command_line: ["tracy", "--slug", "REQ", "--exclude", "a b"]
+                .map(String::from)
+                .into(),
+            ..FormatOptions::default()
+        };
+
+        let out = text(format_output(
+            OutputFormat::Sarif,
+            &options,
+            None,
+            &results,
+            &[],
+            &sources,
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let run = &value["runs"][0];
+        // The requirement link is per requirement, so the slug has none.
+        assert!(run["tool"]["driver"]["rules"][0].get("helpUri").is_none());
+        assert_eq!(
+            run["invocations"][0]["commandLine"],
+            "tracy --slug REQ --exclude 'a b'"
+        );
+        assert_eq!(run["invocations"][0]["exitCode"], 0);
+
+        let result = &run["results"][0];
+        assert_eq!(
+            result["properties"]["requirement_url"],
+            "https://tracker.example.com/REQ/REQ-1"
+        );
+        let location = &result["locations"][0];
+        assert_eq!(
+            location["physicalLocation"]["region"]["snippet"]["text"],
+            "    // REQ-1: validate input\n"
+        );
+        let context = &location["physicalLocation"]["contextRegion"];
+        assert_eq!(
+            (&context["startLine"], &context["endLine"]),
+            (&1.into(), &5.into())
+        );
+        assert_eq!(
+            location["logicalLocations"][0],
+            serde_json::json!({
+                "name": "parse",
+                "fullyQualifiedName": "input::parse",
+                "kind": "function",
+            })
+        );
+
+        let options = FormatOptions {
+            requirement_url: Some("https://tracker.example.com/{slug}".to_string()),
+            ..FormatOptions::default()
+        };
+        let out = text(format_output(
+            OutputFormat::Sarif,
+            &options,
+            None,
+            &results,
+            &[],
+            &sources,
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(
+            value["runs"][0]["tool"]["driver"]["rules"][0]["helpUri"],
+            "https://tracker.example.com/REQ"
+        );
+    }
+
+    #[test]
+    fn sarif_fingerprints_survive_line_shifts() {
+        let fingerprints = |line| {
+            let mut results = one_result();
+            // A second, identical marker is told apart.
+            let mut twin = one_result().remove("REQ-1").unwrap();
+            twin[0].line = line + 1;
+            let entries = results.get_mut("REQ-1").unwrap();
+            entries[0].line = line;
+            entries.extend(twin);
+            let out = text(format_output(
+                OutputFormat::Sarif,
+                &FormatOptions::default(),
+                None,
+                &results,
+                &[],
+                &SourceFiles::new(),
+            ));
+            let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+            value["runs"][0]["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| {
+                    r["partialFingerprints"]["tracy/v1"]
+                        .as_str()
+                        .unwrap()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+        let before = fingerprints(1);
+        assert_eq!(before, fingerprints(40));
+        assert_ne!(before[0], before[1]);
+    }
+
+    #[test]
+    fn json_with_violations_wraps_results() {
+        let results = one_result();
+        let violations = vec![Violation {
+            rule: Rule::DocCommentsOnly,
+            severity: Severity::Error,
+            requirement_id: "REQ-1".to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 1,
+            message: "REQ-1 is referenced outside a doc comment".to_string(),
+        }];
+
+        let out = text(format_output(
+            OutputFormat::Json,
+            &FormatOptions::default(),
+            None,
+            &results,
+            &violations,
+            &SourceFiles::new(),
+        ));
+        let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(value.get("meta").is_none());
+        assert!(value["results"].get("REQ-1").is_some());
+        assert_eq!(value["violations"][0]["rule"], "policy.doc_comments_only");
+        assert_eq!(value["violations"][0]["severity"], "error");
+    }
+}
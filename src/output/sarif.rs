@@ -0,0 +1,651 @@
+use super::{FormatOptions, SourceFiles};
+use crate::check::{Rule, Violation, max_severity, slug_of};
+use crate::diff::{Change, ChangeKind, DiffReport};
+use crate::git::{BlameInfo, GitMeta, Submodule};
+use crate::scan::{Entry, ScanResult, fnv1a};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// The rule of requirement references, one per slug below it.
+pub(super) const REFERENCE_RULE: &str = "traceability.requirement_ref";
+
+/// The `partialFingerprints` key of tracy's fingerprints.
+const FINGERPRINT: &str = "tracy/v1";
+
+/// Lines either side of a result's line in its `contextRegion`.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Serialize)]
+struct SarifLog<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<SarifRun<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRun<'a> {
+    tool: SarifTool,
+    invocations: Vec<SarifInvocation>,
+    /// Where each scan root label used as a `uriBaseId` is
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    original_uri_base_ids: BTreeMap<String, SarifArtifactLocation>,
+    results: Vec<SarifResult<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    version_control_provenance: Vec<SarifVersionControlDetails<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<&'a GitMeta>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifVersionControlDetails<'a> {
+    repository_uri: &'a str,
+    revision_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision_tag: Option<&'a str>,
+    /// Where a submodule's repository is checked out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mapped_to: Option<SarifArtifactLocation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifInvocation {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    command_line: String,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    arguments: Vec<String>,
+    execution_successful: bool,
+    exit_code: u8,
+}
+
+#[derive(Serialize)]
+struct SarifTool {
+    driver: SarifDriver,
+}
+
+#[derive(Serialize)]
+struct SarifDriver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<SarifRule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    id: String,
+    name: String,
+    short_description: SarifMessage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult<'a> {
+    rule_id: String,
+    level: &'static str,
+    message: SarifMessage,
+    locations: Vec<SarifLocation>,
+    partial_fingerprints: BTreeMap<&'static str, String>,
+    properties: SarifResultProperties<'a>,
+}
+
+#[derive(Serialize)]
+struct SarifResultProperties<'a> {
+    requirement_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requirement_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment_text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blame: Option<&'a BlameInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submodule: Option<&'a Submodule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<&'a Path>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    logical_locations: Vec<SarifLogicalLocation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifPhysicalLocation {
+    artifact_location: SarifArtifactLocation,
+    region: SarifRegion,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    context_region: Option<SarifRegion>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLogicalLocation {
+    name: String,
+    fully_qualified_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifArtifactLocation {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    uri: String,
+    /// The label of the scan root the uri is relative to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<String>,
+}
+
+impl SarifArtifactLocation {
+    fn new(path: &Path, root: Option<&str>) -> Self {
+        SarifArtifactLocation {
+            uri: path.to_string_lossy().replace('\\', "/"),
+            uri_base_id: root.map(str::to_string),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRegion {
+    start_line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<SarifMessage>,
+}
+
+#[derive(Serialize)]
+struct SarifMessage {
+    text: String,
+}
+
+impl SarifLocation {
+    /// `line` of `file`, relative to the labeled scan root `root` if its
+    /// directory is known, quoted from the file's text when known.
+    fn new(
+        file: &Path,
+        root: Option<&str>,
+        line: usize,
+        options: &FormatOptions,
+        sources: &SourceFiles,
+    ) -> Self {
+        let root = root.filter(|root| options.roots.contains_key(*root));
+        let relative = root
+            .and_then(|root| file.strip_prefix(root).ok())
+            .unwrap_or(file);
+        SarifLocation {
+            physical_location: SarifPhysicalLocation::new(
+                SarifArtifactLocation::new(relative, root),
+                line,
+                sources.get(file).map(String::as_str),
+            ),
+            logical_locations: Vec::new(),
+        }
+    }
+
+    /// An entry's location, within its innermost scope.
+    fn entry(entry: &Entry, options: &FormatOptions, sources: &SourceFiles) -> Self {
+        SarifLocation {
+            logical_locations: entry
+                .scope
+                .first()
+                .map(|scope| SarifLogicalLocation {
+                    name: scope.name.clone().unwrap_or_else(|| scope.kind.clone()),
+                    fully_qualified_name: entry.scope_path(),
+                    kind: logical_kind(&scope.kind),
+                })
+                .into_iter()
+                .collect(),
+            ..SarifLocation::new(
+                &entry.file,
+                entry.root.as_deref(),
+                entry.line,
+                options,
+                sources,
+            )
+        }
+    }
+
+    /// A violation's location, relative to the labeled scan root its path
+    /// starts with.
+    fn violation(violation: &Violation, options: &FormatOptions, sources: &SourceFiles) -> Self {
+        let root = options
+            .roots
+            .keys()
+            .filter(|label| violation.file.starts_with(label))
+            .max_by_key(|label| label.len());
+        SarifLocation::new(
+            &violation.file,
+            root.map(String::as_str),
+            violation.line,
+            options,
+            sources,
+        )
+    }
+}
+
+impl SarifPhysicalLocation {
+    /// The whole of `line`, with the lines around it as the context region,
+    /// both quoted from the file's text when known.
+    fn new(artifact_location: SarifArtifactLocation, line: usize, source: Option<&str>) -> Self {
+        let lines: Vec<&str> = source.map(|s| s.lines().collect()).unwrap_or_default();
+        let quote = |from: usize, to: usize| SarifMessage {
+            text: lines[from - 1..to]
+                .iter()
+                .map(|l| format!("{l}\n"))
+                .collect(),
+        };
+        let known = (1..=lines.len()).contains(&line);
+        let from = line.saturating_sub(CONTEXT_LINES).max(1);
+        let to = (line + CONTEXT_LINES).min(lines.len());
+        SarifPhysicalLocation {
+            artifact_location,
+            region: SarifRegion {
+                start_line: line,
+                end_line: None,
+                snippet: known.then(|| quote(line, line)),
+            },
+            context_region: known.then(|| SarifRegion {
+                start_line: from,
+                end_line: Some(to),
+                snippet: Some(quote(from, to)),
+            }),
+        }
+    }
+}
+
+/// The SARIF kind of a scope's syntax node kind, where one fits.
+fn logical_kind(kind: &str) -> Option<&'static str> {
+    const TYPES: [&str; 8] = [
+        "class",
+        "struct",
+        "enum",
+        "union",
+        "trait",
+        "interface",
+        "impl",
+        "type",
+    ];
+    if kind.contains("function") || kind.contains("method") {
+        Some("function")
+    } else if kind.contains("namespace") {
+        Some("namespace")
+    } else if kind.starts_with("mod") {
+        Some("module")
+    } else if TYPES.iter().any(|t| kind.contains(t)) {
+        Some("type")
+    } else {
+        None
+    }
+}
+
+impl SarifRule {
+    fn new(id: &str, name: &str, description: &str) -> Self {
+        SarifRule {
+            id: id.to_string(),
+            name: name.to_string(),
+            short_description: SarifMessage {
+                text: description.to_string(),
+            },
+            help_uri: None,
+        }
+    }
+}
+
+/// Fingerprints that stay put when lines shift: a hash of what identifies a
+/// result, numbered among the results it does not tell apart.
+#[derive(Default)]
+pub(super) struct Fingerprints(HashMap<String, usize>);
+
+impl Fingerprints {
+    pub(super) fn next(&mut self, parts: &[&str]) -> String {
+        let key = parts.join("\0");
+        let n = self.0.entry(key.clone()).or_default();
+        *n += 1;
+        fnv1a(&format!("{key}\0{n}"))
+    }
+
+    /// A reference's: its requirement, file, scope and comment text.
+    pub(super) fn entry(&mut self, prefix: &str, requirement_id: &str, entry: &Entry) -> String {
+        self.next(&[
+            prefix,
+            requirement_id,
+            &entry.file.to_string_lossy(),
+            &entry.scope_path(),
+            &entry.fingerprint(),
+        ])
+    }
+
+    /// A violation's: its rule, requirement and file.
+    pub(super) fn violation(&mut self, violation: &Violation) -> String {
+        self.next(&[
+            violation.rule.id(),
+            &violation.requirement_id,
+            &violation.file.to_string_lossy(),
+        ])
+    }
+}
+
+/// The `partialFingerprints` of a result with `fingerprint`.
+fn partial_fingerprints(fingerprint: String) -> BTreeMap<&'static str, String> {
+    BTreeMap::from([(FINGERPRINT, fingerprint)])
+}
+
+/// The run's command line, each argument quoted as a POSIX shell would need.
+fn invocation(options: &FormatOptions, exit_code: u8) -> SarifInvocation {
+    let quote = |arg: &String| {
+        let plain = !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./:=,@+%".contains(c));
+        if plain {
+            arg.clone()
+        } else {
+            format!("'{}'", arg.replace('\'', "'\\''"))
+        }
+    };
+    SarifInvocation {
+        command_line: options
+            .command_line
+            .iter()
+            .map(quote)
+            .collect::<Vec<_>>()
+            .join(" "),
+        arguments: options.command_line.iter().skip(1).cloned().collect(),
+        execution_successful: true,
+        exit_code,
+    }
+}
+
+/// What a diff's change did, such as `REQ-1 moved from src/a.rs:3`.
+pub(super) fn change_message(change: &Change) -> String {
+    match (change.kind, &change.base) {
+        (ChangeKind::Moved, Some(base)) => format!(
+            "{} moved from {}:{}",
+            change.requirement_id,
+            base.file.display(),
+            base.line
+        ),
+        (kind, _) => format!(
+            "{} {}",
+            change.requirement_id,
+            kind.as_str().replace('_', " ")
+        ),
+    }
+}
+
+/// One entry per remote, so viewers can link results back to the repository,
+/// and likewise for each submodule and additional scan root.
+fn provenance(meta: &GitMeta) -> Vec<SarifVersionControlDetails<'_>> {
+    let mut details = repository_provenance(meta, None);
+    for submodule in &meta.submodules {
+        details.extend(repository_provenance(
+            &submodule.meta,
+            Some(SarifArtifactLocation::new(&submodule.path, None)),
+        ));
+    }
+    for root in &meta.roots {
+        details.extend(repository_provenance(
+            &root.meta,
+            Some(SarifArtifactLocation::new(Path::new(""), Some(&root.label))),
+        ));
+    }
+    details
+}
+
+fn repository_provenance<'a>(
+    meta: &'a GitMeta,
+    mapped_to: Option<SarifArtifactLocation>,
+) -> Vec<SarifVersionControlDetails<'a>> {
+    meta.remotes
+        .iter()
+        .map(|remote| SarifVersionControlDetails {
+            repository_uri: &remote.url,
+            revision_id: &meta.head_sha,
+            // With `--rev <tag>` the ref is the tag, not a branch.
+            branch: meta
+                .head_ref
+                .as_deref()
+                .filter(|r| !meta.tags.iter().any(|t| t == r)),
+            revision_tag: meta.tags.first().map(String::as_str),
+            mapped_to: mapped_to.clone(),
+        })
+        .collect()
+}
+
+/// The `file://` URI of a directory, with the trailing slash SARIF needs
+/// to resolve relative URIs against it.
+fn directory_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    let path = path.to_string_lossy().replace('\\', "/");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    if !uri.ends_with('/') {
+        uri.push('/');
+    }
+    uri
+}
+
+fn sarif_log<'a>(
+    options: &FormatOptions,
+    rules: Vec<SarifRule>,
+    invocation: SarifInvocation,
+    results: Vec<SarifResult<'a>>,
+    meta: Option<&'a GitMeta>,
+) -> Result<String, serde_json::Error> {
+    let sarif = SarifLog {
+        schema: "https://schemastore.azurewebsites.net/schemas/json/sarif-2.1.0.json",
+        version: "2.1.0",
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "tracy",
+                    version: env!("CARGO_PKG_VERSION"),
+                    rules,
+                },
+            },
+            invocations: vec![invocation],
+            original_uri_base_ids: options
+                .roots
+                .iter()
+                .map(|(label, path)| {
+                    (
+                        label.clone(),
+                        SarifArtifactLocation {
+                            uri: directory_uri(path),
+                            uri_base_id: None,
+                        },
+                    )
+                })
+                .collect(),
+            results,
+            version_control_provenance: meta.map(provenance).unwrap_or_default(),
+            properties: meta,
+        }],
+    };
+
+    serde_json::to_string_pretty(&sarif)
+}
+
+pub(super) fn format_sarif(
+    options: &FormatOptions,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+    sources: &SourceFiles,
+) -> Result<String, serde_json::Error> {
+    let mut fingerprints = Fingerprints::default();
+    let mut sarif_results = Vec::new();
+    for (requirement_id, entries) in results {
+        for entry in entries {
+            sarif_results.push(SarifResult {
+                rule_id: format!("{REFERENCE_RULE}/{}", slug_of(requirement_id)),
+                level: "note",
+                message: SarifMessage {
+                    text: format!("Requirement reference: {requirement_id}"),
+                },
+                locations: vec![SarifLocation::entry(entry, options, sources)],
+                partial_fingerprints: partial_fingerprints(fingerprints.entry(
+                    REFERENCE_RULE,
+                    requirement_id,
+                    entry,
+                )),
+                properties: SarifResultProperties {
+                    requirement_id,
+                    requirement_url: options.requirement_url(requirement_id),
+                    comment_text: Some(&entry.comment_text),
+                    blame: entry.blame.as_ref(),
+                    url: entry.url.as_deref(),
+                    submodule: entry.submodule.as_ref(),
+                    root: entry.root.as_deref(),
+                    component: entry.component.as_deref(),
+                },
+            });
+        }
+    }
+
+    let slugs: BTreeSet<&str> = results.keys().map(|id| slug_of(id)).collect();
+    let mut rules: Vec<SarifRule> = slugs
+        .into_iter()
+        .map(|slug| SarifRule {
+            help_uri: options.slug_url(slug),
+            ..SarifRule::new(
+                &format!("{REFERENCE_RULE}/{slug}"),
+                &format!("{slug} requirement reference"),
+                &format!("References to {slug} requirements found in comments"),
+            )
+        })
+        .collect();
+    let policy_rules: BTreeSet<Rule> = options
+        .rules
+        .iter()
+        .copied()
+        .chain(violations.iter().map(|v| v.rule))
+        .collect();
+    for rule in policy_rules {
+        rules.push(SarifRule::new(rule.id(), rule.name(), rule.description()));
+    }
+
+    for violation in violations {
+        sarif_results.push(SarifResult {
+            rule_id: violation.rule.id().to_string(),
+            level: violation.severity.as_str(),
+            message: SarifMessage {
+                text: violation.message.clone(),
+            },
+            locations: vec![SarifLocation::violation(violation, options, sources)],
+            partial_fingerprints: partial_fingerprints(fingerprints.violation(violation)),
+            properties: SarifResultProperties {
+                requirement_id: &violation.requirement_id,
+                requirement_url: options.requirement_url(&violation.requirement_id),
+                comment_text: None,
+                blame: None,
+                url: None,
+                submodule: None,
+                root: None,
+                component: None,
+            },
+        });
+    }
+
+    let exit_code = max_severity(violations).map_or(0, |s| s.exit_code());
+    sarif_log(
+        options,
+        rules,
+        invocation(options, exit_code),
+        sarif_results,
+        meta,
+    )
+}
+
+pub(super) fn format_diff_sarif(
+    options: &FormatOptions,
+    report: &DiffReport,
+) -> Result<String, serde_json::Error> {
+    let rule = |kind: ChangeKind| match kind {
+        ChangeKind::Added => SarifRule::new(
+            "diff.added",
+            "Trace link added",
+            "Requirement reference added",
+        ),
+        ChangeKind::Removed => SarifRule::new(
+            "diff.removed",
+            "Trace link removed",
+            "Requirement reference removed",
+        ),
+        ChangeKind::Moved => SarifRule::new(
+            "diff.moved",
+            "Trace link moved",
+            "Requirement reference moved to another file or scope",
+        ),
+        ChangeKind::ChangedText => SarifRule::new(
+            "diff.changed_text",
+            "Trace link text changed",
+            "Requirement reference comment text changed",
+        ),
+    };
+
+    let kinds: BTreeSet<ChangeKind> = report.changes.iter().map(|c| c.kind).collect();
+    let rules = kinds.into_iter().map(rule).collect();
+
+    let mut fingerprints = Fingerprints::default();
+    let mut sarif_results = Vec::new();
+    for change in &report.changes {
+        // Removed links only exist at the base revision; everything else is
+        // reported where it now lives.
+        let Some(entry) = change.head.as_ref().or(change.base.as_ref()) else {
+            continue;
+        };
+        let rule_id = rule(change.kind).id;
+        sarif_results.push(SarifResult {
+            partial_fingerprints: partial_fingerprints(fingerprints.entry(
+                &rule_id,
+                &change.requirement_id,
+                entry,
+            )),
+            rule_id,
+            level: "note",
+            message: SarifMessage {
+                text: change_message(change),
+            },
+            locations: vec![SarifLocation::entry(entry, options, &SourceFiles::new())],
+            properties: SarifResultProperties {
+                requirement_id: &change.requirement_id,
+                requirement_url: options.requirement_url(&change.requirement_id),
+                comment_text: Some(&entry.comment_text),
+                blame: None,
+                url: entry.url.as_deref(),
+                submodule: entry.submodule.as_ref(),
+                root: entry.root.as_deref(),
+                component: entry.component.as_deref(),
+            },
+        });
+    }
+
+    sarif_log(options, rules, invocation(options, 0), sarif_results, None)
+}
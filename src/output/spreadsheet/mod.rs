@@ -0,0 +1,624 @@
+//! Traceability matrix workbooks, written as XLSX or ODS. Both writers render
+//! the same [`Sheet`]s: a header row, frozen, and typed cells.
+
+mod ods;
+mod xlsx;
+
+use super::html::{date, escape};
+use crate::check::{Rule, Violation, slug_of};
+use crate::diff::DiffReport;
+use crate::git::GitMeta;
+use crate::scan::{Entry, ScanResult};
+use std::collections::{BTreeSet, HashMap};
+use zip::result::ZipResult;
+
+pub(super) use ods::write_ods;
+pub(super) use xlsx::write_xlsx;
+
+/// A sheet of a workbook.
+pub(super) struct Sheet {
+    /// A single word, so references to it need no quoting
+    name: &'static str,
+    header: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    Empty,
+    Text(String),
+    Number(i64),
+    /// Seconds since the epoch, shown as a UTC date and time
+    Time(i64),
+    /// Text linking to a url
+    Link {
+        text: String,
+        url: String,
+    },
+    /// Text linking to row `row` (0-based, below the header) of sheet `sheet`
+    Jump {
+        text: String,
+        sheet: &'static str,
+        row: usize,
+    },
+}
+
+impl Cell {
+    fn text(&self) -> &str {
+        match self {
+            Cell::Text(text) | Cell::Link { text, .. } | Cell::Jump { text, .. } => text,
+            Cell::Empty | Cell::Number(_) | Cell::Time(_) => "",
+        }
+    }
+
+    fn optional(text: Option<impl ToString>) -> Cell {
+        text.map_or(Cell::Empty, |text| Cell::Text(text.to_string()))
+    }
+}
+
+/// The sheets of a scan: requirements, their references, uncovered
+/// requirements and the run's metadata.
+pub(super) fn scan_sheets(
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Vec<Sheet> {
+    let references = reference_sheet(results);
+    // The References row each requirement's rows start at.
+    let mut first_row = HashMap::new();
+    let mut row = 0;
+    for (requirement_id, entries) in results {
+        first_row.insert(requirement_id.as_str(), row);
+        row += entries.len();
+    }
+    let jump = |requirement_id: &str| match first_row.get(requirement_id) {
+        Some(&row) => Cell::Jump {
+            text: requirement_id.to_string(),
+            sheet: references.name,
+            row,
+        },
+        None => Cell::Text(requirement_id.to_string()),
+    };
+
+    let requirements = Sheet {
+        name: "Requirements",
+        header: vec!["Requirement", "Slug", "References", "Files", "Locations"],
+        rows: results
+            .iter()
+            .map(|(requirement_id, entries)| {
+                let files: BTreeSet<_> = entries.iter().map(|e| &e.file).collect();
+                let locations: Vec<_> = entries.iter().map(location).collect();
+                vec![
+                    jump(requirement_id),
+                    Cell::Text(slug_of(requirement_id).to_string()),
+                    Cell::Number(entries.len() as i64),
+                    Cell::Number(files.len() as i64),
+                    Cell::Text(locations.join(", ")),
+                ]
+            })
+            .collect(),
+    };
+
+    let uncovered: Vec<_> = violations
+        .iter()
+        .filter(|v| matches!(v.rule, Rule::MinReferences | Rule::RequireVerification))
+        .collect();
+    let uncovered_sheet = Sheet {
+        name: "Uncovered",
+        header: vec!["Requirement", "Rule", "Severity", "File", "Line", "Message"],
+        rows: uncovered
+            .iter()
+            .map(|violation| {
+                vec![
+                    jump(&violation.requirement_id),
+                    Cell::Text(violation.rule.id().to_string()),
+                    Cell::Text(violation.severity.as_str().to_string()),
+                    Cell::Text(violation.file.display().to_string()),
+                    Cell::Number(violation.line as i64),
+                    Cell::Text(violation.message.clone()),
+                ]
+            })
+            .collect(),
+    };
+
+    let mut run = vec![
+        pair("Tool", format!("tracy {}", env!("CARGO_PKG_VERSION"))),
+        vec![
+            Cell::Text("Requirements".into()),
+            Cell::Number(results.len() as i64),
+        ],
+        vec![
+            Cell::Text("References".into()),
+            Cell::Number(results.values().map(Vec::len).sum::<usize>() as i64),
+        ],
+        vec![
+            Cell::Text("Uncovered".into()),
+            Cell::Number(uncovered.len() as i64),
+        ],
+    ];
+    if let Some(meta) = meta {
+        run.extend(meta_rows("", meta));
+        for submodule in &meta.submodules {
+            let prefix = format!("Submodule {} ", submodule.path.display());
+            run.extend(meta_rows(&prefix, &submodule.meta));
+        }
+        for root in &meta.roots {
+            let prefix = format!("Root {} ", root.label);
+            run.extend(meta_rows(&prefix, &root.meta));
+        }
+    }
+
+    vec![
+        requirements,
+        references,
+        uncovered_sheet,
+        Sheet {
+            name: "Run",
+            header: vec!["Key", "Value"],
+            rows: run,
+        },
+    ]
+}
+
+/// The sheets of `tracy diff`: the changes and the compared revisions.
+pub(super) fn diff_sheets(report: &DiffReport) -> Vec<Sheet> {
+    let side = |entry: Option<&Entry>| match entry {
+        Some(entry) => [
+            file(entry),
+            Cell::Number(entry.line as i64),
+            Cell::Text(entry.comment_text.trim_end().to_string()),
+        ],
+        None => [Cell::Empty, Cell::Empty, Cell::Empty],
+    };
+    let changes = Sheet {
+        name: "Changes",
+        header: vec![
+            "Change",
+            "Requirement",
+            "Base file",
+            "Base line",
+            "Base comment",
+            "Head file",
+            "Head line",
+            "Head comment",
+        ],
+        rows: report
+            .changes
+            .iter()
+            .map(|change| {
+                let mut row = vec![
+                    Cell::Text(change.kind.as_str().replace('_', " ")),
+                    Cell::Text(change.requirement_id.clone()),
+                ];
+                row.extend(side(change.base.as_ref()));
+                row.extend(side(change.head.as_ref()));
+                row
+            })
+            .collect(),
+    };
+
+    let mut run = vec![
+        pair("Tool", format!("tracy {}", env!("CARGO_PKG_VERSION"))),
+        pair("Base", report.base.clone()),
+        pair("Base commit", report.base_sha.clone()),
+        pair(
+            "Head",
+            report.head.as_deref().unwrap_or("working tree").to_string(),
+        ),
+    ];
+    if let Some(sha) = &report.head_sha {
+        run.push(pair("Head commit", sha.clone()));
+    }
+    vec![
+        changes,
+        Sheet {
+            name: "Run",
+            header: vec!["Key", "Value"],
+            rows: run,
+        },
+    ]
+}
+
+/// A column of the References sheet: its name and each entry's cell.
+type Column = (&'static str, fn(&Entry) -> Cell);
+
+/// The text, kind, name and line columns of a code context field.
+macro_rules! context_columns {
+    ($field:ident, $label:literal) => {
+        [
+            (
+                $label,
+                (|e| Cell::optional(e.$field.as_ref().map(|c| &c.text))) as fn(&Entry) -> Cell,
+            ),
+            (concat!($label, " kind"), |e| {
+                Cell::optional(e.$field.as_ref().map(|c| &c.kind))
+            }),
+            (concat!($label, " name"), |e| {
+                Cell::optional(e.$field.as_ref().and_then(|c| c.name.as_ref()))
+            }),
+            (concat!($label, " line"), |e| {
+                e.$field
+                    .as_ref()
+                    .map_or(Cell::Empty, |c| Cell::Number(c.line as i64))
+            }),
+        ]
+    };
+}
+
+/// One row per reference, with its context flattened into columns.
+fn reference_sheet(results: &ScanResult) -> Sheet {
+    let entries = || results.values().flatten();
+
+    let mut columns: Vec<Column> = vec![
+        ("File", file),
+        ("Line", |e| Cell::Number(e.line as i64)),
+        ("Comment", |e| {
+            Cell::Text(e.comment_text.trim_end().to_string())
+        }),
+    ];
+    if entries().any(|e| e.above.is_some()) {
+        columns.extend(context_columns!(above, "Above"));
+    }
+    if entries().any(|e| e.below.is_some()) {
+        columns.extend(context_columns!(below, "Below"));
+    }
+    if entries().any(|e| e.inline.is_some()) {
+        columns.extend(context_columns!(inline, "Inline"));
+    }
+    if entries().any(|e| !e.scope.is_empty()) {
+        columns.push(("Scope", |e| {
+            let scope: Vec<_> = e
+                .scope
+                .iter()
+                .rev()
+                .map(|item| match &item.name {
+                    Some(name) => format!("{} {name}", item.kind),
+                    None => item.kind.clone(),
+                })
+                .collect();
+            Cell::Text(scope.join(" > "))
+        }));
+    }
+    if entries().any(|e| e.blame.is_some()) {
+        columns.extend([
+            (
+                "Blame commit",
+                (|e| Cell::optional(e.blame.as_ref().map(|b| &b.commit))) as fn(&Entry) -> Cell,
+            ),
+            ("Author", |e| {
+                Cell::optional(e.blame.as_ref().and_then(|b| b.author.as_ref()))
+            }),
+            ("Author email", |e| {
+                Cell::optional(e.blame.as_ref().and_then(|b| b.author_mail.as_ref()))
+            }),
+            ("Authored", |e| {
+                e.blame
+                    .as_ref()
+                    .and_then(|b| b.author_time)
+                    .map_or(Cell::Empty, Cell::Time)
+            }),
+            ("Summary", |e| {
+                Cell::optional(e.blame.as_ref().and_then(|b| b.summary.as_ref()))
+            }),
+        ]);
+    }
+    if entries().any(|e| e.root.is_some()) {
+        columns.push(("Root", |e| Cell::optional(e.root.as_ref())));
+    }
+    if entries().any(|e| e.component.is_some()) {
+        columns.push(("Component", |e| {
+            Cell::optional(e.component.as_ref().map(|c| c.display()))
+        }));
+    }
+    if entries().any(|e| e.submodule.is_some()) {
+        columns.extend([
+            (
+                "Submodule",
+                (|e| Cell::optional(e.submodule.as_ref().map(|s| s.path.display())))
+                    as fn(&Entry) -> Cell,
+            ),
+            ("Submodule commit", |e| {
+                Cell::optional(e.submodule.as_ref().map(|s| &s.commit))
+            }),
+        ]);
+    }
+
+    let mut header = vec!["Requirement"];
+    header.extend(columns.iter().map(|(name, _)| *name));
+    let rows = results
+        .iter()
+        .flat_map(|(requirement_id, entries)| {
+            entries.iter().map(|entry| {
+                let mut row = vec![Cell::Text(requirement_id.clone())];
+                row.extend(columns.iter().map(|(_, cell)| cell(entry)));
+                row
+            })
+        })
+        .collect();
+    Sheet {
+        name: "References",
+        header,
+        rows,
+    }
+}
+
+/// The rows describing a commit, their keys prefixed with `prefix`.
+fn meta_rows(prefix: &str, meta: &GitMeta) -> Vec<Vec<Cell>> {
+    let key = |name: &str| Cell::Text(format!("{prefix}{name}"));
+    let mut rows = vec![
+        vec![
+            key("Repository"),
+            Cell::Text(meta.repo_root.display().to_string()),
+        ],
+        vec![key("Commit"), Cell::Text(meta.head_sha.clone())],
+        vec![key("Branch"), Cell::optional(meta.head_ref.as_ref())],
+        vec![key("Describe"), Cell::Text(meta.describe.clone())],
+        vec![key("Dirty"), Cell::Text(meta.is_dirty.to_string())],
+        vec![key("Tags"), Cell::Text(meta.tags.join(", "))],
+        vec![key("Authored"), Cell::Time(meta.author_time)],
+        vec![key("Committed"), Cell::Time(meta.commit_time)],
+    ];
+    for remote in &meta.remotes {
+        rows.push(vec![
+            key(&format!("Remote {}", remote.name)),
+            Cell::Text(remote.url.clone()),
+        ]);
+    }
+    if !meta.dirty_files.is_empty() {
+        let files: Vec<_> = meta
+            .dirty_files
+            .iter()
+            .map(|f| f.display().to_string())
+            .collect();
+        rows.push(vec![key("Dirty files"), Cell::Text(files.join(", "))]);
+    }
+    rows
+}
+
+fn pair(key: &str, value: String) -> Vec<Cell> {
+    vec![Cell::Text(key.to_string()), Cell::Text(value)]
+}
+
+/// The entry's file, linked to its permalink when it has one.
+fn file(entry: &Entry) -> Cell {
+    let text = entry.file.display().to_string();
+    match &entry.url {
+        Some(url) => Cell::Link {
+            text,
+            url: url.clone(),
+        },
+        None => Cell::Text(text),
+    }
+}
+
+fn location(entry: &Entry) -> String {
+    format!("{}:{}", entry.file.display(), entry.line)
+}
+
+/// The width of each column, in characters: its longest line, within limits.
+fn column_widths(sheet: &Sheet) -> Vec<usize> {
+    (0..sheet.header.len())
+        .map(|column| {
+            let cells = sheet.rows.iter().filter_map(|row| row.get(column));
+            let longest = cells
+                .map(|cell| match cell {
+                    Cell::Number(n) => n.to_string().len(),
+                    Cell::Time(_) => 16,
+                    cell => cell
+                        .text()
+                        .lines()
+                        .map(|l| l.chars().count())
+                        .max()
+                        .unwrap_or(0),
+                })
+                .chain([sheet.header[column].len()])
+                .max()
+                .unwrap_or(0);
+            longest.clamp(8, 60) + 2
+        })
+        .collect()
+}
+
+/// The A1-style name of a 0-based column.
+fn column_name(mut column: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (column % 26) as u8);
+        if column < 26 {
+            break;
+        }
+        column = column / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// A unix timestamp as a UTC `YYYY-MM-DDTHH:MM:SS` date and time.
+fn datetime(timestamp: i64) -> String {
+    let seconds = timestamp.rem_euclid(86_400);
+    format!(
+        "{}T{:02}:{:02}:{:02}",
+        date(timestamp),
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// `text` escaped for XML, without the control characters XML cannot hold.
+pub(super) fn xml(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|&c| !c.is_control() || matches!(c, '\t' | '\n'))
+        .collect();
+    escape(&text)
+}
+
+/// Write `files` into a zip archive, in order.
+fn zip(files: &[(&str, String)], first_stored: bool) -> ZipResult<Vec<u8>> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (i, (name, content)) in files.iter().enumerate() {
+        let options = if i == 0 && first_stored {
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored)
+        } else {
+            SimpleFileOptions::default()
+        };
+        zip.start_file(*name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::Severity;
+    use crate::git::BlameInfo;
+    use crate::scan::CodeContext;
+    use std::io::Read;
+    use std::path::PathBuf;
+
+    pub(super) fn entry(file: &str, line: usize) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line,
+            comment_text: format!("// marker at {line}\n"),
+            above: None,
+            below: Some(CodeContext {
+                kind: "function_item".into(),
+                name: Some("check".into()),
+                text: "fn check() {}".into(),
+                line: line + 1,
+            }),
+            inline: None,
+            scope: Vec::new(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    pub(super) fn sheets() -> Vec<Sheet> {
+        let mut linked = entry("src/lib.rs", 3);
+        linked.url = Some("https://example.com/src/lib.rs#L3".into());
+        linked.blame = Some(BlameInfo {
+            commit: "c".repeat(40),
+            author: Some("Ada".into()),
+            author_mail: None,
+            author_time: Some(1_700_000_000),
+            summary: None,
+        });
+        let results = ScanResult::from([
+            (
+                "REQ-1".to_string(),
+                vec![entry("src/a.rs", 1), entry("src/a.rs", 9)],
+            ),
+            ("REQ-2".to_string(), vec![linked]),
+        ]);
+        let violations = [Violation {
+            rule: Rule::MinReferences,
+            severity: Severity::Error,
+            requirement_id: "REQ-2".into(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 3,
+            message: "REQ-2 has 1 reference(s), expected at least 2".into(),
+        }];
+        scan_sheets(None, &results, &violations)
+    }
+
+    pub(super) fn unzip(bytes: &[u8]) -> Vec<(String, String)> {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).unwrap();
+                (file.name().to_string(), content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flattens_references_and_links_requirements_to_them() {
+        let sheets = sheets();
+        let names: Vec<_> = sheets.iter().map(|s| s.name).collect();
+        assert_eq!(names, ["Requirements", "References", "Uncovered", "Run"]);
+
+        let [requirements, references, uncovered, run] = &sheets[..] else {
+            unreachable!()
+        };
+        assert_eq!(
+            requirements.rows[1][..4],
+            [
+                Cell::Jump {
+                    text: "REQ-2".into(),
+                    sheet: "References",
+                    row: 2
+                },
+                Cell::Text("REQ".into()),
+                Cell::Number(1),
+                Cell::Number(1),
+            ]
+        );
+        assert_eq!(
+            requirements.rows[0][4],
+            Cell::Text("src/a.rs:1, src/a.rs:9".into())
+        );
+
+        assert_eq!(
+            references.header,
+            [
+                "Requirement",
+                "File",
+                "Line",
+                "Comment",
+                "Below",
+                "Below kind",
+                "Below name",
+                "Below line",
+                "Blame commit",
+                "Author",
+                "Author email",
+                "Authored",
+                "Summary",
+            ]
+        );
+        let row = &references.rows[2];
+        assert_eq!(
+            row[1],
+            Cell::Link {
+                text: "src/lib.rs".into(),
+                url: "https://example.com/src/lib.rs#L3".into()
+            }
+        );
+        assert_eq!(row[3], Cell::Text("// marker at 3".into()));
+        assert_eq!(row[6], Cell::Text("check".into()));
+        assert_eq!(row[11], Cell::Time(1_700_000_000));
+        assert_eq!(references.rows[0][8], Cell::Empty);
+
+        assert_eq!(uncovered.rows.len(), 1);
+        assert_eq!(
+            uncovered.rows[0][1],
+            Cell::Text("policy.min_references".into())
+        );
+        assert_eq!(
+            run.rows[3],
+            vec![Cell::Text("Uncovered".into()), Cell::Number(1)]
+        );
+    }
+
+    #[test]
+    fn names_columns_and_formats_times() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(27 * 26), "AAA");
+        assert_eq!(datetime(1_700_000_000), "2023-11-14T22:13:20");
+        assert_eq!(xml("a\u{1b}<b>\n"), "a&lt;b&gt;\n");
+    }
+}
@@ -0,0 +1,215 @@
+//! OpenDocument spreadsheets (`.ods`).
+
+use super::{Cell, Sheet, column_name, column_widths, datetime, xml, zip};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use zip::result::ZipResult;
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+const NAMESPACES: &str = "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+     xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" \
+     xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+     xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+     xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" \
+     xmlns:number=\"urn:oasis:names:tc:opendocument:xmlns:datastyle:1.0\" \
+     xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+     xmlns:config=\"urn:oasis:names:tc:opendocument:xmlns:config:1.0\" \
+     office:version=\"1.2\"";
+
+const STYLES: &str = "<number:date-style style:name=\"N1\">\
+     <number:year number:style=\"long\"/><number:text>-</number:text>\
+     <number:month number:style=\"long\"/><number:text>-</number:text>\
+     <number:day number:style=\"long\"/><number:text> </number:text>\
+     <number:hours number:style=\"long\"/><number:text>:</number:text>\
+     <number:minutes number:style=\"long\"/></number:date-style>\
+     <style:style style:name=\"header\" style:family=\"table-cell\">\
+     <style:table-cell-properties fo:background-color=\"#dde4ee\" fo:border-bottom=\"0.5pt solid #000000\"/>\
+     <style:text-properties fo:font-weight=\"bold\"/></style:style>\
+     <style:style style:name=\"time\" style:family=\"table-cell\" style:data-style-name=\"N1\"/>";
+
+pub(in crate::output) fn write_ods(sheets: &[Sheet]) -> ZipResult<Vec<u8>> {
+    let widths: Vec<_> = sheets.iter().map(column_widths).collect();
+    let mut content =
+        format!("{HEADER}<office:document-content {NAMESPACES}><office:automatic-styles>{STYLES}");
+    // A column style per width in use.
+    for width in widths.iter().flatten().collect::<BTreeSet<_>>() {
+        let _ = write!(
+            content,
+            "<style:style style:name=\"co{width}\" style:family=\"table-column\">\
+             <style:table-column-properties style:column-width=\"{:.2}cm\"/></style:style>",
+            *width as f64 * 0.2
+        );
+    }
+    content.push_str("</office:automatic-styles><office:body><office:spreadsheet>");
+
+    let mut ranges = String::new();
+    for (sheet, widths) in sheets.iter().zip(&widths) {
+        let _ = write!(content, "<table:table table:name=\"{}\">", sheet.name);
+        for width in widths {
+            let _ = write!(
+                content,
+                "<table:table-column table:style-name=\"co{width}\"/>"
+            );
+        }
+        content.push_str("<table:table-header-rows><table:table-row>");
+        for name in &sheet.header {
+            let _ = write!(
+                content,
+                "<table:table-cell table:style-name=\"header\" office:value-type=\"string\">\
+                 <text:p>{}</text:p></table:table-cell>",
+                xml(name)
+            );
+        }
+        content.push_str("</table:table-row></table:table-header-rows>");
+        for row in &sheet.rows {
+            content.push_str("<table:table-row>");
+            for cell in row {
+                table_cell(&mut content, cell);
+            }
+            content.push_str("</table:table-row>");
+        }
+        content.push_str("</table:table>");
+        let _ = write!(
+            ranges,
+            "<table:database-range table:name=\"__Anonymous_Sheet_DB__{}\" \
+             table:target-range-address=\"{name}.A1:{name}.{}{}\" table:display-filter-buttons=\"true\"/>",
+            ranges.matches("<table:database-range ").count(),
+            column_name(sheet.header.len() - 1),
+            sheet.rows.len() + 1,
+            name = sheet.name
+        );
+    }
+    let _ = write!(
+        content,
+        "<table:database-ranges>{ranges}</table:database-ranges>\
+         </office:spreadsheet></office:body></office:document-content>"
+    );
+
+    let files = [
+        ("mimetype", MIMETYPE.to_string()),
+        (
+            "META-INF/manifest.xml",
+            format!(
+                "{HEADER}<manifest:manifest \
+                 xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\
+                 <manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.2\" manifest:media-type=\"{MIMETYPE}\"/>\
+                 <manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>\
+                 <manifest:file-entry manifest:full-path=\"settings.xml\" manifest:media-type=\"text/xml\"/>\
+                 </manifest:manifest>"
+            ),
+        ),
+        ("content.xml", content),
+        ("settings.xml", settings(sheets)),
+    ];
+    // The mimetype comes first and uncompressed, so the type can be sniffed.
+    zip(&files, true)
+}
+
+fn table_cell(out: &mut String, cell: &Cell) {
+    match cell {
+        Cell::Empty => out.push_str("<table:table-cell/>"),
+        Cell::Text(text) => {
+            out.push_str("<table:table-cell office:value-type=\"string\">");
+            for line in text.split('\n') {
+                let _ = write!(out, "<text:p>{}</text:p>", xml(line));
+            }
+            out.push_str("</table:table-cell>");
+        }
+        Cell::Number(n) => {
+            let _ = write!(
+                out,
+                "<table:table-cell office:value-type=\"float\" office:value=\"{n}\">\
+                 <text:p>{n}</text:p></table:table-cell>"
+            );
+        }
+        Cell::Time(timestamp) => {
+            let value = datetime(*timestamp);
+            let _ = write!(
+                out,
+                "<table:table-cell table:style-name=\"time\" office:value-type=\"date\" \
+                 office:date-value=\"{value}\"><text:p>{}</text:p></table:table-cell>",
+                value[..16].replace('T', " ")
+            );
+        }
+        Cell::Link { text, url } => link(out, text, url),
+        Cell::Jump { text, sheet, row } => link(out, text, &format!("#{sheet}.A{}", row + 2)),
+    }
+}
+
+fn link(out: &mut String, text: &str, href: &str) {
+    let _ = write!(
+        out,
+        "<table:table-cell office:value-type=\"string\"><text:p>\
+         <text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>\
+         </text:p></table:table-cell>",
+        xml(href),
+        xml(text)
+    );
+}
+
+/// View settings freezing each sheet's header row.
+fn settings(sheets: &[Sheet]) -> String {
+    let mut tables = String::new();
+    for sheet in sheets {
+        let _ = write!(
+            tables,
+            "<config:config-item-map-entry config:name=\"{}\">\
+             <config:config-item config:name=\"HorizontalSplitMode\" config:type=\"short\">0</config:config-item>\
+             <config:config-item config:name=\"VerticalSplitMode\" config:type=\"short\">2</config:config-item>\
+             <config:config-item config:name=\"VerticalSplitPosition\" config:type=\"int\">1</config:config-item>\
+             <config:config-item config:name=\"ActiveSplitRange\" config:type=\"short\">2</config:config-item>\
+             <config:config-item config:name=\"PositionTop\" config:type=\"int\">0</config:config-item>\
+             <config:config-item config:name=\"PositionBottom\" config:type=\"int\">1</config:config-item>\
+             </config:config-item-map-entry>",
+            sheet.name
+        );
+    }
+    format!(
+        "{HEADER}<office:document-settings {NAMESPACES}><office:settings>\
+         <config:config-item-set config:name=\"ooo:view-settings\">\
+         <config:config-item-map-indexed config:name=\"Views\"><config:config-item-map-entry>\
+         <config:config-item config:name=\"ViewId\" config:type=\"string\">view1</config:config-item>\
+         <config:config-item-map-named config:name=\"Tables\">{tables}</config:config-item-map-named>\
+         </config:config-item-map-entry></config:config-item-map-indexed>\
+         </config:config-item-set></office:settings></office:document-settings>"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{sheets, unzip};
+    use super::*;
+
+    #[test]
+    fn writes_a_stored_mimetype_then_tables_with_frozen_headers() {
+        let bytes = write_ods(&sheets()).unwrap();
+        // Readable at a fixed offset, as the OpenDocument spec requires.
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..38 + MIMETYPE.len()], MIMETYPE.as_bytes());
+
+        let files = unzip(&bytes);
+        let file = |name: &str| &files.iter().find(|(n, _)| n == name).unwrap().1;
+        let content = file("content.xml");
+        assert!(content.contains("<table:table table:name=\"Uncovered\">"));
+        assert!(content.contains(
+            "<table:table-cell table:style-name=\"header\" office:value-type=\"string\"><text:p>Requirement</text:p>"
+        ));
+        assert!(content.contains(
+            "<text:a xlink:type=\"simple\" xlink:href=\"#References.A4\">REQ-2</text:a>"
+        ));
+        assert!(
+            content
+                .contains("xlink:href=\"https://example.com/src/lib.rs#L3\">src/lib.rs</text:a>")
+        );
+        assert!(content.contains(
+            "office:date-value=\"2023-11-14T22:13:20\"><text:p>2023-11-14 22:13</text:p>"
+        ));
+        assert!(content.contains("table:target-range-address=\"Run.A1:Run.B5\""));
+        assert!(file("settings.xml").contains(
+            "<config:config-item-map-entry config:name=\"References\">\
+             <config:config-item config:name=\"HorizontalSplitMode\" config:type=\"short\">0</config:config-item>\
+             <config:config-item config:name=\"VerticalSplitMode\" config:type=\"short\">2</config:config-item>"
+        ));
+    }
+}
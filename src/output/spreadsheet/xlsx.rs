@@ -0,0 +1,277 @@
+//! Office Open XML workbooks (`.xlsx`), with inline strings so no shared
+//! string table is needed.
+
+use super::{Cell, Sheet, column_name, column_widths, xml, zip};
+use std::fmt::Write;
+use zip::result::ZipResult;
+
+const MAIN: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const PACKAGE_RELATIONSHIPS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// The most characters a cell holds.
+const CELL_LIMIT: usize = 32_767;
+
+/// Cell formats, by their index in `cellXfs` of [`STYLES`].
+const HEADER_STYLE: usize = 1;
+const LINK_STYLE: usize = 2;
+const TIME_STYLE: usize = 3;
+
+const STYLES: &str = r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+<numFmts count="1"><numFmt numFmtId="164" formatCode="yyyy-mm-dd hh:mm"/></numFmts>
+<fonts count="3">
+<font><sz val="11"/><name val="Calibri"/></font>
+<font><b/><sz val="11"/><name val="Calibri"/></font>
+<font><u/><sz val="11"/><color rgb="FF0563C1"/><name val="Calibri"/></font>
+</fonts>
+<fills count="3">
+<fill><patternFill patternType="none"/></fill>
+<fill><patternFill patternType="gray125"/></fill>
+<fill><patternFill patternType="solid"><fgColor rgb="FFDDE4EE"/><bgColor indexed="64"/></patternFill></fill>
+</fills>
+<borders count="2">
+<border><left/><right/><top/><bottom/><diagonal/></border>
+<border><left/><right/><top/><bottom style="thin"><color auto="1"/></bottom><diagonal/></border>
+</borders>
+<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>
+<cellXfs count="4">
+<xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/>
+<xf numFmtId="0" fontId="1" fillId="2" borderId="1" xfId="0" applyFont="1" applyFill="1" applyBorder="1"/>
+<xf numFmtId="0" fontId="2" fillId="0" borderId="0" xfId="0" applyFont="1"/>
+<xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+</cellXfs>
+<cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>
+</styleSheet>
+"#;
+
+pub(in crate::output) fn write_xlsx(sheets: &[Sheet]) -> ZipResult<Vec<u8>> {
+    let mut types = format!(
+        "{HEADER}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>"
+    );
+    let mut workbook =
+        format!("{HEADER}<workbook xmlns=\"{MAIN}\" xmlns:r=\"{RELATIONSHIPS}\"><sheets>");
+    let mut filters = String::new();
+    let mut workbook_rels = format!("{HEADER}<Relationships xmlns=\"{PACKAGE_RELATIONSHIPS}\">");
+    let mut parts = Vec::new();
+
+    for (i, sheet) in sheets.iter().enumerate() {
+        let n = i + 1;
+        let _ = write!(
+            types,
+            "<Override PartName=\"/xl/worksheets/sheet{n}.xml\" \
+             ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>"
+        );
+        let _ = write!(
+            workbook,
+            "<sheet name=\"{}\" sheetId=\"{n}\" r:id=\"rId{n}\"/>",
+            sheet.name
+        );
+        let _ = write!(
+            filters,
+            "<definedName name=\"_xlnm._FilterDatabase\" localSheetId=\"{i}\" hidden=\"1\">\
+             {}!$A$1:${}${}</definedName>",
+            sheet.name,
+            column_name(sheet.header.len() - 1),
+            sheet.rows.len() + 1
+        );
+        let _ = write!(
+            workbook_rels,
+            "<Relationship Id=\"rId{n}\" Type=\"{RELATIONSHIPS}/worksheet\" \
+             Target=\"worksheets/sheet{n}.xml\"/>"
+        );
+        let (xml, rels) = worksheet(sheet, i == 0);
+        parts.push((format!("xl/worksheets/sheet{n}.xml"), xml));
+        if let Some(rels) = rels {
+            parts.push((format!("xl/worksheets/_rels/sheet{n}.xml.rels"), rels));
+        }
+    }
+    types.push_str("</Types>");
+    let _ = write!(
+        workbook,
+        "</sheets><definedNames>{filters}</definedNames></workbook>"
+    );
+    let _ = write!(
+        workbook_rels,
+        "<Relationship Id=\"rId{}\" Type=\"{RELATIONSHIPS}/styles\" Target=\"styles.xml\"/>\
+         </Relationships>",
+        sheets.len() + 1
+    );
+
+    let mut files = vec![
+        ("[Content_Types].xml", types),
+        (
+            "_rels/.rels",
+            format!(
+                "{HEADER}<Relationships xmlns=\"{PACKAGE_RELATIONSHIPS}\">\
+                 <Relationship Id=\"rId1\" Type=\"{RELATIONSHIPS}/officeDocument\" \
+                 Target=\"xl/workbook.xml\"/></Relationships>"
+            ),
+        ),
+        ("xl/workbook.xml", workbook),
+        ("xl/_rels/workbook.xml.rels", workbook_rels),
+        ("xl/styles.xml", format!("{HEADER}{STYLES}")),
+    ];
+    files.extend(parts.iter().map(|(name, xml)| (name.as_str(), xml.clone())));
+    zip(&files, false)
+}
+
+/// The worksheet XML of `sheet`, and the relationships of its external links.
+fn worksheet(sheet: &Sheet, selected: bool) -> (String, Option<String>) {
+    let mut out = format!("{HEADER}<worksheet xmlns=\"{MAIN}\" xmlns:r=\"{RELATIONSHIPS}\">");
+    // The header row stays in view.
+    let _ = write!(
+        out,
+        "<sheetViews><sheetView workbookViewId=\"0\"{}>\
+         <pane ySplit=\"1\" topLeftCell=\"A2\" activePane=\"bottomLeft\" state=\"frozen\"/>\
+         <selection pane=\"bottomLeft\" activeCell=\"A2\" sqref=\"A2\"/>\
+         </sheetView></sheetViews>",
+        if selected { " tabSelected=\"1\"" } else { "" }
+    );
+    out.push_str("<cols>");
+    for (i, width) in column_widths(sheet).into_iter().enumerate() {
+        let _ = write!(
+            out,
+            "<col min=\"{n}\" max=\"{n}\" width=\"{width}\" customWidth=\"1\"/>",
+            n = i + 1
+        );
+    }
+    out.push_str("</cols><sheetData><row r=\"1\">");
+    for (column, name) in sheet.header.iter().enumerate() {
+        let reference = format!("{}1", column_name(column));
+        text_cell(&mut out, &reference, name, Some(HEADER_STYLE));
+    }
+    out.push_str("</row>");
+
+    let mut hyperlinks = String::new();
+    let mut rels = String::new();
+    for (i, row) in sheet.rows.iter().enumerate() {
+        let r = i + 2;
+        let _ = write!(out, "<row r=\"{r}\">");
+        for (column, cell) in row.iter().enumerate() {
+            let reference = format!("{}{r}", column_name(column));
+            match cell {
+                Cell::Empty => {}
+                Cell::Text(text) => text_cell(&mut out, &reference, text, None),
+                Cell::Number(n) => {
+                    let _ = write!(out, "<c r=\"{reference}\"><v>{n}</v></c>");
+                }
+                Cell::Time(timestamp) => {
+                    // Days since 1899-12-30, the epoch of spreadsheet dates.
+                    let serial = *timestamp as f64 / 86_400.0 + 25_569.0;
+                    let _ = write!(
+                        out,
+                        "<c r=\"{reference}\" s=\"{TIME_STYLE}\"><v>{serial}</v></c>"
+                    );
+                }
+                Cell::Link { text, url } => {
+                    text_cell(&mut out, &reference, text, Some(LINK_STYLE));
+                    let id = format!("rId{}", rels.matches("<Relationship ").count() + 1);
+                    let _ = write!(
+                        rels,
+                        "<Relationship Id=\"{id}\" Type=\"{RELATIONSHIPS}/hyperlink\" \
+                         Target=\"{}\" TargetMode=\"External\"/>",
+                        xml(url)
+                    );
+                    let _ = write!(hyperlinks, "<hyperlink ref=\"{reference}\" r:id=\"{id}\"/>");
+                }
+                Cell::Jump {
+                    text,
+                    sheet,
+                    row: target,
+                } => {
+                    text_cell(&mut out, &reference, text, Some(LINK_STYLE));
+                    let _ = write!(
+                        hyperlinks,
+                        "<hyperlink ref=\"{reference}\" location=\"{sheet}!A{}\" display=\"{}\"/>",
+                        target + 2,
+                        xml(text)
+                    );
+                }
+            }
+        }
+        out.push_str("</row>");
+    }
+    let _ = write!(
+        out,
+        "</sheetData><autoFilter ref=\"A1:{}{}\"/>",
+        column_name(sheet.header.len() - 1),
+        sheet.rows.len() + 1
+    );
+    if !hyperlinks.is_empty() {
+        let _ = write!(out, "<hyperlinks>{hyperlinks}</hyperlinks>");
+    }
+    out.push_str("</worksheet>");
+
+    let rels = (!rels.is_empty()).then(|| {
+        format!("{HEADER}<Relationships xmlns=\"{PACKAGE_RELATIONSHIPS}\">{rels}</Relationships>")
+    });
+    (out, rels)
+}
+
+fn text_cell(out: &mut String, reference: &str, text: &str, style: Option<usize>) {
+    let text: String = text.chars().take(CELL_LIMIT).collect();
+    let style = style.map(|s| format!(" s=\"{s}\"")).unwrap_or_default();
+    let _ = write!(
+        out,
+        "<c r=\"{reference}\" t=\"inlineStr\"{style}><is><t xml:space=\"preserve\">{}</t></is></c>",
+        xml(&text)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{sheets, unzip};
+    use super::*;
+
+    #[test]
+    fn writes_a_workbook_with_frozen_headers_and_links() {
+        let files = unzip(&write_xlsx(&sheets()).unwrap());
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "xl/workbook.xml",
+                "xl/_rels/workbook.xml.rels",
+                "xl/styles.xml",
+                "xl/worksheets/sheet1.xml",
+                "xl/worksheets/sheet2.xml",
+                "xl/worksheets/_rels/sheet2.xml.rels",
+                "xl/worksheets/sheet3.xml",
+                "xl/worksheets/sheet4.xml",
+            ]
+        );
+        let file = |name: &str| &files.iter().find(|(n, _)| n == name).unwrap().1;
+
+        assert!(
+            file("xl/workbook.xml")
+                .contains("<sheet name=\"References\" sheetId=\"2\" r:id=\"rId2\"/>")
+        );
+        let requirements = file("xl/worksheets/sheet1.xml");
+        assert!(requirements.contains(
+            "<pane ySplit=\"1\" topLeftCell=\"A2\" activePane=\"bottomLeft\" state=\"frozen\"/>"
+        ));
+        assert!(requirements.contains(
+            "<c r=\"A1\" t=\"inlineStr\" s=\"1\"><is><t xml:space=\"preserve\">Requirement</t></is></c>"
+        ));
+        assert!(requirements.contains("<c r=\"C3\"><v>1</v></c>"));
+        assert!(
+            requirements
+                .contains("<hyperlink ref=\"A3\" location=\"References!A4\" display=\"REQ-2\"/>")
+        );
+
+        let references = file("xl/worksheets/sheet2.xml");
+        assert!(references.contains("<hyperlink ref=\"B4\" r:id=\"rId1\"/>"));
+        assert!(references.contains("<c r=\"L4\" s=\"3\"><v>45244.9259259259"));
+        assert!(
+            file("xl/worksheets/_rels/sheet2.xml.rels")
+                .contains("Target=\"https://example.com/src/lib.rs#L3\" TargetMode=\"External\"")
+        );
+    }
+}
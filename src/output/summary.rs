@@ -0,0 +1,75 @@
+//! Totals of a run, as the `template` and `envelope` formats report them.
+
+use crate::check::{Severity, Violation, slug_of};
+use crate::diff::{ChangeKind, DiffReport};
+use crate::scan::ScanResult;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize)]
+pub(super) struct Tool {
+    name: &'static str,
+    version: &'static str,
+}
+
+pub(super) const TOOL: Tool = Tool {
+    name: env!("CARGO_PKG_NAME"),
+    version: env!("CARGO_PKG_VERSION"),
+};
+
+#[derive(Default, Serialize)]
+pub(super) struct SlugSummary {
+    requirements: usize,
+    references: usize,
+}
+
+#[derive(Serialize)]
+pub(super) struct Summary<'a> {
+    requirements: usize,
+    references: usize,
+    files: usize,
+    slugs: BTreeMap<&'a str, SlugSummary>,
+    /// Violations by severity, each severity present
+    violations: BTreeMap<&'static str, usize>,
+}
+
+impl<'a> Summary<'a> {
+    pub(super) fn new(results: &'a ScanResult, violations: &[Violation]) -> Self {
+        let mut slugs: BTreeMap<&str, SlugSummary> = BTreeMap::new();
+        for (id, entries) in results {
+            let slug = slugs.entry(slug_of(id)).or_default();
+            slug.requirements += 1;
+            slug.references += entries.len();
+        }
+        let files: BTreeSet<_> = results.values().flatten().map(|e| &e.file).collect();
+        let mut by_severity: BTreeMap<_, _> = [Severity::Note, Severity::Warning, Severity::Error]
+            .map(|severity| (severity.as_str(), 0))
+            .into();
+        for violation in violations {
+            *by_severity.entry(violation.severity.as_str()).or_default() += 1;
+        }
+        Summary {
+            requirements: results.len(),
+            references: results.values().map(Vec::len).sum(),
+            files: files.len(),
+            slugs,
+            violations: by_severity,
+        }
+    }
+}
+
+/// Changes of a diff by kind, each kind present.
+pub(super) fn diff_summary(report: &DiffReport) -> BTreeMap<&'static str, usize> {
+    let mut summary: BTreeMap<_, _> = [
+        ChangeKind::Added,
+        ChangeKind::Removed,
+        ChangeKind::Moved,
+        ChangeKind::ChangedText,
+    ]
+    .map(|kind| (kind.as_str(), 0))
+    .into();
+    for change in &report.changes {
+        *summary.entry(change.kind.as_str()).or_default() += 1;
+    }
+    summary
+}
@@ -0,0 +1,331 @@
+//! The `template` format: output rendered through a Jinja-style template,
+//! either a file or one of the built-in examples.
+
+use super::OutputError;
+use super::summary::{Summary, TOOL, Tool, diff_summary};
+use crate::check::{Violation, slug_of};
+use crate::diff::DiffReport;
+use crate::git::GitMeta;
+use crate::scan::{Entry, ScanResult};
+use minijinja::{Environment, path_loader};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The prefix naming a built-in template rather than a file.
+const BUILTIN_PREFIX: &str = "builtin:";
+
+/// The built-in example templates, by name.
+const BUILTINS: &[(&str, &str)] = &[
+    ("plain", include_str!("templates/plain.txt.j2")),
+    (
+        "markdown-list",
+        include_str!("templates/markdown-list.md.j2"),
+    ),
+    ("asciidoc", include_str!("templates/asciidoc.adoc.j2")),
+];
+
+/// The template of the `template` format: a file, or `builtin:<name>`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub enum TemplateSource {
+    Builtin(String),
+    File(PathBuf),
+}
+
+impl TemplateSource {
+    /// `self` with a relative file resolved against `dir`.
+    pub fn resolve(self, dir: &Path) -> TemplateSource {
+        match self {
+            TemplateSource::File(path) => TemplateSource::File(dir.join(path)),
+            builtin => builtin,
+        }
+    }
+
+    /// The names of the built-in templates.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTINS.iter().map(|(name, _)| *name)
+    }
+}
+
+impl From<String> for TemplateSource {
+    fn from(value: String) -> Self {
+        match value.strip_prefix(BUILTIN_PREFIX) {
+            Some(name) => TemplateSource::Builtin(name.to_string()),
+            None => TemplateSource::File(PathBuf::from(value)),
+        }
+    }
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateSource::Builtin(name) => write!(f, "{BUILTIN_PREFIX}{name}"),
+            TemplateSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Requirement<'a> {
+    id: &'a str,
+    slug: &'a str,
+    references: &'a [Entry],
+}
+
+pub(super) fn format_template(
+    template: Option<&TemplateSource>,
+    meta: Option<&GitMeta>,
+    results: &ScanResult,
+    violations: &[Violation],
+) -> Result<String, OutputError> {
+    #[derive(Serialize)]
+    struct Context<'a> {
+        tool: Tool,
+        meta: Option<&'a GitMeta>,
+        results: &'a ScanResult,
+        requirements: Vec<Requirement<'a>>,
+        violations: &'a [Violation],
+        summary: Summary<'a>,
+    }
+
+    render(
+        template,
+        Context {
+            tool: TOOL,
+            meta,
+            results,
+            requirements: results
+                .iter()
+                .map(|(id, entries)| Requirement {
+                    id,
+                    slug: slug_of(id),
+                    references: entries,
+                })
+                .collect(),
+            violations,
+            summary: Summary::new(results, violations),
+        },
+    )
+}
+
+pub(super) fn format_diff_template(
+    template: Option<&TemplateSource>,
+    report: &DiffReport,
+) -> Result<String, OutputError> {
+    #[derive(Serialize)]
+    struct Context<'a> {
+        tool: Tool,
+        diff: &'a DiffReport,
+        /// Changes by kind
+        summary: BTreeMap<&'static str, usize>,
+    }
+
+    render(
+        template,
+        Context {
+            tool: TOOL,
+            diff: report,
+            summary: diff_summary(report),
+        },
+    )
+}
+
+/// Render `context` through the template. Files may include others beside
+/// them by name.
+fn render(
+    template: Option<&TemplateSource>,
+    context: impl Serialize,
+) -> Result<String, OutputError> {
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+
+    let name = match template.ok_or(OutputError::NoTemplate)? {
+        TemplateSource::Builtin(name) => {
+            let Some((_, source)) = BUILTINS.iter().find(|(builtin, _)| builtin == name) else {
+                return Err(OutputError::UnknownTemplate {
+                    name: name.clone(),
+                    known: TemplateSource::builtin_names()
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                });
+            };
+            let name = format!("{BUILTIN_PREFIX}{name}");
+            env.add_template_owned(name.clone(), *source)?;
+            name
+        }
+        TemplateSource::File(path) => {
+            let source = fs::read_to_string(path).map_err(|source| OutputError::ReadTemplate {
+                path: path.clone(),
+                source,
+            })?;
+            if let Some(dir) = path.parent() {
+                env.set_loader(path_loader(dir));
+            }
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| path.display().to_string());
+            env.add_template_owned(name.clone(), source)?;
+            name
+        }
+    };
+
+    // Printed with a newline of its own, like the other formats.
+    let mut out = env.get_template(&name)?.render(context)?;
+    out.truncate(out.trim_end_matches('\n').len());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::{Rule, Severity};
+    use crate::diff::{Change, ChangeKind};
+
+    fn entry(file: &str, line: usize) -> Entry {
+        Entry {
+            file: PathBuf::from(file),
+            line,
+            comment_text: "// REQ-1".to_string(),
+            above: None,
+            below: None,
+            inline: None,
+            scope: Vec::new(),
+            blame: None,
+            url: None,
+            submodule: None,
+            root: None,
+            component: None,
+        }
+    }
+
+    fn results() -> ScanResult {
+        let mut results = ScanResult::new();
+        results.insert(
+            "REQ-1".to_string(),
+            vec![entry("src/lib.rs", 3), entry("src/main.rs", 7)],
+        );
+        results.insert("SYS-2".to_string(), vec![entry("src/lib.rs", 9)]);
+        results
+    }
+
+    fn template_file(source: &str) -> (tempfile::TempDir, TemplateSource) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.j2");
+        fs::write(&path, source).unwrap();
+        (dir, TemplateSource::File(path))
+    }
+
+    #[test]
+    fn parses_builtin_names_and_paths() {
+        assert_eq!(
+            TemplateSource::from("builtin:plain".to_string()),
+            TemplateSource::Builtin("plain".to_string())
+        );
+        assert_eq!(
+            TemplateSource::from("tpl/report.j2".to_string()).resolve(Path::new("/repo")),
+            TemplateSource::File(PathBuf::from("/repo/tpl/report.j2"))
+        );
+    }
+
+    #[test]
+    fn renders_requirements_summary_and_violations() {
+        let (_dir, template) = template_file(
+            "{{ summary.requirements }}/{{ summary.references }}/{{ summary.files }}\n\
+             {% for slug, counts in summary.slugs | items %}\n\
+             {{ slug }}={{ counts.references }}\n\
+             {% endfor %}\n\
+             {% for requirement in requirements %}\n\
+             {{ requirement.id }}:{{ requirement.references | map(attribute=\"line\") | join(\",\") }}\n\
+             {% endfor %}\n\
+             {{ violations[0].rule }} {{ summary.violations.warning }}\n",
+        );
+        let violations = [Violation {
+            rule: Rule::MinReferences,
+            severity: Severity::Warning,
+            requirement_id: "SYS-2".to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 9,
+            message: String::new(),
+        }];
+
+        let out = format_template(Some(&template), None, &results(), &violations).unwrap();
+        assert_eq!(
+            out,
+            "2/3/2\nREQ=2\nSYS=1\nREQ-1:3,7\nSYS-2:9\n\
+             policy.min_references 1"
+        );
+    }
+
+    #[test]
+    fn includes_templates_beside_the_file() {
+        let (dir, template) = template_file("{% include \"row.j2\" %}");
+        fs::write(dir.path().join("row.j2"), "{{ tool.name }}").unwrap();
+        assert_eq!(
+            format_template(Some(&template), None, &results(), &[]).unwrap(),
+            "tracy"
+        );
+    }
+
+    #[test]
+    fn errors_report_the_template_line() {
+        let (_dir, template) = template_file("ok\n{% for r in requirements %}\n{{ r.id }\n");
+        let err = format_template(Some(&template), None, &results(), &[]).unwrap_err();
+        assert!(err.to_string().contains("(in report.j2:3)"), "{err}");
+
+        let (_dir, template) = template_file("ok\n\n{{ summary.references | nope }}");
+        let err = format_template(Some(&template), None, &results(), &[]).unwrap_err();
+        assert!(err.to_string().contains("unknown filter"), "{err}");
+        assert!(err.to_string().contains("(in report.j2:3)"), "{err}");
+    }
+
+    #[test]
+    fn builtins_render_scans_and_diffs() {
+        let plain = TemplateSource::Builtin("plain".to_string());
+        assert_eq!(
+            format_template(Some(&plain), None, &results(), &[]).unwrap(),
+            "src/lib.rs:3: REQ-1\nsrc/main.rs:7: REQ-1\nsrc/lib.rs:9: SYS-2"
+        );
+
+        let report = DiffReport {
+            base: "main".to_string(),
+            base_sha: "a".repeat(40),
+            head: None,
+            head_sha: None,
+            changes: vec![Change {
+                kind: ChangeKind::Added,
+                requirement_id: "REQ-1".to_string(),
+                base: None,
+                head: Some(entry("src/lib.rs", 3)),
+            }],
+        };
+        assert_eq!(
+            format_diff_template(Some(&plain), &report).unwrap(),
+            "src/lib.rs:3: added REQ-1"
+        );
+
+        for name in TemplateSource::builtin_names() {
+            let template = TemplateSource::Builtin(name.to_string());
+            format_template(Some(&template), None, &results(), &[]).unwrap();
+            format_diff_template(Some(&template), &report).unwrap();
+        }
+    }
+
+    #[test]
+    fn requires_a_known_template() {
+        assert!(matches!(
+            format_template(None, None, &results(), &[]),
+            Err(OutputError::NoTemplate)
+        ));
+        let unknown = TemplateSource::Builtin("nope".to_string());
+        let err = format_template(Some(&unknown), None, &results(), &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown built-in template `nope` (expected one of: plain, markdown-list, asciidoc)"
+        );
+    }
+}
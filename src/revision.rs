@@ -0,0 +1,323 @@
+//! Reading a scan root as of a git revision, without checking it out.
+//!
+//! Files are enumerated from the revision's tree and read straight from the
+//! object database, and `.gitattributes` and `.tracyignore` files are taken
+//! from the same tree, so the working tree is never touched.
+
+use crate::args::{CliOverrides, ScanRoot};
+use crate::component::{
+    Component, component_of, discover_components, layer_components, tag_component,
+};
+use crate::config::{CONFIG_FILES, config_in_with, load_config_with, normalize_path};
+use crate::error::TracyError;
+use crate::filter::{
+    Attributes, AttributesLocation, IGNORE_FILE, IgnoreFiles, RepoAttributes, filter_tree_paths,
+    is_hidden,
+};
+use crate::git::{GitBackend, Submodule, TreeFile, tag_submodules};
+use crate::scan::{ScanArgs, ScanError, ScanResult, merge_results, scan_sources};
+use ast_grep_language::{Language, SupportLang};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Scannable files of a revision, keyed by path relative to the scan root.
+#[derive(Debug)]
+pub struct RevisionSources {
+    /// The resolved commit sha
+    pub sha: String,
+    /// Submodules included with `include_submodules`, at their recorded commits
+    pub submodules: Vec<Submodule>,
+    pub sources: Vec<(PathBuf, String)>,
+}
+
+/// A scan of a revision.
+#[derive(Debug)]
+pub struct RevisionScan {
+    /// The resolved commit sha
+    pub sha: String,
+    pub submodules: Vec<Submodule>,
+    pub results: ScanResult,
+}
+
+/// Collect the contents of every file under `scan_root` at `rev` that passes
+/// the filters of its component and is in a supported language.
+///
+/// With `include_submodules`, the files of each submodule are read from the
+/// submodule's own repository at the commit `rev` records for it.
+pub fn collect_revision_sources(
+    git: GitBackend,
+    scan_root: &Path,
+    rev: &str,
+    components: &[Component],
+) -> Result<RevisionSources, TracyError> {
+    let sha = git.resolve_revision(scan_root, rev)?;
+    let submodules = if components[0].filter.include_submodules {
+        git.revision_submodules(scan_root, &sha)?
+    } else {
+        Vec::new()
+    };
+
+    let repos = std::iter::once((PathBuf::new(), sha.clone())).chain(
+        submodules
+            .iter()
+            .map(|s| (s.path.clone(), s.commit.clone())),
+    );
+    let mut attributes = RepoAttributes::default();
+    let mut trees = Vec::new();
+    for (prefix, commit) in repos {
+        let root = scan_root.join(&prefix);
+        let tree = git.list_tree(&root, &commit)?;
+        attributes.insert(prefix.clone(), tree_attributes(git, &root, &commit, &tree)?);
+        trees.push((prefix, tree));
+    }
+
+    let paths: Vec<PathBuf> = trees
+        .iter()
+        .flat_map(|(prefix, tree)| tree.iter().map(|f| prefix.join(&f.path)))
+        .collect();
+    let ignores = tree_ignores(git, scan_root, &sha, &trees, &paths)?;
+    let mut kept = HashSet::new();
+    for component in components {
+        let paths = paths
+            .iter()
+            .filter(|path| component_of(components, path).dir == component.dir)
+            .cloned()
+            .collect();
+        kept.extend(filter_tree_paths(
+            paths,
+            &attributes,
+            &ignores,
+            &component.filter,
+        )?);
+    }
+
+    let mut sources = Vec::new();
+    for (prefix, tree) in trees {
+        let files: Vec<_> = tree
+            .into_iter()
+            .map(|f| TreeFile {
+                path: prefix.join(f.path),
+                oid: f.oid,
+            })
+            .filter(|f| kept.contains(&f.path) && SupportLang::from_path(&f.path).is_some())
+            .collect();
+        let oids: Vec<&str> = files.iter().map(|f| f.oid.as_str()).collect();
+        let blobs = git.read_blobs(&scan_root.join(&prefix), &oids)?;
+
+        for (file, blob) in files.into_iter().zip(blobs) {
+            let text = blob_text(blob).map_err(|e| ScanError::ReadFile {
+                path: scan_root.join(&file.path),
+                source: e,
+            })?;
+            sources.push((file.path, text));
+        }
+    }
+
+    Ok(RevisionSources {
+        sha,
+        submodules,
+        sources,
+    })
+}
+
+/// Attributes as of `sha`: `.gitattributes` files under the scan root come from
+/// the listed tree, and those of its ancestors up to the repository root are
+/// looked up individually.
+fn tree_attributes(
+    git: GitBackend,
+    scan_root: &Path,
+    sha: &str,
+    tree: &[TreeFile],
+) -> Result<Attributes, TracyError> {
+    let location = AttributesLocation::discover(scan_root);
+
+    let files: Vec<&TreeFile> = tree
+        .iter()
+        .filter(|f| f.path.file_name() == Some(OsStr::new(".gitattributes")))
+        .collect();
+    let oids: Vec<&str> = files.iter().map(|f| f.oid.as_str()).collect();
+    let blobs = git.read_blobs(scan_root, &oids)?;
+    // One that is not UTF-8 is skipped, as it is on disk.
+    let mut contents: HashMap<PathBuf, String> = files
+        .into_iter()
+        .zip(blobs)
+        .filter_map(|(file, blob)| {
+            let dir = file.path.parent().unwrap_or(Path::new(""));
+            Some((location.prefix.join(dir), blob_text(blob).ok()?))
+        })
+        .collect();
+
+    let paths: Vec<PathBuf> = tree.iter().map(|f| f.path.clone()).collect();
+    Attributes::load(&location, &paths, |dir| {
+        if dir.starts_with(&location.prefix) {
+            return Ok(contents.remove(dir));
+        }
+        let path = dir.join(".gitattributes");
+        git.show_file(&location.top, sha, &path.to_string_lossy())
+    })
+    .map_err(TracyError::from)
+}
+
+/// `.tracyignore` files as of `sha`: those under the scan root come from the
+/// listed trees, its own and those of submodules, and those of its ancestors
+/// up to the repository root are looked up individually.
+fn tree_ignores(
+    git: GitBackend,
+    scan_root: &Path,
+    sha: &str,
+    trees: &[(PathBuf, Vec<TreeFile>)],
+    paths: &[PathBuf],
+) -> Result<IgnoreFiles, TracyError> {
+    let location = AttributesLocation::discover(scan_root);
+
+    let mut contents = HashMap::new();
+    for (prefix, tree) in trees {
+        let files: Vec<&TreeFile> = tree
+            .iter()
+            .filter(|f| f.path.file_name() == Some(OsStr::new(IGNORE_FILE)))
+            .collect();
+        let oids: Vec<&str> = files.iter().map(|f| f.oid.as_str()).collect();
+        let blobs = git.read_blobs(&scan_root.join(prefix), &oids)?;
+        for (file, blob) in files.into_iter().zip(blobs) {
+            let dir = prefix.join(file.path.parent().unwrap_or(Path::new("")));
+            contents.insert(location.prefix.join(dir), ignore_text(&blob));
+        }
+    }
+
+    IgnoreFiles::load(&location, paths, |dir| {
+        if dir.starts_with(&location.prefix) {
+            return Ok(contents.remove(dir));
+        }
+        let path = dir.join(IGNORE_FILE);
+        git.show_file(&location.top, sha, &path.to_string_lossy())
+    })
+    .map_err(TracyError::from)
+}
+
+/// The components of `root` as of `rev`, as [`discover_components`] finds
+/// them in the working tree, with their config files, and those they extend
+/// within the repository, read from the revision's tree.
+pub fn revision_components(
+    git: GitBackend,
+    root: &ScanRoot,
+    rev: &str,
+    scan: &ScanArgs,
+    cli: &CliOverrides,
+    enabled: bool,
+) -> Result<Vec<Component>, TracyError> {
+    if !enabled {
+        return discover_components(root, scan, cli, false);
+    }
+    let sha = git.resolve_revision(&root.path, rev)?;
+    let tree = git.list_tree(&root.path, &sha)?;
+    let paths: Vec<PathBuf> = tree.iter().map(|f| f.path.clone()).collect();
+    let ignores = tree_ignores(
+        git,
+        &root.path,
+        &sha,
+        &[(PathBuf::new(), tree.clone())],
+        &paths,
+    )?;
+    let location = AttributesLocation::discover(&root.path);
+
+    let oids: HashMap<PathBuf, String> = tree.into_iter().map(|f| (f.path, f.oid)).collect();
+    // A config file by its path relative to the scan root; outside of the
+    // repository it can only be read from disk.
+    let read = |relative: &Path| -> Result<Option<Vec<u8>>, TracyError> {
+        let relative = normalize_path(relative);
+        if let Some(oid) = oids.get(&relative) {
+            return Ok(Some(git.read_blobs(&root.path, &[oid.as_str()])?.remove(0)));
+        }
+        let path = normalize_path(&location.prefix.join(&relative));
+        if !relative.starts_with("..") {
+            Ok(None)
+        } else if path.starts_with("..") {
+            Ok(fs::read(root.path.join(&relative)).ok())
+        } else {
+            let text = git.show_file(&location.top, &sha, &path.to_string_lossy())?;
+            Ok(text.map(String::into_bytes))
+        }
+    };
+
+    // A config directly in the root is a top-level config, not a component.
+    let dirs: BTreeSet<&Path> = paths
+        .iter()
+        .filter(|path| {
+            path.components().count() > 1
+                && CONFIG_FILES
+                    .iter()
+                    .any(|name| path.file_name() == Some(OsStr::new(name)))
+                && (root.filter.include_hidden || !is_hidden(path))
+                && !ignores.is_ignored(path)
+        })
+        .filter_map(|path| path.parent())
+        .collect();
+    // A file that is not UTF-8 is no config, as on disk.
+    let mut configs = Vec::new();
+    for dir in dirs {
+        let config = config_in_with(dir, |path| {
+            Ok::<_, TracyError>(read(path)?.and_then(|blob| blob_text(blob).ok()))
+        })?;
+        configs.extend(config);
+    }
+    layer_components(root, scan, cli, configs, |path| {
+        load_config_with(&root.path.join(path), &mut |file| {
+            let relative = file.strip_prefix(&root.path).unwrap_or(file);
+            match read(relative) {
+                Ok(Some(blob)) => blob_text(blob),
+                Ok(None) => Err(io::ErrorKind::NotFound.into()),
+                Err(e) => Err(io::Error::other(e.to_string())),
+            }
+        })
+    })
+}
+
+/// A blob's text, failing as `fs::read_to_string` does on a file that is not
+/// UTF-8.
+fn blob_text(blob: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(blob).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })
+}
+
+/// An ignore file's lines up to the first that is not UTF-8, which is as far
+/// as the walker reads one.
+fn ignore_text(blob: &[u8]) -> String {
+    blob.split_inclusive(|&byte| byte == b'\n')
+        .map_while(|line| std::str::from_utf8(line).ok())
+        .collect()
+}
+
+/// Scan `scan_root` as of `rev`, each file with the scan settings of its component.
+pub fn scan_revision(
+    git: GitBackend,
+    scan_root: &Path,
+    rev: &str,
+    components: &[Component],
+) -> Result<RevisionScan, TracyError> {
+    let revision = collect_revision_sources(git, scan_root, rev, components)?;
+    let mut results = ScanResult::new();
+    for component in components {
+        let sources: Vec<(PathBuf, String)> = revision
+            .sources
+            .iter()
+            .filter(|(path, _)| component_of(components, path).dir == component.dir)
+            .cloned()
+            .collect();
+        let mut found = scan_sources(&sources, &component.scan)?;
+        tag_component(component, &mut found);
+        merge_results(found, &mut results);
+    }
+    tag_submodules(&revision.submodules, &mut results);
+    Ok(RevisionScan {
+        sha: revision.sha,
+        submodules: revision.submodules,
+        results,
+    })
+}
@@ -1,10 +1,11 @@
 use clap::Args;
 
-#[derive(Debug, Args)]
+#[derive(Debug, Clone, Args)]
 pub struct ScanArgs {
     #[arg(
         long,
         short = 's',
+        global = true,
         help = "Slug pattern to search for (e.g., 'REQ' matches 'REQ-123'). Can be repeated."
     )]
     pub slug: Vec<String>,
@@ -9,6 +9,12 @@ pub enum ScanError {
         source: std::io::Error,
     },
 
+    #[error("failed to write file {path}: {source}")]
+    WriteFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[error("invalid slug pattern: {0}")]
     InvalidPattern(#[from] regex::Error),
 }
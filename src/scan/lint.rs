@@ -0,0 +1,336 @@
+//! Near-miss detection for requirement markers.
+//!
+//! The scanner only accepts exact `{SLUG}-{NUMBER}` markers. This module looks
+//! for comments that were likely meant to contain one (`REQ 12`, `REQ_12`,
+//! `REQ–12`, `req-12`, `REQ-`) so they can be reported or rewritten.
+
+use super::{ScanArgs, ScanError, is_comment};
+use crate::check::{Rule, Severity, Violation};
+use ast_grep_language::{Language, LanguageExt, SupportLang};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Separators accepted in place of the hyphen: space, tab, underscore,
+/// the unicode hyphen/dash range and the minus sign.
+const SEPARATORS: &str = r"[ \t_\-\u{2010}-\u{2015}\u{2212}]";
+
+/// Hyphen-like separators; only these count as an attempted marker with no number.
+const DASHES: &str = r"[\-\u{2010}-\u{2015}\u{2212}]";
+
+/// A likely-intended marker that does not match the exact marker format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss {
+    /// Relative file path from the scan root
+    pub file: PathBuf,
+    /// 1-indexed line number of the near miss
+    pub line: usize,
+    /// Byte range of the near miss within the file
+    pub start: usize,
+    pub end: usize,
+    /// The text as written
+    pub found: String,
+    /// The corrected marker, when a number was present
+    pub suggestion: Option<String>,
+}
+
+impl NearMiss {
+    pub fn violation(&self) -> Violation {
+        let message = match &self.suggestion {
+            Some(suggestion) => format!(
+                "malformed marker `{}`, did you mean {suggestion}?",
+                self.found
+            ),
+            None => format!("malformed marker `{}` has no number", self.found),
+        };
+        Violation {
+            rule: Rule::MalformedMarker,
+            severity: Severity::Warning,
+            requirement_id: self
+                .suggestion
+                .clone()
+                .unwrap_or_else(|| self.found.clone()),
+            file: self.file.clone(),
+            line: self.line,
+            message,
+        }
+    }
+}
+
+struct SlugMatcher {
+    slug: String,
+    numbered: Regex,
+    missing_number: Regex,
+}
+
+pub fn lint_files(
+    root: &Path,
+    paths: &[PathBuf],
+    args: &ScanArgs,
+) -> Result<Vec<NearMiss>, ScanError> {
+    let matchers = args
+        .slug
+        .iter()
+        .map(|slug| {
+            let escaped = regex::escape(slug);
+            Ok(SlugMatcher {
+                slug: slug.clone(),
+                numbered: Regex::new(&format!(r"(?i)\b{escaped}{SEPARATORS}?(\d+)\b"))?,
+                missing_number: Regex::new(&format!(r"(?im)\b({escaped}{DASHES})(?:$|\W)"))?,
+            })
+        })
+        .collect::<Result<Vec<_>, regex::Error>>()?;
+
+    let mut near_misses = Vec::new();
+    for path in paths {
+        lint_file(root, path, &matchers, &mut near_misses)?;
+    }
+
+    Ok(near_misses)
+}
+
+/// Rewrite every near miss that has a suggestion in place.
+///
+/// Returns the near misses that were fixed.
+pub fn apply_fixes(root: &Path, near_misses: &[NearMiss]) -> Result<Vec<NearMiss>, ScanError> {
+    let mut by_file: BTreeMap<&Path, Vec<&NearMiss>> = BTreeMap::new();
+    for near_miss in near_misses {
+        if near_miss.suggestion.is_some() {
+            by_file.entry(&near_miss.file).or_default().push(near_miss);
+        }
+    }
+
+    let mut fixed = Vec::new();
+    for (file, mut fixes) in by_file {
+        let path = root.join(file);
+        let mut source = fs::read_to_string(&path).map_err(|e| ScanError::ReadFile {
+            path: path.clone(),
+            source: e,
+        })?;
+
+        // Replace back to front so earlier byte offsets stay valid.
+        fixes.sort_by_key(|f| std::cmp::Reverse(f.start));
+        let unchanged = fixed.len();
+        for fix in fixes {
+            if let Some(suggestion) = &fix.suggestion
+                && source.get(fix.start..fix.end) == Some(fix.found.as_str())
+            {
+                source.replace_range(fix.start..fix.end, suggestion);
+                fixed.push(fix.clone());
+            }
+        }
+        // A file whose text has changed since it was linted is left as is.
+        if fixed.len() == unchanged {
+            continue;
+        }
+
+        fs::write(&path, source).map_err(|e| ScanError::WriteFile {
+            path: path.clone(),
+            source: e,
+        })?;
+    }
+
+    fixed.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(fixed)
+}
+
+fn lint_file(
+    root: &Path,
+    path: &Path,
+    matchers: &[SlugMatcher],
+    near_misses: &mut Vec<NearMiss>,
+) -> Result<(), ScanError> {
+    let Some(lang) = SupportLang::from_path(path) else {
+        return Ok(());
+    };
+
+    let source = fs::read_to_string(path).map_err(|e| ScanError::ReadFile {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let ast_root = lang.ast_grep(&source);
+
+    for node in ast_root.root().dfs() {
+        let kind = node.kind();
+        if !is_comment(&kind) {
+            continue;
+        }
+
+        let text = node.text();
+        let offset = node.range().start;
+        let line = node.start_pos().line() + 1;
+
+        for (start, end, suggestion) in find_near_misses(&text, matchers) {
+            near_misses.push(NearMiss {
+                file: relative.to_path_buf(),
+                line: line + text[..start].matches('\n').count(),
+                start: offset + start,
+                end: offset + end,
+                found: text[start..end].to_string(),
+                suggestion,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Find near misses in a single comment, as `(start, end, suggestion)` byte ranges.
+fn find_near_misses(text: &str, matchers: &[SlugMatcher]) -> Vec<(usize, usize, Option<String>)> {
+    let mut found = Vec::new();
+
+    for matcher in matchers {
+        for caps in matcher.numbered.captures_iter(text) {
+            let Some(m) = caps.get(0) else {
+                continue;
+            };
+            let suggestion = format!("{}-{}", matcher.slug, &caps[1]);
+            if m.as_str() != suggestion {
+                found.push((m.start(), m.end(), Some(suggestion)));
+            }
+        }
+
+        for caps in matcher.missing_number.captures_iter(text) {
+            if let Some(m) = caps.get(1) {
+                found.push((m.start(), m.end(), None));
+            }
+        }
+    }
+
+    // Slugs can match overlapping text ("SW-REQ 1" and "REQ 1"); keep the
+    // first of those, so fixes never rewrite the same bytes twice.
+    found.sort();
+    let mut end = 0;
+    found.retain(|&(start, to, _)| {
+        let disjoint = start >= end;
+        if disjoint {
+            end = to;
+        }
+        disjoint
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn lint(ext: &str, content: &str) -> (tempfile::NamedTempFile, Vec<NearMiss>) {
+        let mut file = tempfile::Builder::new().suffix(ext).tempfile().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let root = file.path().parent().unwrap();
+        let args = ScanArgs {
+            slug: vec!["REQ".to_string()],
+        };
+        let near_misses = lint_files(root, &[file.path().to_path_buf()], &args).unwrap();
+        (file, near_misses)
+    }
+
+    fn suggestions(near_misses: &[NearMiss]) -> Vec<Option<&str>> {
+        near_misses
+            .iter()
+            .map(|n| n.suggestion.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn suggests_corrections_for_common_typos() {
+        let (_file, near_misses) = lint(
+            ".rs",
+            "// REQ 12\n// REQ_13\n// REQ\u{2013}14\n// req-15\n// REQ16\n// req42\nfn x() {}",
+        );
+        assert_eq!(
+            suggestions(&near_misses),
+            vec![
+                Some("REQ-12"),
+                Some("REQ-13"),
+                Some("REQ-14"),
+                Some("REQ-15"),
+                Some("REQ-16"),
+                Some("REQ-42")
+            ]
+        );
+        let lines: Vec<usize> = near_misses.iter().map(|n| n.line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn reports_missing_number_without_suggestion() {
+        let (_file, near_misses) = lint(".rs", "// REQ-: todo\n// see REQ-\nfn x() {}");
+        assert_eq!(near_misses.len(), 2);
+        assert_eq!(near_misses[0].found, "REQ-");
+        assert_eq!(suggestions(&near_misses), vec![None, None]);
+
+        // Any case, as for markers with a number.
+        let (_file, near_misses) = lint(".rs", "// req-: todo\nfn x() {}");
+        assert_eq!(near_misses.len(), 1);
+        assert_eq!(near_misses[0].found, "req-");
+    }
+
+    #[test]
+    fn ignores_valid_markers_and_prose() {
+        let (_file, near_misses) = lint(
+            ".rs",
+            "// REQ-1 is fine, requests and REQ-abc are not markers\nfn x() {}",
+        );
+        assert!(near_misses.is_empty(), "{near_misses:?}");
+    }
+
+    #[test]
+    fn ignores_near_misses_outside_comments() {
+        let (_file, near_misses) = lint(".rs", "fn x() { let s = \"REQ 12\"; }");
+        assert!(near_misses.is_empty());
+    }
+
+    #[test]
+    fn reports_line_within_block_comment() {
+        let (_file, near_misses) = lint(".rs", "/*\n * ok\n * REQ 3\n */\nfn x() {}");
+        assert_eq!(near_misses.len(), 1);
+        assert_eq!(near_misses[0].line, 3);
+    }
+
+    #[test]
+    fn overlapping_slugs_fix_once() {
+        let mut file = tempfile::Builder::new().suffix(".rs").tempfile().unwrap();
+        file.write_all(b"// SW-REQ 12\nfn x() {}").unwrap();
+        let root = file.path().parent().unwrap();
+        let args = ScanArgs {
+            slug: vec!["REQ".to_string(), "SW-REQ".to_string()],
+        };
+        let near_misses = lint_files(root, &[file.path().to_path_buf()], &args).unwrap();
+        assert_eq!(suggestions(&near_misses), vec![Some("SW-REQ-12")]);
+
+        apply_fixes(root, &near_misses).unwrap();
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "// SW-REQ-12\nfn x() {}");
+    }
+
+    #[test]
+    fn apply_fixes_rewrites_in_place() {
+        let (file, near_misses) = lint(".rs", "// REQ 1 and req_2\n// REQ-\nfn x() {}");
+        let root = file.path().parent().unwrap();
+
+        let fixed = apply_fixes(root, &near_misses).unwrap();
+        assert_eq!(fixed.len(), 2);
+
+        let content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "// REQ-1 and REQ-2\n// REQ-\nfn x() {}");
+    }
+
+    #[test]
+    fn apply_fixes_leaves_changed_files_untouched() {
+        let (file, near_misses) = lint(".rs", "// REQ 1\nfn x() {}");
+        let root = file.path().parent().unwrap();
+        fs::write(file.path(), "// REQ 2\nfn x() {}").unwrap();
+        let modified = std::time::SystemTime::UNIX_EPOCH;
+        file.as_file().set_modified(modified).unwrap();
+
+        assert!(apply_fixes(root, &near_misses).unwrap().is_empty());
+        let metadata = fs::metadata(file.path()).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+    }
+}
@@ -1,12 +1,14 @@
 pub mod args;
 mod context;
 mod error;
+mod lint;
 
 pub use args::ScanArgs;
 pub use context::{CodeContext, ScopeItem};
 pub use error::ScanError;
+pub use lint::{NearMiss, apply_fixes, lint_files};
 
-use crate::git::BlameInfo;
+use crate::git::{BlameInfo, Submodule};
 use ast_grep_language::{Language, LanguageExt, SupportLang};
 use context::{extract_block_context, extract_hierarchy};
 use regex::Regex;
@@ -40,43 +42,137 @@ pub struct Entry {
     /// Git blame metadata for the marker line
     #[serde(skip_serializing_if = "Option::is_none")]
     pub blame: Option<BlameInfo>,
+
+    /// Forge permalink to the marker line, pinned to the scanned commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// The submodule the file belongs to, with `--include-submodules`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submodule: Option<Submodule>,
+
+    /// The label of the scan root the file was found under, when it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+
+    /// The component config the file was scanned with, when below the scan root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<PathBuf>,
+}
+
+impl Entry {
+    /// Stable fingerprint of the comment text, insensitive to whitespace changes.
+    ///
+    /// 64-bit FNV-1a over the whitespace-normalized text, as 16 hex digits.
+    pub fn fingerprint(&self) -> String {
+        let normalized = self
+            .comment_text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        fnv1a(&normalized)
+    }
+
+    /// Scope hierarchy as a path from outermost to innermost (`outer::Foo::bar`).
+    pub fn scope_path(&self) -> String {
+        self.scope
+            .iter()
+            .rev()
+            .map(|s| match &s.name {
+                Some(name) => name.clone(),
+                None => s.kind.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("::")
+    }
 }
 
 pub type ScanResult = BTreeMap<String, Vec<Entry>>;
 
+/// 64-bit FNV-1a of `text`, as 16 hex digits: the same on every run and
+/// platform, unlike the std hasher.
+pub fn fnv1a(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
 pub fn scan_files(
     root: &Path,
     paths: &[PathBuf],
     args: &ScanArgs,
 ) -> Result<ScanResult, ScanError> {
-    let slugs: Vec<String> = args.slug.iter().map(|s| regex::escape(s)).collect();
-    let pattern = Regex::new(&format!(r"(?:{})-\d+", slugs.join("|")))?;
+    let pattern = marker_pattern(args)?;
     let mut results: ScanResult = BTreeMap::new();
 
     for path in paths {
-        scan_file(root, path, &pattern, &mut results)?;
+        if SupportLang::from_path(path).is_none() {
+            continue;
+        }
+
+        let source = fs::read_to_string(path).map_err(|e| ScanError::ReadFile {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        scan_source(relative, &source, &pattern, &mut results);
     }
 
     Ok(results)
 }
 
-fn scan_file(
-    root: &Path,
-    path: &Path,
-    pattern: &Regex,
-    results: &mut ScanResult,
-) -> Result<(), ScanError> {
-    let Some(lang) = SupportLang::from_path(path) else {
-        return Ok(());
-    };
+/// Scan in-memory sources keyed by their path relative to the scan root.
+///
+/// Used when file contents do not come from the working tree (e.g. git blobs).
+/// Add the entries of `results` to those already in `into`.
+pub fn merge_results(results: ScanResult, into: &mut ScanResult) {
+    for (requirement_id, entries) in results {
+        into.entry(requirement_id).or_default().extend(entries);
+    }
+}
+
+pub fn scan_sources(
+    sources: &[(PathBuf, String)],
+    args: &ScanArgs,
+) -> Result<ScanResult, ScanError> {
+    let pattern = marker_pattern(args)?;
+    let mut results: ScanResult = BTreeMap::new();
+
+    for (relative, source) in sources {
+        scan_source(relative, source, &pattern, &mut results);
+    }
+
+    Ok(results)
+}
+
+/// Why `slug` cannot be a marker slug, if it cannot.
+pub fn slug_error(slug: &str) -> Option<&'static str> {
+    if slug.is_empty() {
+        Some("slugs cannot be empty")
+    } else if slug.chars().any(char::is_whitespace) {
+        Some("slugs cannot contain whitespace")
+    } else if slug.ends_with('-') {
+        Some("markers are `{SLUG}-{NUMBER}`, so slugs cannot end with `-`")
+    } else {
+        None
+    }
+}
 
-    let source = fs::read_to_string(path).map_err(|e| ScanError::ReadFile {
-        path: path.to_path_buf(),
-        source: e,
-    })?;
+fn marker_pattern(args: &ScanArgs) -> Result<Regex, ScanError> {
+    let slugs: Vec<String> = args.slug.iter().map(|s| regex::escape(s)).collect();
+    Ok(Regex::new(&format!(r"(?:{})-\d+", slugs.join("|")))?)
+}
 
-    let relative = path.strip_prefix(root).unwrap_or(path);
-    let ast_root = lang.ast_grep(&source);
+fn scan_source(relative: &Path, source: &str, pattern: &Regex, results: &mut ScanResult) {
+    let Some(lang) = SupportLang::from_path(relative) else {
+        return;
+    };
+
+    let ast_root = lang.ast_grep(source);
     let ast_root_node = ast_root.root();
     let source_lines: Vec<&str> = source.lines().collect();
     let mut seen: HashSet<(String, usize)> = HashSet::new();
@@ -112,12 +208,14 @@ fn scan_file(
                     inline: block_ctx.inline,
                     scope,
                     blame: None,
+                    url: None,
+                    submodule: None,
+                    root: None,
+                    component: None,
                 });
             }
         }
     }
-
-    Ok(())
 }
 
 fn is_comment(kind: &str) -> bool {
@@ -13,7 +13,13 @@ fn run_scan(
     let filter_args = tracy::filter::FilterArgs {
         include_vendored,
         include_generated,
+        include_documentation: false,
         include_submodules: false,
+        include_hidden: false,
+        include_gitignored: false,
+        follow_symlinks: false,
+        max_depth: None,
+        one_file_system: false,
         include: Vec::new(),
         exclude: Vec::new(),
     };